@@ -60,7 +60,7 @@ fn main() {
     pri_header.sequence.set_sequence_type(SeqFlag::Unsegmented);
     pri_header.sequence.set_sequence_count(1);
 
-    // you can also set the packet length with pri_header.set_packet_length(4), which
+    // you can also set the packet length with pri_header.set_packet_length(10), which
     // accounts for the primary header size and the 1 byte minimum size defined in the standard.
     pri_header.length.set_length_field(3);
     
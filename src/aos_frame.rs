@@ -0,0 +1,614 @@
+/*!
+The aos_frame module reads AOS Transfer Frames, as defined in CCSDS 732.0, AOS Space
+Data Link Protocol, and extracts the space packets carried in their Multiplexing
+Protocol Data Units (M_PDUs).
+
+AOS frames have a fixed length for a mission, and are sent on a physical channel
+shared by up to 63 virtual channels, with virtual channel 63 reserved for idle frames.
+The primary header may be protected by a Frame Header Error Control field, a
+Reed-Solomon (10,6) code over GF(16) which can correct up to two 4 bit symbols of the
+master channel ID, virtual channel ID and signalling field.
+
+Packets extracted from frames are checked by a CcsdsParser, using the same checks
+the parser applies to packets in its own byte stream.
+*/
+use std::collections::{BTreeMap, VecDeque};
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+
+use clcw::*;
+use frame::*;
+use parser::*;
+
+
+/// The size of the AOS Transfer Frame primary header, not including the Frame
+/// Header Error Control field.
+pub const AOS_PRI_HEADER_SIZE_BYTES: usize = 6;
+
+/// The size of the Frame Header Error Control field.
+pub const AOS_FHEC_SIZE_BYTES: usize = 2;
+
+/// The transfer frame version number of AOS frames.
+pub const AOS_FRAME_VERSION: u8 = 1;
+
+/// The virtual channel ID of idle frames.
+pub const AOS_IDLE_VCID: u8 = 0x3F;
+
+/// The size of the Operational Control Field.
+pub const AOS_OCF_SIZE_BYTES: usize = 4;
+
+/// The size of the M_PDU header at the start of the data field.
+pub const AOS_MPDU_HEADER_SIZE_BYTES: usize = 2;
+
+/// The first header pointer of an M_PDU in which no packet starts.
+pub const AOS_FHP_NO_PACKET_START: u16 = 0x07FF;
+
+/// The first header pointer of an M_PDU carrying only idle data.
+pub const AOS_FHP_IDLE_DATA: u16 = 0x07FE;
+
+/// The number of values of the virtual channel frame count.
+const AOS_FRAME_COUNT_MODULUS: u32 = 0x0100_0000;
+
+/// Powers of the primitive element of GF(16), with field polynomial x^4 + x + 1.
+const GF16_EXP: [u8; 15] = [1, 2, 4, 8, 3, 6, 12, 11, 5, 10, 7, 14, 15, 13, 9];
+
+/// Logarithms of the nonzero elements of GF(16). The entry for 0 is unused.
+const GF16_LOG: [u8; 16] = [0, 0, 1, 4, 2, 8, 5, 10, 3, 14, 9, 7, 6, 13, 11, 12];
+
+/// The FHEC generator polynomial, (x + a^6)(x + a^7)(x + a^8)(x + a^9), highest power first.
+const FHEC_GENERATOR: [u8; 5] = [1, 8, 2, 8, 1];
+
+/// The power of the primitive element of the first root of the FHEC generator.
+const FHEC_FIRST_ROOT: usize = 6;
+
+/// The number of 4 bit symbols in an FHEC codeword.
+const FHEC_SYMBOLS: usize = 10;
+
+
+/// The primary header of an AOS Transfer Frame, not including the Frame Header Error
+/// Control field. Like the PrimaryHeader of a packet, it is kept as its raw bytes,
+/// with accessors for each field.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AosFrameHeader(pub [u8; 6]);
+
+impl AosFrameHeader {
+    /// Create an AosFrameHeader from a slice. If the slice is not long enough then
+    /// None is returned.
+    pub fn from_slice(bytes: &[u8]) -> Option<AosFrameHeader> {
+        if bytes.len() < AOS_PRI_HEADER_SIZE_BYTES {
+            return None;
+        }
+
+        let mut header = AosFrameHeader::default();
+        header.0.copy_from_slice(&bytes[0..AOS_PRI_HEADER_SIZE_BYTES]);
+        Some(header)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[0] >> 6
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.0[0] = (self.0[0] & 0x3F) | ((version & 0x03) << 6);
+    }
+
+    pub fn spacecraft_id(&self) -> u8 {
+        ((BigEndian::read_u16(&self.0[0..2]) >> 6) & 0x00FF) as u8
+    }
+
+    pub fn set_spacecraft_id(&mut self, spacecraft_id: u8) {
+        let word = (BigEndian::read_u16(&self.0[0..2]) & 0xC03F) | ((spacecraft_id as u16) << 6);
+        BigEndian::write_u16(&mut self.0[0..2], word);
+    }
+
+    pub fn vcid(&self) -> u8 {
+        self.0[1] & 0x3F
+    }
+
+    pub fn set_vcid(&mut self, vcid: u8) {
+        self.0[1] = (self.0[1] & 0xC0) | (vcid & 0x3F);
+    }
+
+    /// The 24 bit virtual channel frame count.
+    pub fn virtual_channel_frame_count(&self) -> u32 {
+        BigEndian::read_u24(&self.0[2..5])
+    }
+
+    pub fn set_virtual_channel_frame_count(&mut self, count: u32) {
+        BigEndian::write_u24(&mut self.0[2..5], count & (AOS_FRAME_COUNT_MODULUS - 1));
+    }
+
+    /// Whether the frame is replayed from storage, rather than sent in real time.
+    pub fn replay_flag(&self) -> bool {
+        (self.0[5] & 0x80) != 0
+    }
+
+    pub fn set_replay_flag(&mut self, flag: bool) {
+        self.0[5] = (self.0[5] & 0x7F) | ((flag as u8) << 7);
+    }
+
+    /// Whether the virtual channel frame count cycle is in use.
+    pub fn frame_count_usage_flag(&self) -> bool {
+        (self.0[5] & 0x40) != 0
+    }
+
+    pub fn set_frame_count_usage_flag(&mut self, flag: bool) {
+        self.0[5] = (self.0[5] & 0xBF) | ((flag as u8) << 6);
+    }
+
+    /// The virtual channel frame count cycle, which counts wraps of the frame count
+    /// when the frame count usage flag is set.
+    pub fn frame_count_cycle(&self) -> u8 {
+        self.0[5] & 0x0F
+    }
+
+    pub fn set_frame_count_cycle(&mut self, cycle: u8) {
+        self.0[5] = (self.0[5] & 0xF0) | (cycle & 0x0F);
+    }
+
+    /// The virtual channel frame count, extended by the frame count cycle if it is in use.
+    pub fn extended_frame_count(&self) -> u32 {
+        if self.frame_count_usage_flag() {
+            ((self.frame_count_cycle() as u32) << 24) | self.virtual_channel_frame_count()
+        } else {
+            self.virtual_channel_frame_count()
+        }
+    }
+
+    /// Compute the Frame Header Error Control field protecting this header.
+    pub fn fhec(&self) -> u16 {
+        let symbols = self.fhec_symbols(0);
+        let mut parity = [0u8; 4];
+
+        for symbol in symbols[0..FHEC_SYMBOLS - parity.len()].iter() {
+            let feedback = symbol ^ parity[0];
+            for index in 0..parity.len() - 1 {
+                parity[index] = parity[index + 1] ^ gf16_mul(feedback, FHEC_GENERATOR[index + 1]);
+            }
+            parity[3] = gf16_mul(feedback, FHEC_GENERATOR[4]);
+        }
+
+        parity.iter().fold(0, |fhec, symbol| (fhec << 4) | *symbol as u16)
+    }
+
+    /// Check this header against a received Frame Header Error Control field,
+    /// correcting up to two 4 bit symbols of the header and FHEC. The number of
+    /// corrected symbols is returned, or None if the header can not be corrected,
+    /// in which case it is left unchanged.
+    pub fn correct(&mut self, fhec: u16) -> Option<usize> {
+        let mut symbols = self.fhec_symbols(fhec);
+        let syndromes = fhec_syndromes(&symbols);
+
+        if syndromes.iter().all(|syndrome| *syndrome == 0) {
+            return Some(0);
+        }
+
+        // with at most two errors, there is one pair of positions whose error values
+        // explain all four syndromes. A single error is a pair with one value of 0.
+        for first in 0..FHEC_SYMBOLS {
+            for second in first + 1..FHEC_SYMBOLS {
+                if let Some((first_error, second_error)) = fhec_error_values(&syndromes, first, second) {
+                    symbols[first] ^= first_error;
+                    symbols[second] ^= second_error;
+
+                    self.0[0] = (symbols[0] << 4) | symbols[1];
+                    self.0[1] = (symbols[2] << 4) | symbols[3];
+                    self.0[5] = (symbols[4] << 4) | symbols[5];
+
+                    return Some((first_error != 0) as usize + (second_error != 0) as usize);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The FHEC codeword of this header with the given FHEC, as 4 bit symbols.
+    fn fhec_symbols(&self, fhec: u16) -> [u8; FHEC_SYMBOLS] {
+        [self.0[0] >> 4, self.0[0] & 0x0F,
+         self.0[1] >> 4, self.0[1] & 0x0F,
+         self.0[5] >> 4, self.0[5] & 0x0F,
+         (fhec >> 12) as u8, ((fhec >> 8) & 0x0F) as u8,
+         ((fhec >> 4) & 0x0F) as u8, (fhec & 0x0F) as u8]
+    }
+}
+
+
+/// An AosFrameConfig gives the managed parameters of an AOS physical channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct AosFrameConfig {
+    /// The length of every frame on the channel, in bytes.
+    pub frame_length: usize,
+
+    /// Whether the primary header is followed by a Frame Header Error Control field.
+    pub has_fhec: bool,
+
+    /// The length of the insert zone following the primary header, or 0 if there is none.
+    pub insert_zone_length: usize,
+
+    /// Whether frames carry an Operational Control Field.
+    pub has_ocf: bool,
+
+    /// Whether frames end with a Frame Error Control Field.
+    pub has_fecf: bool,
+
+    /// The spacecraft whose frames are accepted, or None to accept any spacecraft.
+    pub spacecraft_id: Option<u8>,
+}
+
+impl AosFrameConfig {
+    /// Create a configuration for frames of the given length with a Frame Error
+    /// Control Field, and no FHEC, insert zone or OCF, accepting frames from any spacecraft.
+    pub fn new(frame_length: usize) -> AosFrameConfig {
+        AosFrameConfig {
+            frame_length,
+            has_fhec: false,
+            insert_zone_length: 0,
+            has_ocf: false,
+            has_fecf: true,
+            spacecraft_id: None,
+        }
+    }
+
+    /// The offset of the data field from the start of a frame.
+    pub fn data_field_start(&self) -> usize {
+        let mut start = AOS_PRI_HEADER_SIZE_BYTES + self.insert_zone_length;
+        if self.has_fhec {
+            start += AOS_FHEC_SIZE_BYTES;
+        }
+
+        start
+    }
+
+    /// The offset of the end of the data field from the start of a frame.
+    pub fn data_field_end(&self) -> usize {
+        let mut end = self.frame_length;
+        if self.has_fecf {
+            end = end.saturating_sub(FECF_SIZE_BYTES);
+        }
+
+        if self.has_ocf {
+            end = end.saturating_sub(AOS_OCF_SIZE_BYTES);
+        }
+
+        end
+    }
+}
+
+
+/// An AosFrame is a view of an AOS Transfer Frame in a byte slice, checked against
+/// the channel's configuration. The frame's header is kept separately from its bytes,
+/// as it may have been corrected using the FHEC.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct AosFrame<'a> {
+    bytes: &'a [u8],
+    header: AosFrameHeader,
+    corrected_symbols: usize,
+    config: AosFrameConfig,
+}
+
+impl<'a> AosFrame<'a> {
+    /// Read a frame, checking its length, FHEC, version, spacecraft ID and FECF.
+    /// Errors in the primary header are corrected if the FHEC allows it.
+    pub fn parse(config: &AosFrameConfig, bytes: &'a [u8]) -> Result<AosFrame<'a>, FrameError> {
+        if bytes.len() != config.frame_length {
+            return Err(FrameError::WrongLength { expected: config.frame_length, actual: bytes.len() });
+        }
+
+        if config.data_field_start() > config.data_field_end() {
+            return Err(FrameError::NotEnoughBytes);
+        }
+
+        let mut header = AosFrameHeader::from_slice(bytes).ok_or(FrameError::NotEnoughBytes)?;
+
+        let mut corrected_symbols = 0;
+        if config.has_fhec {
+            let fhec = BigEndian::read_u16(&bytes[AOS_PRI_HEADER_SIZE_BYTES..]);
+            corrected_symbols = header.correct(fhec).ok_or(FrameError::InvalidFhec)?;
+        }
+
+        if header.version() != AOS_FRAME_VERSION {
+            return Err(FrameError::InvalidVersion(header.version()));
+        }
+
+        if let Some(spacecraft_id) = config.spacecraft_id {
+            if header.spacecraft_id() != spacecraft_id {
+                return Err(FrameError::WrongSpacecraft(header.spacecraft_id() as u16));
+            }
+        }
+
+        if config.has_fecf && !fecf_is_valid(bytes) {
+            return Err(FrameError::InvalidFecf);
+        }
+
+        Ok(AosFrame { bytes, header, corrected_symbols, config: *config })
+    }
+
+    /// The frame's primary header, after any correction using the FHEC.
+    pub fn header(&self) -> AosFrameHeader {
+        self.header
+    }
+
+    /// The number of 4 bit symbols of the primary header and FHEC which were corrected.
+    pub fn corrected_symbols(&self) -> usize {
+        self.corrected_symbols
+    }
+
+    /// The bytes of the whole frame, as received.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The frame's insert zone, if the channel has one.
+    pub fn insert_zone(&self) -> Option<&'a [u8]> {
+        if self.config.insert_zone_length == 0 {
+            return None;
+        }
+
+        let start = self.config.data_field_start() - self.config.insert_zone_length;
+        Some(&self.bytes[start..self.config.data_field_start()])
+    }
+
+    /// The frame's data field.
+    pub fn data_field(&self) -> &'a [u8] {
+        &self.bytes[self.config.data_field_start()..self.config.data_field_end()]
+    }
+
+    /// The Operational Control Field, if the channel has one. This usually holds a
+    /// Communications Link Control Word reporting the state of the command link.
+    pub fn ocf(&self) -> Option<u32> {
+        if !self.config.has_ocf {
+            return None;
+        }
+
+        let start = self.config.data_field_end();
+        Some(BigEndian::read_u32(&self.bytes[start..start + AOS_OCF_SIZE_BYTES]))
+    }
+
+    /// The CLCW in the frame's Operational Control Field, if it holds one.
+    pub fn clcw(&self) -> Option<Clcw> {
+        self.ocf().map(Clcw::from).filter(|clcw| clcw.control_word_type() == 0)
+    }
+
+    /// The Frame Error Control Field, if the channel uses one.
+    pub fn fecf(&self) -> Option<u16> {
+        if !self.config.has_fecf {
+            return None;
+        }
+
+        Some(BigEndian::read_u16(&self.bytes[self.bytes.len() - FECF_SIZE_BYTES..]))
+    }
+
+    /// Whether the frame is an idle frame, on the idle virtual channel.
+    pub fn is_idle(&self) -> bool {
+        self.header.vcid() == AOS_IDLE_VCID
+    }
+
+    /// The first header pointer of the M_PDU in the data field, or None if the data
+    /// field is too short to hold an M_PDU header.
+    pub fn mpdu_first_header_pointer(&self) -> Option<u16> {
+        let data_field = self.data_field();
+        if data_field.len() < AOS_MPDU_HEADER_SIZE_BYTES {
+            return None;
+        }
+
+        Some(BigEndian::read_u16(data_field) & 0x07FF)
+    }
+
+    /// The packet zone of the M_PDU in the data field, following the M_PDU header.
+    pub fn mpdu_packet_zone(&self) -> Option<&'a [u8]> {
+        let data_field = self.data_field();
+        if data_field.len() < AOS_MPDU_HEADER_SIZE_BYTES {
+            return None;
+        }
+
+        Some(&data_field[AOS_MPDU_HEADER_SIZE_BYTES..])
+    }
+
+    /// The offset in the M_PDU packet zone of the first packet header, or None if no
+    /// packet starts in the frame.
+    pub fn first_packet_offset(&self) -> Option<usize> {
+        match self.mpdu_first_header_pointer() {
+            Some(AOS_FHP_NO_PACKET_START) | Some(AOS_FHP_IDLE_DATA) | None => None,
+            Some(pointer) => Some(pointer as usize),
+        }
+    }
+}
+
+
+/// The frame count and packet extractor of a virtual channel.
+#[derive(Debug, Clone, Default)]
+struct VirtualChannel {
+    next_frame_count: Option<u32>,
+    extractor: PacketExtractor,
+}
+
+/// An AosFrameDecoder reads the AOS frames of a physical channel and extracts the
+/// packets carried in the M_PDUs of each virtual channel. A gap in a virtual channel's
+/// frame count means frames were lost, so any partial packet on that channel is dropped.
+///
+/// Each extracted packet is checked with the decoder's parser, using
+/// CcsdsParser::packet_status. Packets which are not valid are counted and dropped.
+pub struct AosFrameDecoder {
+    /// The configuration of the physical channel.
+    pub config: AosFrameConfig,
+
+    /// The parser whose configuration and validators are used to check extracted packets.
+    /// Only its checks are used; extracted packets are not added to its bytes.
+    pub parser: CcsdsParser,
+
+    /// The number of frames which could not be read, such as frames with a bad FECF.
+    pub invalid_frames: usize,
+
+    /// The number of frames whose primary header was corrected using the FHEC.
+    pub corrected_headers: usize,
+
+    /// The number of gaps in virtual channel frame counts.
+    pub virtual_channel_gaps: usize,
+
+    /// The number of extracted packets rejected by the parser's checks.
+    pub rejected_packets: usize,
+
+    channels: BTreeMap<u8, VirtualChannel>,
+
+    packets: VecDeque<(u8, BytesMut)>,
+}
+
+impl AosFrameDecoder {
+    /// Create a decoder for a physical channel, checking packets with a default parser.
+    pub fn new(config: AosFrameConfig) -> AosFrameDecoder {
+        AosFrameDecoder::with_parser(config, CcsdsParser::new())
+    }
+
+    /// Create a decoder for a physical channel, checking packets with the given parser.
+    pub fn with_parser(config: AosFrameConfig, parser: CcsdsParser) -> AosFrameDecoder {
+        AosFrameDecoder {
+            config,
+            parser,
+            invalid_frames: 0,
+            corrected_headers: 0,
+            virtual_channel_gaps: 0,
+            rejected_packets: 0,
+            channels: BTreeMap::new(),
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Process a received frame, extracting any packets it completes. Frames that
+    /// can not be read are counted and their error returned. The returned header
+    /// includes any correction made using the FHEC.
+    pub fn recv_frame(&mut self, bytes: &[u8]) -> Result<AosFrameHeader, FrameError> {
+        let frame = match AosFrame::parse(&self.config, bytes) {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.invalid_frames += 1;
+                return Err(err);
+            },
+        };
+
+        if frame.corrected_symbols() > 0 {
+            self.corrected_headers += 1;
+        }
+
+        let header = frame.header();
+        if frame.is_idle() {
+            return Ok(header);
+        }
+
+        let vcid = header.vcid();
+        let channel = self.channels.entry(vcid).or_default();
+
+        let frame_count = header.extended_frame_count();
+        if let Some(expected) = channel.next_frame_count {
+            if expected != frame_count {
+                self.virtual_channel_gaps += 1;
+                channel.extractor.reset();
+            }
+        }
+
+        let modulus = if header.frame_count_usage_flag() {
+            AOS_FRAME_COUNT_MODULUS << 4
+        } else {
+            AOS_FRAME_COUNT_MODULUS
+        };
+        channel.next_frame_count = Some((frame_count + 1) % modulus);
+
+        if frame.mpdu_first_header_pointer() != Some(AOS_FHP_IDLE_DATA) {
+            if let Some(packet_zone) = frame.mpdu_packet_zone() {
+                channel.extractor.recv_data(packet_zone, frame.first_packet_offset());
+            }
+
+            while let Some(packet) = channel.extractor.pull_packet() {
+                if self.parser.packet_status(&packet) == CcsdsParserStatus::ValidPacket {
+                    self.packets.push_back((vcid, packet));
+                } else {
+                    self.rejected_packets += 1;
+                }
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Take the next extracted packet.
+    pub fn pull_packet(&mut self) -> Option<BytesMut> {
+        self.packets.pop_front().map(|(_, packet)| packet)
+    }
+
+    /// Take the next extracted packet, along with the virtual channel it was carried on.
+    pub fn pull_packet_with_vcid(&mut self) -> Option<(u8, BytesMut)> {
+        self.packets.pop_front()
+    }
+
+    /// The packet extractor of a virtual channel, if any frames have been received on it.
+    pub fn extractor(&self, vcid: u8) -> Option<&PacketExtractor> {
+        self.channels.get(&vcid).map(|channel| &channel.extractor)
+    }
+}
+
+
+fn gf16_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+
+    GF16_EXP[(GF16_LOG[a as usize] as usize + GF16_LOG[b as usize] as usize) % 15]
+}
+
+fn gf16_div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+
+    GF16_EXP[(GF16_LOG[a as usize] as usize + 15 - GF16_LOG[b as usize] as usize) % 15]
+}
+
+/// The primitive element raised to a power.
+fn gf16_alpha_pow(power: usize) -> u8 {
+    GF16_EXP[power % 15]
+}
+
+/// The syndromes of an FHEC codeword: the codeword evaluated at each root of the generator.
+/// The first symbol is the coefficient of the highest power.
+fn fhec_syndromes(symbols: &[u8; FHEC_SYMBOLS]) -> [u8; 4] {
+    let mut syndromes = [0u8; 4];
+
+    for (index, syndrome) in syndromes.iter_mut().enumerate() {
+        let root = gf16_alpha_pow(FHEC_FIRST_ROOT + index);
+        *syndrome = symbols.iter().fold(0, |value, symbol| gf16_mul(value, root) ^ symbol);
+    }
+
+    syndromes
+}
+
+/// Find the error values at two symbol positions which explain the syndromes, if any.
+fn fhec_error_values(syndromes: &[u8; 4], first: usize, second: usize) -> Option<(u8, u8)> {
+    // the power of x of each position, as the first symbol is the highest power
+    let first_power = FHEC_SYMBOLS - 1 - first;
+    let second_power = FHEC_SYMBOLS - 1 - second;
+    let locator = |power: usize, root: usize| gf16_alpha_pow(power * root);
+
+    // solve the first two syndrome equations for the two error values
+    let (a, b) = (locator(first_power, FHEC_FIRST_ROOT), locator(second_power, FHEC_FIRST_ROOT));
+    let (c, d) = (locator(first_power, FHEC_FIRST_ROOT + 1), locator(second_power, FHEC_FIRST_ROOT + 1));
+    let determinant = gf16_mul(a, d) ^ gf16_mul(b, c);
+    if determinant == 0 {
+        return None;
+    }
+
+    let first_error = gf16_div(gf16_mul(syndromes[0], d) ^ gf16_mul(syndromes[1], b), determinant);
+    let second_error = gf16_div(gf16_mul(syndromes[1], a) ^ gf16_mul(syndromes[0], c), determinant);
+
+    // the remaining syndromes must agree for these to be the errors
+    for (index, syndrome) in syndromes.iter().enumerate().skip(2) {
+        let root = FHEC_FIRST_ROOT + index;
+        let expected = gf16_mul(first_error, locator(first_power, root)) ^
+                       gf16_mul(second_error, locator(second_power, root));
+        if expected != *syndrome {
+            return None;
+        }
+    }
+
+    Some((first_error, second_error))
+}
@@ -0,0 +1,170 @@
+use primary_header::*;
+
+
+/// The number of distinct APIDs representable in the 11 bit APID field.
+pub const CCSDS_NUM_APIDS: usize = 0x800;
+
+/// The largest APID representable in the 11 bit APID field.
+pub const CCSDS_MAX_APID: u16 = 0x07FF;
+
+/// The number of u64 words needed to hold one bit per APID.
+const APID_SET_WORDS: usize = CCSDS_NUM_APIDS / 64;
+
+
+/// An ApidRule describes a group of APIDs. Rules are added to an ApidFilter
+/// as either allowed or denied APIDs.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ApidRule {
+    /// A single APID.
+    Apid(u16),
+
+    /// An inclusive range of APIDs, from the first to the last APID given.
+    Range(u16, u16),
+
+    /// All APIDs where the APID masked with the mask is equal to the value.
+    /// This is useful when a subsystem ID is encoded in some of the APID bits.
+    Mask {
+        mask: u16,
+        value: u16,
+    },
+}
+
+impl ApidRule {
+    /// Check whether the given APID is covered by this rule.
+    pub fn matches(&self, apid: u16) -> bool {
+        match *self {
+            ApidRule::Apid(rule_apid) => apid == rule_apid,
+            ApidRule::Range(first, last) => apid >= first && apid <= last,
+            ApidRule::Mask { mask, value } => (apid & mask) == (value & mask),
+        }
+    }
+}
+
+
+/// An ApidSet is a bit set with one bit for each possible APID. This gives
+/// a constant time check for whether an APID is in the set.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct ApidSet([u64; APID_SET_WORDS]);
+
+impl ApidSet {
+    fn empty() -> ApidSet {
+        ApidSet([0; APID_SET_WORDS])
+    }
+
+    fn insert(&mut self, apid: u16) {
+        let apid = (apid & CCSDS_MAX_APID) as usize;
+        self.0[apid / 64] |= 1 << (apid % 64);
+    }
+
+    fn contains(&self, apid: u16) -> bool {
+        let apid = (apid & CCSDS_MAX_APID) as usize;
+        (self.0[apid / 64] & (1 << (apid % 64))) != 0
+    }
+
+    fn insert_rule(&mut self, rule: ApidRule) {
+        for apid in 0..(CCSDS_NUM_APIDS as u16) {
+            if rule.matches(apid) {
+                self.insert(apid);
+            }
+        }
+    }
+}
+
+
+/// An ApidFilter determines which APIDs a CcsdsParser will accept.
+///
+/// APIDs are allowed and denied using ApidRules, either for all packets or only
+/// for a particular PacketType. A denied APID is never allowed, regardless of
+/// the order in which rules are added.
+///
+/// Rules are expanded into a table when they are added, so checking an APID
+/// takes constant time no matter how many rules the filter contains.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ApidFilter {
+    /// Allowed APIDs, indexed by packet type (Data, then Command).
+    allowed: [ApidSet; 2],
+
+    /// Denied APIDs, indexed by packet type (Data, then Command).
+    denied: [ApidSet; 2],
+}
+
+impl Default for ApidFilter {
+    fn default() -> ApidFilter {
+        ApidFilter::new()
+    }
+}
+
+impl ApidFilter {
+    /// Create a new filter which does not allow any APIDs.
+    pub fn new() -> ApidFilter {
+        ApidFilter {
+            allowed: [ApidSet::empty(); 2],
+            denied: [ApidSet::empty(); 2],
+        }
+    }
+
+    /// Create a new filter which allows all APIDs. APIDs can then
+    /// be removed with deny rules.
+    pub fn allow_all() -> ApidFilter {
+        let mut filter = ApidFilter::new();
+        filter.allow(ApidRule::Range(0, CCSDS_MAX_APID));
+        filter
+    }
+
+    /// Create a new filter that allows each APID in a list.
+    pub fn from_apids(apids: &[u16]) -> ApidFilter {
+        let mut filter = ApidFilter::new();
+        for apid in apids {
+            filter.allow(ApidRule::Apid(*apid));
+        }
+        filter
+    }
+
+    /// Allow the APIDs covered by a rule for all packet types.
+    pub fn allow(&mut self, rule: ApidRule) {
+        self.allowed[0].insert_rule(rule);
+        self.allowed[1].insert_rule(rule);
+    }
+
+    /// Deny the APIDs covered by a rule for all packet types.
+    pub fn deny(&mut self, rule: ApidRule) {
+        self.denied[0].insert_rule(rule);
+        self.denied[1].insert_rule(rule);
+    }
+
+    /// Allow the APIDs covered by a rule only for packets of the given type.
+    pub fn allow_for(&mut self, packet_type: PacketType, rule: ApidRule) {
+        self.allowed[packet_type_index(packet_type)].insert_rule(rule);
+    }
+
+    /// Deny the APIDs covered by a rule only for packets of the given type.
+    pub fn deny_for(&mut self, packet_type: PacketType, rule: ApidRule) {
+        self.denied[packet_type_index(packet_type)].insert_rule(rule);
+    }
+
+    /// Allow a single APID for all packet types.
+    pub fn allow_apid(&mut self, apid: u16) {
+        self.allowed[0].insert(apid);
+        self.allowed[1].insert(apid);
+    }
+
+    /// Check whether an APID is allowed for a packet of the given type.
+    pub fn is_allowed(&self, packet_type: PacketType, apid: u16) -> bool {
+        let index = packet_type_index(packet_type);
+        self.allowed[index].contains(apid) && !self.denied[index].contains(apid)
+    }
+
+    /// Check whether the APID and packet type of a primary header are allowed.
+    pub fn allows_header(&self, pri_header: &PrimaryHeader) -> bool {
+        self.is_allowed(pri_header.control.packet_type(), pri_header.control.apid())
+    }
+}
+
+/// The packet type field is a single bit, so Unknown is never decoded from
+/// a header. It is treated as a Data packet, matching its encoding.
+fn packet_type_index(packet_type: PacketType) -> usize {
+    match packet_type {
+        PacketType::Command => 1,
+        PacketType::Data | PacketType::Unknown => 0,
+    }
+}
@@ -0,0 +1,255 @@
+/*!
+The cfe module supports the packet headers of NASA's core Flight System (cFS),
+as defined by its core Flight Executive (cFE).
+
+cFE telemetry packets have a 6 byte secondary header holding the time the packet
+was generated. cFE command packets have a 2 byte secondary header holding a function
+code, which selects the command within an application, and a checksum over the
+whole packet.
+*/
+use byteorder::{BigEndian, ByteOrder};
+
+use primary_header::*;
+use secondary_header::*;
+use parser::*;
+use time::*;
+
+
+/// The size of the cFE telemetry secondary header.
+pub const CFE_TLM_SEC_HEADER_BYTES: usize = 6;
+
+/// The size of the cFE command secondary header.
+pub const CFE_CMD_SEC_HEADER_BYTES: usize = 2;
+
+/// The offset of the function code byte from the start of a command packet.
+pub const CFE_FUNCTION_CODE_OFFSET: usize = CCSDS_PRI_HEADER_SIZE_BYTES as usize;
+
+/// The offset of the checksum byte from the start of a command packet.
+pub const CFE_CHECKSUM_OFFSET: usize = CFE_FUNCTION_CODE_OFFSET + 1;
+
+/// The largest function code. The top bit of the function code byte is reserved.
+pub const CFE_MAX_FUNCTION_CODE: u8 = 0x7F;
+
+/// The reason code reported by the parser from cfe::parser when a command packet's
+/// checksum is not valid.
+pub const CFE_CHECKSUM_REJECTED: u32 = 0xCFE0_0001;
+
+
+/// The secondary header of a cFE telemetry packet, holding the time the packet
+/// was generated.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Hash)]
+pub struct CfeTlmHeader {
+    /// Whole seconds since the mission's epoch.
+    pub seconds: u32,
+
+    /// Fractions of a second, in units of 2^-16 seconds.
+    pub subseconds: u16,
+}
+
+impl CfeTlmHeader {
+    /// The header's time as a CUC time, which can be used with cfe::time_format.
+    pub fn time(&self) -> CucTime {
+        CucTime { coarse: self.seconds, fine: (self.subseconds as u32) << 16 }
+    }
+
+    /// Create a header from a CUC time. Fractions of a second are truncated to 16 bits.
+    pub fn from_time(time: &CucTime) -> CfeTlmHeader {
+        CfeTlmHeader { seconds: time.coarse, subseconds: (time.fine >> 16) as u16 }
+    }
+}
+
+impl SecondaryHeader for CfeTlmHeader {
+    type Layout = ();
+
+    fn size(_: &()) -> usize {
+        CFE_TLM_SEC_HEADER_BYTES
+    }
+
+    fn parse(_: &(), bytes: &[u8]) -> Option<CfeTlmHeader> {
+        if bytes.len() < CFE_TLM_SEC_HEADER_BYTES {
+            return None;
+        }
+
+        Some(CfeTlmHeader {
+            seconds: BigEndian::read_u32(&bytes[0..4]),
+            subseconds: BigEndian::read_u16(&bytes[4..6]),
+        })
+    }
+
+    fn write(&self, _: &(), bytes: &mut [u8]) -> Option<usize> {
+        if bytes.len() < CFE_TLM_SEC_HEADER_BYTES {
+            return None;
+        }
+
+        BigEndian::write_u32(&mut bytes[0..4], self.seconds);
+        BigEndian::write_u16(&mut bytes[4..6], self.subseconds);
+        Some(CFE_TLM_SEC_HEADER_BYTES)
+    }
+}
+
+/// The secondary header of a cFE command packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Hash)]
+pub struct CfeCmdHeader {
+    /// The command's function code, from 0 to 127.
+    pub function_code: u8,
+
+    /// The checksum of the command packet.
+    pub checksum: u8,
+}
+
+impl SecondaryHeader for CfeCmdHeader {
+    type Layout = ();
+
+    fn size(_: &()) -> usize {
+        CFE_CMD_SEC_HEADER_BYTES
+    }
+
+    fn parse(_: &(), bytes: &[u8]) -> Option<CfeCmdHeader> {
+        if bytes.len() < CFE_CMD_SEC_HEADER_BYTES {
+            return None;
+        }
+
+        Some(CfeCmdHeader { function_code: bytes[0] & CFE_MAX_FUNCTION_CODE, checksum: bytes[1] })
+    }
+
+    fn write(&self, _: &(), bytes: &mut [u8]) -> Option<usize> {
+        if bytes.len() < CFE_CMD_SEC_HEADER_BYTES || self.function_code > CFE_MAX_FUNCTION_CODE {
+            return None;
+        }
+
+        bytes[0] = self.function_code;
+        bytes[1] = self.checksum;
+        Some(CFE_CMD_SEC_HEADER_BYTES)
+    }
+}
+
+
+/// The CUC format of cFE telemetry times: 4 bytes of seconds and 2 bytes of
+/// subseconds, with no P-field, measured from the mission's epoch.
+pub fn time_format(epoch: Epoch) -> CucFormat {
+    CucFormat::with_epoch(4, 2, epoch)
+}
+
+/// Check whether a packet is a cFE command, with a secondary header and enough
+/// bytes for the command secondary header.
+pub fn is_command(packet: &[u8]) -> bool {
+    match PrimaryHeader::from_slice(packet) {
+        Some(pri_header) => {
+            pri_header.control.packet_type() == PacketType::Command &&
+            pri_header.control.secondary_header_flag() == SecondaryHeaderFlag::Present &&
+            packet.len() > CFE_CHECKSUM_OFFSET
+        },
+
+        None => false,
+    }
+}
+
+/// The function code of a command packet, or None if the packet is not a cFE command.
+pub fn function_code(packet: &[u8]) -> Option<u8> {
+    if !is_command(packet) {
+        return None;
+    }
+
+    Some(packet[CFE_FUNCTION_CODE_OFFSET] & CFE_MAX_FUNCTION_CODE)
+}
+
+/// Set the function code of a command packet. The checksum must be updated afterwards.
+/// Returns false if the packet is not a cFE command or the function code is too large.
+pub fn set_function_code(packet: &mut [u8], function_code: u8) -> bool {
+    if !is_command(packet) || function_code > CFE_MAX_FUNCTION_CODE {
+        return false;
+    }
+
+    packet[CFE_FUNCTION_CODE_OFFSET] = function_code;
+    true
+}
+
+/// The checksum byte of a command packet, or None if the packet is not a cFE command.
+pub fn checksum(packet: &[u8]) -> Option<u8> {
+    if !is_command(packet) {
+        return None;
+    }
+
+    Some(packet[CFE_CHECKSUM_OFFSET])
+}
+
+/// Compute the checksum of a command packet. The checksum is the XOR of 0xFF with
+/// every byte of the packet, taking the checksum byte itself as 0, so that the XOR of
+/// 0xFF with every byte of a packet with a correct checksum is 0.
+///
+/// The packet length is taken from the primary header, and bytes after the packet
+/// are not included. None is returned if the packet is not a complete cFE command.
+pub fn compute_checksum(packet: &[u8]) -> Option<u8> {
+    let packet = command_bytes(packet)?;
+
+    let checksum = packet.iter()
+                         .enumerate()
+                         .filter(|&(index, _)| index != CFE_CHECKSUM_OFFSET)
+                         .fold(0xFF, |checksum, (_, byte)| checksum ^ byte);
+
+    Some(checksum)
+}
+
+/// Compute the checksum of a command packet and write it to the packet's checksum byte.
+/// Returns false if the packet is not a complete cFE command.
+pub fn set_checksum(packet: &mut [u8]) -> bool {
+    match compute_checksum(packet) {
+        Some(checksum) => {
+            packet[CFE_CHECKSUM_OFFSET] = checksum;
+            true
+        },
+
+        None => false,
+    }
+}
+
+/// Check the checksum of a command packet. Packets that are not complete cFE commands
+/// are never valid.
+pub fn is_checksum_valid(packet: &[u8]) -> bool {
+    match command_bytes(packet) {
+        Some(packet) => packet.iter().fold(0xFF, |checksum, byte| checksum ^ byte) == 0,
+        None => false,
+    }
+}
+
+/// A parser configuration for cFE packets. Packets with a secondary header must be
+/// long enough to hold the cFE telemetry or command secondary header.
+pub fn parser_config() -> CcsdsParserConfig {
+    let mut config = CcsdsParserConfig::new();
+    config.secondary_header_sizes.data = Some(CFE_TLM_SEC_HEADER_BYTES as u32);
+    config.secondary_header_sizes.command = Some(CFE_CMD_SEC_HEADER_BYTES as u32);
+    config
+}
+
+/// A parser for cFE packets, using cfe::parser_config, which also rejects command
+/// packets whose checksum is not valid. These are reported as
+/// CcsdsParserStatus::RejectedByValidator(CFE_CHECKSUM_REJECTED).
+pub fn parser() -> CcsdsParser {
+    let mut parser = CcsdsParser::with_config(parser_config());
+
+    parser.add_validator(|pri_header, packet| {
+        if pri_header.control.packet_type() == PacketType::Command &&
+           pri_header.control.secondary_header_flag() == SecondaryHeaderFlag::Present &&
+           !is_checksum_valid(packet) {
+            return Err(CFE_CHECKSUM_REJECTED);
+        }
+
+        Ok(())
+    });
+
+    parser
+}
+
+/// The bytes of a complete cFE command packet, without any following bytes.
+fn command_bytes(packet: &[u8]) -> Option<&[u8]> {
+    if !is_command(packet) {
+        return None;
+    }
+
+    let packet_length = PrimaryHeader::from_slice(packet)?.packet_length() as usize;
+    if packet.len() < packet_length || packet_length <= CFE_CHECKSUM_OFFSET {
+        return None;
+    }
+
+    Some(&packet[0..packet_length])
+}
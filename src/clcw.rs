@@ -0,0 +1,199 @@
+/*!
+The clcw module reads and writes the Communications Link Control Word (CLCW), as
+defined in CCSDS 232.0, TC Space Data Link Protocol. The CLCW is the report of the
+FARM-1 on board, giving the state of a TC virtual channel. It is sent to the ground
+in the Operational Control Field of TM, AOS or USLP frames, where it drives the
+FOP-1 which sends sequence controlled frames.
+*/
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+
+/// The size of a CLCW, which fills the Operational Control Field.
+pub const CLCW_SIZE_BYTES: usize = 4;
+
+/// The CLCW version number.
+pub const CLCW_VERSION: u8 = 0;
+
+/// The COP in effect field when COP-1 is used.
+pub const CLCW_COP_1: u8 = 1;
+
+
+/// The Communications Link Control Word, kept as its raw bytes. An Operational Control
+/// Field may hold another kind of report instead, which has a control word type of 1,
+/// so the clcw accessors of TM, AOS and USLP frames only return a Clcw for type 0.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Clcw(pub [u8; 4]);
+
+impl Clcw {
+    /// Create a CLCW for a virtual channel using COP-1, with all flags clear.
+    pub fn new(vcid: u8) -> Clcw {
+        let mut clcw = Clcw::default();
+        clcw.set_cop_in_effect(CLCW_COP_1);
+        clcw.set_vcid(vcid);
+        clcw
+    }
+
+    /// Create a Clcw from a slice, such as an Operational Control Field. If the slice
+    /// is not long enough then None is returned.
+    pub fn from_slice(bytes: &[u8]) -> Option<Clcw> {
+        if bytes.len() < CLCW_SIZE_BYTES {
+            return None;
+        }
+
+        let mut clcw = Clcw::default();
+        clcw.0.copy_from_slice(&bytes[0..CLCW_SIZE_BYTES]);
+        Some(clcw)
+    }
+
+    /// The control word type, which is 0 for a CLCW.
+    pub fn control_word_type(&self) -> u8 {
+        self.0[0] >> 7
+    }
+
+    pub fn set_control_word_type(&mut self, control_word_type: u8) {
+        self.0[0] = (self.0[0] & 0x7F) | ((control_word_type & 0x01) << 7);
+    }
+
+    pub fn version(&self) -> u8 {
+        (self.0[0] >> 5) & 0x03
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.0[0] = (self.0[0] & 0x9F) | ((version & 0x03) << 5);
+    }
+
+    /// The status field, which is mission specific.
+    pub fn status_field(&self) -> u8 {
+        (self.0[0] >> 2) & 0x07
+    }
+
+    pub fn set_status_field(&mut self, status_field: u8) {
+        self.0[0] = (self.0[0] & 0xE3) | ((status_field & 0x07) << 2);
+    }
+
+    /// The COP in effect, which is 1 for COP-1.
+    pub fn cop_in_effect(&self) -> u8 {
+        self.0[0] & 0x03
+    }
+
+    pub fn set_cop_in_effect(&mut self, cop_in_effect: u8) {
+        self.0[0] = (self.0[0] & 0xFC) | (cop_in_effect & 0x03);
+    }
+
+    /// The virtual channel the report is for.
+    pub fn vcid(&self) -> u8 {
+        self.0[1] >> 2
+    }
+
+    pub fn set_vcid(&mut self, vcid: u8) {
+        self.0[1] = (self.0[1] & 0x03) | ((vcid & 0x3F) << 2);
+    }
+
+    /// Whether the receiver has no RF signal.
+    pub fn no_rf_available(&self) -> bool {
+        (self.0[2] & 0x80) != 0
+    }
+
+    pub fn set_no_rf_available(&mut self, flag: bool) {
+        self.set_flag(0x80, flag);
+    }
+
+    /// Whether the receiver has no bit lock.
+    pub fn no_bit_lock(&self) -> bool {
+        (self.0[2] & 0x40) != 0
+    }
+
+    pub fn set_no_bit_lock(&mut self, flag: bool) {
+        self.set_flag(0x40, flag);
+    }
+
+    /// Whether the FARM-1 is in lockout, rejecting all Type-AD frames until unlocked.
+    pub fn lockout(&self) -> bool {
+        (self.0[2] & 0x20) != 0
+    }
+
+    pub fn set_lockout(&mut self, flag: bool) {
+        self.set_flag(0x20, flag);
+    }
+
+    /// Whether the FARM-1 has no buffer space for further Type-AD frames.
+    pub fn wait(&self) -> bool {
+        (self.0[2] & 0x10) != 0
+    }
+
+    pub fn set_wait(&mut self, flag: bool) {
+        self.set_flag(0x10, flag);
+    }
+
+    /// Whether a Type-AD frame has been rejected, so frames must be sent again.
+    pub fn retransmit(&self) -> bool {
+        (self.0[2] & 0x08) != 0
+    }
+
+    pub fn set_retransmit(&mut self, flag: bool) {
+        self.set_flag(0x08, flag);
+    }
+
+    /// The two lowest bits of the count of Type-B frames accepted.
+    pub fn farm_b_counter(&self) -> u8 {
+        (self.0[2] >> 1) & 0x03
+    }
+
+    pub fn set_farm_b_counter(&mut self, counter: u8) {
+        self.0[2] = (self.0[2] & 0xF9) | ((counter & 0x03) << 1);
+    }
+
+    /// The report value, which is the sequence number of the next Type-AD frame
+    /// expected, V(R) in COP-1.
+    pub fn report_value(&self) -> u8 {
+        self.0[3]
+    }
+
+    pub fn set_report_value(&mut self, report_value: u8) {
+        self.0[3] = report_value;
+    }
+
+    fn set_flag(&mut self, mask: u8, flag: bool) {
+        if flag {
+            self.0[2] |= mask;
+        } else {
+            self.0[2] &= !mask;
+        }
+    }
+}
+
+impl From<u32> for Clcw {
+    fn from(word: u32) -> Clcw {
+        let mut clcw = Clcw::default();
+        BigEndian::write_u32(&mut clcw.0, word);
+        clcw
+    }
+}
+
+impl From<Clcw> for u32 {
+    fn from(clcw: Clcw) -> u32 {
+        BigEndian::read_u32(&clcw.0)
+    }
+}
+
+impl fmt::Display for Clcw {
+    /// Display the link status given by a CLCW, such as "VC 1 V(R) 7 FARM-B 2 wait
+    /// retransmit". Only the flags which are set are shown.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VC {} V(R) {} FARM-B {}", self.vcid(), self.report_value(), self.farm_b_counter())?;
+
+        let flags = [(self.no_rf_available(), "no RF"),
+                     (self.no_bit_lock(), "no bit lock"),
+                     (self.lockout(), "lockout"),
+                     (self.wait(), "wait"),
+                     (self.retransmit(), "retransmit")];
+
+        for &(_, name) in flags.iter().filter(|(set, _)| *set) {
+            write!(f, " {}", name)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+/*!
+The cltu module builds and reads Communications Link Transmission Units (CLTUs), as
+defined in CCSDS 231.0, TC Synchronization and Channel Coding, which carry TC frames
+over the uplink.
+
+A CLTU is a start sequence, followed by the frames split into BCH(63,56) codeblocks,
+followed by a tail sequence. Each codeblock holds 7 bytes of data, padded with fill
+bytes in the last codeblock, and a byte of parity which lets the receiver correct a
+single bit error or detect two. The tail sequence is a codeblock which can not be
+corrected, so the receiver knows the CLTU has ended.
+*/
+use std::collections::VecDeque;
+
+use bytes::BytesMut;
+
+
+/// The start sequence at the start of every CLTU.
+pub const CLTU_START_SEQUENCE: [u8; 2] = [0xEB, 0x90];
+
+/// The tail sequence at the end of every CLTU.
+pub const CLTU_TAIL_SEQUENCE: [u8; 8] = [0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0xC5, 0x79];
+
+/// The size of a BCH codeblock.
+pub const BCH_CODEBLOCK_SIZE_BYTES: usize = 8;
+
+/// The size of the data in a BCH codeblock.
+pub const BCH_INFO_SIZE_BYTES: usize = 7;
+
+/// The byte used to pad the last codeblock of a CLTU.
+pub const CLTU_FILL_BYTE: u8 = 0x55;
+
+/// The generator polynomial x^7 + x^6 + x^2 + 1, without its x^7 term.
+const BCH_GENERATOR: u8 = 0x45;
+
+
+/// The parity byte of a BCH codeblock's 7 bytes of data. This is the complement of
+/// the 7 parity bits, followed by a filler bit of 0.
+pub fn bch_parity(info: &[u8]) -> u8 {
+    (!bch_remainder(info) & 0x7F) << 1
+}
+
+/// Correct a BCH codeblock in place, if it has at most one bit error. Returns the
+/// number of bits corrected, or None if the codeblock has errors which can not be
+/// corrected. The filler bit is not checked.
+pub fn bch_correct(codeblock: &mut [u8]) -> Option<usize> {
+    let syndrome = bch_syndrome(codeblock);
+    if syndrome == 0 {
+        return Some(0);
+    }
+
+    // an error in a parity bit gives a syndrome of that bit alone
+    if syndrome.count_ones() == 1 {
+        codeblock[BCH_INFO_SIZE_BYTES] ^= syndrome << 1;
+        return Some(1);
+    }
+
+    let mut error = [0u8; BCH_INFO_SIZE_BYTES];
+    for bit in 0..BCH_INFO_SIZE_BYTES * 8 {
+        error[bit / 8] = 0x80 >> (bit % 8);
+        if bch_remainder(&error) == syndrome {
+            codeblock[bit / 8] ^= error[bit / 8];
+            codeblock[BCH_INFO_SIZE_BYTES] = bch_parity(&codeblock[0..BCH_INFO_SIZE_BYTES]);
+            return Some(1);
+        }
+        error[bit / 8] = 0;
+    }
+
+    None
+}
+
+/// Build the CLTU carrying one or more frames, given one after another.
+pub fn encode_cltu(frames: &[u8]) -> Vec<u8> {
+    let num_codeblocks = (frames.len() + BCH_INFO_SIZE_BYTES - 1) / BCH_INFO_SIZE_BYTES;
+    let mut cltu = Vec::with_capacity(CLTU_START_SEQUENCE.len() +
+                                      num_codeblocks * BCH_CODEBLOCK_SIZE_BYTES +
+                                      CLTU_TAIL_SEQUENCE.len());
+
+    cltu.extend_from_slice(&CLTU_START_SEQUENCE);
+
+    for chunk in frames.chunks(BCH_INFO_SIZE_BYTES) {
+        let mut info = [CLTU_FILL_BYTE; BCH_INFO_SIZE_BYTES];
+        info[0..chunk.len()].copy_from_slice(chunk);
+
+        cltu.extend_from_slice(&info);
+        cltu.push(bch_parity(&info));
+    }
+
+    cltu.extend_from_slice(&CLTU_TAIL_SEQUENCE);
+    cltu
+}
+
+/// The remainder of a codeblock's data divided by the generator polynomial.
+fn bch_remainder(info: &[u8]) -> u8 {
+    let mut remainder = 0u8;
+
+    for byte in info.iter().take(BCH_INFO_SIZE_BYTES) {
+        for bit in (0..8).rev() {
+            let feedback = ((byte >> bit) ^ (remainder >> 6)) & 1;
+            remainder = (remainder << 1) & 0x7F;
+            if feedback != 0 {
+                remainder ^= BCH_GENERATOR;
+            }
+        }
+    }
+
+    remainder
+}
+
+/// The syndrome of a codeblock, which is 0 if it has no errors.
+fn bch_syndrome(codeblock: &[u8]) -> u8 {
+    let received = !(codeblock[BCH_INFO_SIZE_BYTES] >> 1) & 0x7F;
+    received ^ bch_remainder(&codeblock[0..BCH_INFO_SIZE_BYTES])
+}
+
+
+/// A CltuDecoder finds CLTUs in a stream of bytes, as a spacecraft receiver would,
+/// and gives the data of their codeblocks. The data of a CLTU holds its frames,
+/// followed by any fill bytes, which a frame decoder ignores as each frame's header
+/// gives its length.
+///
+/// The stream is expected to be byte aligned with the start sequence. A CLTU ends at
+/// the first codeblock which can not be corrected, which is normally its tail sequence.
+#[derive(Debug, Clone)]
+pub struct CltuDecoder {
+    /// Whether single bit errors are corrected. If not, a codeblock with any error
+    /// ends the CLTU. Errors are corrected by default.
+    pub correct_errors: bool,
+
+    /// The number of bit errors corrected.
+    pub corrected_bits: usize,
+
+    /// The number of codeblocks other than tail sequences which could not be corrected,
+    /// each ending the CLTU it was in.
+    pub rejected_codeblocks: usize,
+
+    in_cltu: bool,
+
+    buffer: Vec<u8>,
+
+    data: Vec<u8>,
+
+    cltus: VecDeque<BytesMut>,
+}
+
+impl Default for CltuDecoder {
+    fn default() -> CltuDecoder {
+        CltuDecoder::new()
+    }
+}
+
+impl CltuDecoder {
+    /// Create a decoder which starts by searching for a start sequence.
+    pub fn new() -> CltuDecoder {
+        CltuDecoder {
+            correct_errors: true,
+            corrected_bits: 0,
+            rejected_codeblocks: 0,
+            in_cltu: false,
+            buffer: Vec::new(),
+            data: Vec::new(),
+            cltus: VecDeque::new(),
+        }
+    }
+
+    /// Give the decoder the next bytes of the stream, decoding any CLTUs they complete.
+    pub fn recv_slice(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut pos = 0;
+        loop {
+            if !self.in_cltu {
+                let start_length = CLTU_START_SEQUENCE.len();
+                match self.buffer[pos..].windows(start_length).position(|window| window == CLTU_START_SEQUENCE) {
+                    Some(offset) => {
+                        pos += offset + start_length;
+                        self.in_cltu = true;
+                    },
+
+                    None => {
+                        // keep a byte which might start a start sequence
+                        pos = self.buffer.len().saturating_sub(start_length - 1).max(pos);
+                        break;
+                    },
+                }
+            }
+
+            if self.buffer.len() - pos < BCH_CODEBLOCK_SIZE_BYTES {
+                break;
+            }
+
+            let mut codeblock = [0u8; BCH_CODEBLOCK_SIZE_BYTES];
+            codeblock.copy_from_slice(&self.buffer[pos..pos + BCH_CODEBLOCK_SIZE_BYTES]);
+
+            let corrected = if self.correct_errors {
+                bch_correct(&mut codeblock)
+            } else if bch_syndrome(&codeblock) == 0 {
+                Some(0)
+            } else {
+                None
+            };
+
+            match corrected {
+                Some(bits) => {
+                    self.corrected_bits += bits;
+                    self.data.extend_from_slice(&codeblock[0..BCH_INFO_SIZE_BYTES]);
+                    pos += BCH_CODEBLOCK_SIZE_BYTES;
+                },
+
+                None => {
+                    if codeblock == CLTU_TAIL_SEQUENCE {
+                        pos += BCH_CODEBLOCK_SIZE_BYTES;
+                    } else {
+                        self.rejected_codeblocks += 1;
+                    }
+
+                    self.end_cltu();
+                },
+            }
+        }
+
+        self.buffer.drain(0..pos);
+    }
+
+    /// Take the data of the next CLTU, if there is one.
+    pub fn pull_cltu(&mut self) -> Option<BytesMut> {
+        self.cltus.pop_front()
+    }
+
+    /// The number of CLTUs waiting to be pulled.
+    pub fn num_cltus(&self) -> usize {
+        self.cltus.len()
+    }
+
+    /// End the current CLTU, keeping its data if it had any, and search again.
+    fn end_cltu(&mut self) {
+        self.in_cltu = false;
+
+        if !self.data.is_empty() {
+            let data = std::mem::take(&mut self.data);
+            self.cltus.push_back(BytesMut::from(data));
+        }
+    }
+}
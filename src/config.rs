@@ -0,0 +1,410 @@
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "json")]
+use serde_json;
+#[cfg(feature = "toml")]
+use toml;
+
+use primary_header::*;
+use apid_filter::*;
+use parser::*;
+
+
+/// A ConfigError describes why a CcsdsParserConfig could not be loaded, or why
+/// a loaded configuration is not consistent.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConfigError {
+    /// The text could not be parsed as TOML or JSON, or did not have the expected fields.
+    Parse(String),
+
+    /// The sync bytes were not a valid hex string.
+    InvalidSyncBytes(String),
+
+    /// A number was given as a string, but was not a valid decimal or hex number.
+    InvalidNumber(String),
+
+    /// An APID was larger than the 11 bit APID field allows.
+    ApidOutOfRange(u32),
+
+    /// An APID rule did not have exactly one of apid, first/last, or mask/value.
+    InvalidApidRule(String),
+
+    /// A packet type was not "data" or "command".
+    InvalidPacketType(String),
+
+    /// The min packet length was larger than the max packet length.
+    MinLengthExceedsMaxLength {
+        min: u32,
+        max: u32,
+    },
+
+    /// A packet length was below the smallest possible CCSDS packet.
+    LengthBelowCcsdsMinimum(u32),
+
+    /// A packet length was above the largest possible CCSDS packet.
+    LengthAboveCcsdsMaximum(u32),
+
+    /// An APID's expected length did not have exactly one of length, or min and max.
+    InvalidApidLength(u16),
+
+    /// The keep header flag was set, but there are no header bytes to keep.
+    KeepHeaderWithoutHeaderBytes,
+
+    /// The keep footer flag was set, but there are no footer bytes to keep.
+    KeepFooterWithoutFooterBytes,
+
+    /// The keep sync flag was set, but there are no sync bytes to keep.
+    KeepSyncWithoutSyncBytes,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Parse(ref msg) =>
+                write!(f, "could not parse parser configuration: {}", msg),
+            ConfigError::InvalidSyncBytes(ref sync) =>
+                write!(f, "sync bytes '{}' are not a valid hex string", sync),
+            ConfigError::InvalidNumber(ref number) =>
+                write!(f, "'{}' is not a valid number", number),
+            ConfigError::ApidOutOfRange(apid) =>
+                write!(f, "APID 0x{:X} is larger than the maximum APID 0x{:X}", apid, CCSDS_MAX_APID),
+            ConfigError::InvalidApidRule(ref msg) =>
+                write!(f, "invalid APID rule: {}", msg),
+            ConfigError::InvalidPacketType(ref packet_type) =>
+                write!(f, "packet type '{}' is not 'data' or 'command'", packet_type),
+            ConfigError::MinLengthExceedsMaxLength { min, max } =>
+                write!(f, "min packet length {} is larger than max packet length {}", min, max),
+            ConfigError::LengthBelowCcsdsMinimum(length) =>
+                write!(f, "packet length {} is below the CCSDS minimum of {}", length, CCSDS_MIN_LENGTH),
+            ConfigError::LengthAboveCcsdsMaximum(length) =>
+                write!(f, "packet length {} is above the CCSDS maximum of {}", length, CCSDS_MAX_LENGTH),
+            ConfigError::InvalidApidLength(apid) =>
+                write!(f, "length for APID 0x{:X} must give either length, or min and max", apid),
+            ConfigError::KeepHeaderWithoutHeaderBytes =>
+                write!(f, "keep_header is set, but num_header_bytes is 0"),
+            ConfigError::KeepFooterWithoutFooterBytes =>
+                write!(f, "keep_footer is set, but num_footer_bytes is 0"),
+            ConfigError::KeepSyncWithoutSyncBytes =>
+                write!(f, "keep_sync is set, but there are no sync bytes"),
+        }
+    }
+}
+
+impl Error for ConfigError {
+}
+
+
+/// A number in a configuration file. TOML supports hex integers directly, but
+/// JSON does not, so numbers may also be given as strings such as "0x7FF".
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigNumber {
+    Int(u32),
+    Text(String),
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl ConfigNumber {
+    fn value(&self) -> Result<u32, ConfigError> {
+        match *self {
+            ConfigNumber::Int(value) => Ok(value),
+            ConfigNumber::Text(ref text) => {
+                let trimmed = text.trim();
+                let result =
+                    if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
+                        u32::from_str_radix(&trimmed[2..], 16)
+                    } else {
+                        trimmed.parse::<u32>()
+                    };
+
+                result.map_err(|_| ConfigError::InvalidNumber(text.clone()))
+            },
+        }
+    }
+
+    fn apid(&self) -> Result<u16, ConfigError> {
+        let value = self.value()?;
+        if value > CCSDS_MAX_APID as u32 {
+            return Err(ConfigError::ApidOutOfRange(value));
+        }
+        Ok(value as u16)
+    }
+}
+
+/// An APID rule in a configuration file. Exactly one of 'apid', 'first' and 'last',
+/// or 'mask' and 'value' must be given. The rule applies to all packets unless
+/// a packet type is given.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApidRuleEntry {
+    apid: Option<ConfigNumber>,
+    first: Option<ConfigNumber>,
+    last: Option<ConfigNumber>,
+    mask: Option<ConfigNumber>,
+    value: Option<ConfigNumber>,
+    packet_type: Option<String>,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl ApidRuleEntry {
+    fn rule(&self) -> Result<ApidRule, ConfigError> {
+        match (&self.apid, &self.first, &self.last, &self.mask, &self.value) {
+            (Some(apid), None, None, None, None) => {
+                Ok(ApidRule::Apid(apid.apid()?))
+            },
+
+            (None, Some(first), Some(last), None, None) => {
+                let (first, last) = (first.apid()?, last.apid()?);
+                if first > last {
+                    return Err(ConfigError::InvalidApidRule(
+                            format!("range start 0x{:X} is after range end 0x{:X}", first, last)));
+                }
+                Ok(ApidRule::Range(first, last))
+            },
+
+            (None, None, None, Some(mask), Some(value)) => {
+                Ok(ApidRule::Mask { mask: mask.apid()?, value: value.apid()? })
+            },
+
+            _ => {
+                Err(ConfigError::InvalidApidRule(
+                        "expected one of 'apid', 'first' and 'last', or 'mask' and 'value'".to_string()))
+            },
+        }
+    }
+
+    fn packet_type(&self) -> Result<Option<PacketType>, ConfigError> {
+        match self.packet_type {
+            None => Ok(None),
+            Some(ref packet_type) => {
+                match packet_type.to_lowercase().as_str() {
+                    "data" | "telemetry" => Ok(Some(PacketType::Data)),
+                    "command" => Ok(Some(PacketType::Command)),
+                    _ => Err(ConfigError::InvalidPacketType(packet_type.clone())),
+                }
+            },
+        }
+    }
+}
+
+/// An expected length for an APID in a configuration file. Either 'length',
+/// or 'min' and 'max', must be given.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApidLengthEntry {
+    apid: ConfigNumber,
+    length: Option<u32>,
+    min: Option<u32>,
+    max: Option<u32>,
+}
+
+/// The secondary header sizes in a configuration file, by APID or by packet type.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct SecondaryHeaderSizesEntry {
+    apids: Vec<ApidSizeEntry>,
+    data: Option<u32>,
+    command: Option<u32>,
+}
+
+/// The secondary header size of an APID in a configuration file.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApidSizeEntry {
+    apid: ConfigNumber,
+    size: u32,
+}
+
+/// The layout of a configuration file. Every field is optional, and defaults
+/// to the value used by CcsdsParserConfig::new.
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    allowed_apids: Option<Vec<ApidRuleEntry>>,
+    denied_apids: Vec<ApidRuleEntry>,
+    max_packet_length: Option<u32>,
+    min_packet_length: Option<u32>,
+    apid_lengths: Vec<ApidLengthEntry>,
+    secondary_header_required: bool,
+    secondary_header_sizes: SecondaryHeaderSizesEntry,
+    sync_bytes: String,
+    keep_sync: bool,
+    num_header_bytes: u32,
+    keep_header: bool,
+    num_footer_bytes: u32,
+    keep_footer: bool,
+    little_endian_header: bool,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl ConfigFile {
+    fn into_config(self) -> Result<CcsdsParserConfig, ConfigError> {
+        let mut config = CcsdsParserConfig::new();
+
+        if self.allowed_apids.is_some() || !self.denied_apids.is_empty() {
+            // with only deny rules, every other APID is allowed
+            let mut filter = match self.allowed_apids {
+                Some(_) => ApidFilter::new(),
+                None => ApidFilter::allow_all(),
+            };
+
+            for entry in self.allowed_apids.iter().flat_map(|entries| entries.iter()) {
+                match entry.packet_type()? {
+                    Some(packet_type) => filter.allow_for(packet_type, entry.rule()?),
+                    None => filter.allow(entry.rule()?),
+                }
+            }
+
+            for entry in self.denied_apids.iter() {
+                match entry.packet_type()? {
+                    Some(packet_type) => filter.deny_for(packet_type, entry.rule()?),
+                    None => filter.deny(entry.rule()?),
+                }
+            }
+
+            config.allowed_apids = Some(filter);
+        }
+
+        for entry in self.apid_lengths.iter() {
+            let apid = entry.apid.apid()?;
+            let expected_length =
+                match (entry.length, entry.min, entry.max) {
+                    (Some(length), None, None) => ExpectedLength::Exact(length),
+                    (None, Some(min), Some(max)) => ExpectedLength::Range { min, max },
+                    _ => return Err(ConfigError::InvalidApidLength(apid)),
+                };
+            config.apid_lengths.insert(apid, expected_length);
+        }
+
+        for entry in self.secondary_header_sizes.apids.iter() {
+            config.secondary_header_sizes.apids.insert(entry.apid.apid()?, entry.size);
+        }
+        config.secondary_header_sizes.data = self.secondary_header_sizes.data;
+        config.secondary_header_sizes.command = self.secondary_header_sizes.command;
+
+        config.max_packet_length = self.max_packet_length;
+        config.min_packet_length = self.min_packet_length;
+        config.secondary_header_required = self.secondary_header_required;
+        config.sync_bytes = parse_hex_bytes(&self.sync_bytes)?;
+        config.keep_sync = self.keep_sync;
+        config.num_header_bytes = self.num_header_bytes;
+        config.keep_header = self.keep_header;
+        config.num_footer_bytes = self.num_footer_bytes;
+        config.keep_footer = self.keep_footer;
+        config.little_endian_header = self.little_endian_header;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+/// Parse a hex string such as "EB90", "0xEB90" or "EB 90" into bytes.
+#[cfg(any(feature = "toml", feature = "json"))]
+fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, ConfigError> {
+    let mut digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.starts_with("0x") || digits.starts_with("0X") {
+        digits = digits[2..].to_string();
+    }
+
+    if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ConfigError::InvalidSyncBytes(text.to_string()));
+    }
+
+    Ok((0..digits.len()).step_by(2)
+                        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap())
+                        .collect())
+}
+
+/// Check that a packet length is possible for a CCSDS packet.
+fn check_length(length: u32) -> Result<(), ConfigError> {
+    if length < CCSDS_MIN_LENGTH {
+        Err(ConfigError::LengthBelowCcsdsMinimum(length))
+    } else if length > CCSDS_MAX_LENGTH {
+        Err(ConfigError::LengthAboveCcsdsMaximum(length))
+    } else {
+        Ok(())
+    }
+}
+
+impl CcsdsParserConfig {
+    /// Load a parser configuration from a TOML string. Sync bytes are given as
+    /// a hex string, and APIDs may be given as integers or hex strings.
+    /// The resulting configuration is checked with validate.
+    /// This requires the toml feature.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(text: &str) -> Result<CcsdsParserConfig, ConfigError> {
+        let config_file: ConfigFile =
+            toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+
+        config_file.into_config()
+    }
+
+    /// Load a parser configuration from a JSON string. The layout is the same as
+    /// for from_toml_str.
+    /// The resulting configuration is checked with validate.
+    /// This requires the json feature.
+    #[cfg(feature = "json")]
+    pub fn from_json_str(text: &str) -> Result<CcsdsParserConfig, ConfigError> {
+        let config_file: ConfigFile =
+            serde_json::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+
+        config_file.into_config()
+    }
+
+    /// Check that a configuration is consistent. This catches settings that could
+    /// never match a packet, such as a min packet length above the max packet length,
+    /// as well as flags that have no effect, such as keeping a header with no bytes.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(min) = self.min_packet_length {
+            check_length(min)?;
+        }
+
+        if let Some(max) = self.max_packet_length {
+            check_length(max)?;
+        }
+
+        if let (Some(min), Some(max)) = (self.min_packet_length, self.max_packet_length) {
+            if min > max {
+                return Err(ConfigError::MinLengthExceedsMaxLength { min, max });
+            }
+        }
+
+        for expected_length in self.apid_lengths.values() {
+            match *expected_length {
+                ExpectedLength::Exact(length) => {
+                    check_length(length)?;
+                },
+
+                ExpectedLength::Range { min, max } => {
+                    check_length(min)?;
+                    check_length(max)?;
+                    if min > max {
+                        return Err(ConfigError::MinLengthExceedsMaxLength { min, max });
+                    }
+                },
+            }
+        }
+
+        if self.keep_header && self.num_header_bytes == 0 {
+            return Err(ConfigError::KeepHeaderWithoutHeaderBytes);
+        }
+
+        if self.keep_footer && self.num_footer_bytes == 0 {
+            return Err(ConfigError::KeepFooterWithoutFooterBytes);
+        }
+
+        if self.keep_sync && self.sync_bytes.is_empty() {
+            return Err(ConfigError::KeepSyncWithoutSyncBytes);
+        }
+
+        Ok(())
+    }
+}
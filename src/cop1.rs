@@ -0,0 +1,691 @@
+/*!
+The cop1 module implements COP-1, the Communications Operation Procedure of CCSDS 232.1,
+which delivers sequence controlled (Type-AD) TC frames to a spacecraft in order and
+without gaps.
+
+The FOP-1 on the ground numbers Type-AD frames, keeps each frame until it has been
+acknowledged, and sends frames again when the FARM-1 on board asks for them or when
+its timer runs out. The FARM-1 accepts Type-AD frames only in sequence, and reports
+its state in a CLCW which is sent back to the FOP-1.
+
+Neither side keeps time or sends anything itself. Each is driven by calls giving the
+events of the protocol, with the current time given explicitly, and queues the frames
+it wants sent to be pulled out. This lets the procedure be run deterministically.
+*/
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use clcw::*;
+use frame::*;
+use tc_frame::*;
+
+
+/// The data of the Unlock control command.
+pub const COP1_UNLOCK_COMMAND: [u8; 1] = [0x00];
+
+/// The start of the Set V(R) control command, which is followed by the new V(R).
+pub const COP1_SET_VR_COMMAND: [u8; 2] = [0x82, 0x00];
+
+
+/// A ControlCommand is carried in a Type-BC frame to control the FARM-1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ControlCommand {
+    /// Leave the lockout state, clearing the wait and retransmit flags.
+    Unlock,
+
+    /// Set the sequence number of the next Type-AD frame expected, V(R).
+    SetVr(u8),
+}
+
+impl ControlCommand {
+    /// The data field of the frame carrying the command.
+    pub fn encode(&self) -> Vec<u8> {
+        match *self {
+            ControlCommand::Unlock => COP1_UNLOCK_COMMAND.to_vec(),
+            ControlCommand::SetVr(vr) => vec![COP1_SET_VR_COMMAND[0], COP1_SET_VR_COMMAND[1], vr],
+        }
+    }
+
+    /// Read a command from the data field of a Type-BC frame. If the data is not a
+    /// command then None is returned.
+    pub fn decode(data: &[u8]) -> Option<ControlCommand> {
+        match data {
+            [0x00] => Some(ControlCommand::Unlock),
+            [0x82, 0x00, vr] => Some(ControlCommand::SetVr(*vr)),
+            _ => None,
+        }
+    }
+}
+
+
+/// A CopError describes why a request to the FOP-1 was rejected.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CopError {
+    /// The FOP-1 is not in a state which allows the request.
+    InvalidState(FopState),
+
+    /// The sent queue and wait queue are full. The data can be given again once
+    /// more frames have been acknowledged.
+    Busy,
+
+    /// The frame carrying the data could not be built.
+    Frame(FrameError),
+}
+
+impl From<FrameError> for CopError {
+    fn from(err: FrameError) -> CopError {
+        CopError::Frame(err)
+    }
+}
+
+
+/// The FarmState is the state of a FARM-1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FarmState {
+    /// Type-AD frames are accepted in sequence.
+    Open,
+
+    /// There is no buffer space for Type-AD frames, so they are discarded.
+    Wait,
+
+    /// A Type-AD frame outside the sliding window was received, and all Type-AD
+    /// frames are discarded until an Unlock command.
+    Lockout,
+}
+
+/// The FarmAction is what the FARM-1 did with a frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FarmAction {
+    /// The frame was accepted. Accepted Type-AD and Type-BD frames should be passed on
+    /// for packet extraction. Accepted control commands have been carried out.
+    Accept,
+
+    /// The frame was discarded.
+    Discard,
+}
+
+
+/// A Farm is the FARM-1 of a virtual channel on board, which decides which frames
+/// to accept and reports its state in a CLCW.
+#[derive(Debug, Clone)]
+pub struct Farm {
+    /// The virtual channel of the frames accepted.
+    pub vcid: u8,
+
+    /// The width of the sliding window, W, an even number from 2 to 254. Type-AD frames
+    /// less than half the window ahead of the expected frame ask for a retransmission,
+    /// and those up to half the window behind are discarded. Frames outside the window
+    /// cause a lockout.
+    pub window_width: u8,
+
+    /// The no RF available flag reported in the CLCW.
+    pub no_rf_available: bool,
+
+    /// The no bit lock flag reported in the CLCW.
+    pub no_bit_lock: bool,
+
+    receiver_sequence_number: u8,
+
+    farm_b_counter: u8,
+
+    lockout: bool,
+
+    wait: bool,
+
+    retransmit: bool,
+
+    buffer_available: bool,
+}
+
+impl Farm {
+    /// Create a FARM-1 in the open state, expecting a Type-AD frame numbered 0.
+    pub fn new(vcid: u8, window_width: u8) -> Farm {
+        Farm {
+            vcid,
+            window_width,
+            no_rf_available: false,
+            no_bit_lock: false,
+            receiver_sequence_number: 0,
+            farm_b_counter: 0,
+            lockout: false,
+            wait: false,
+            retransmit: false,
+            buffer_available: true,
+        }
+    }
+
+    /// The current state of the FARM-1.
+    pub fn state(&self) -> FarmState {
+        if self.lockout {
+            FarmState::Lockout
+        } else if self.wait {
+            FarmState::Wait
+        } else {
+            FarmState::Open
+        }
+    }
+
+    /// The sequence number of the next Type-AD frame expected, V(R).
+    pub fn next_sequence_number(&self) -> u8 {
+        self.receiver_sequence_number
+    }
+
+    /// Whether there is buffer space for Type-AD frames.
+    pub fn buffer_available(&self) -> bool {
+        self.buffer_available
+    }
+
+    /// Give whether there is buffer space for Type-AD frames. Once space is released,
+    /// the wait flag is cleared.
+    pub fn set_buffer_available(&mut self, available: bool) {
+        self.buffer_available = available;
+        if available {
+            self.wait = false;
+        }
+    }
+
+    /// The CLCW reporting the state of the FARM-1.
+    pub fn clcw(&self) -> Clcw {
+        let mut clcw = Clcw::new(self.vcid);
+        clcw.set_no_rf_available(self.no_rf_available);
+        clcw.set_no_bit_lock(self.no_bit_lock);
+        clcw.set_lockout(self.lockout);
+        clcw.set_wait(self.wait);
+        clcw.set_retransmit(self.retransmit);
+        clcw.set_farm_b_counter(self.farm_b_counter);
+        clcw.set_report_value(self.receiver_sequence_number);
+        clcw
+    }
+
+    /// Process a frame which has passed the frame checks, deciding whether it is accepted.
+    /// Frames for other virtual channels are discarded.
+    pub fn recv_frame(&mut self, frame: &TcFrame) -> FarmAction {
+        let header = frame.header();
+        if header.vcid() != self.vcid {
+            return FarmAction::Discard;
+        }
+
+        match header.frame_type() {
+            TcFrameType::AD => self.recv_ad_frame(header.sequence_number()),
+
+            TcFrameType::BD => {
+                self.farm_b_counter = self.farm_b_counter.wrapping_add(1);
+                FarmAction::Accept
+            },
+
+            TcFrameType::BC => match ControlCommand::decode(frame.data()) {
+                Some(ControlCommand::Unlock) => {
+                    self.farm_b_counter = self.farm_b_counter.wrapping_add(1);
+                    self.lockout = false;
+                    self.wait = false;
+                    self.retransmit = false;
+                    FarmAction::Accept
+                },
+
+                Some(ControlCommand::SetVr(vr)) => {
+                    self.farm_b_counter = self.farm_b_counter.wrapping_add(1);
+                    if !self.lockout {
+                        self.wait = false;
+                        self.retransmit = false;
+                        self.receiver_sequence_number = vr;
+                    }
+                    FarmAction::Accept
+                },
+
+                None => FarmAction::Discard,
+            },
+
+            TcFrameType::Invalid => FarmAction::Discard,
+        }
+    }
+
+    fn recv_ad_frame(&mut self, sequence_number: u8) -> FarmAction {
+        let half_window = self.window_width / 2;
+        let ahead = sequence_number.wrapping_sub(self.receiver_sequence_number);
+        let behind = self.receiver_sequence_number.wrapping_sub(sequence_number);
+
+        if self.lockout {
+            return FarmAction::Discard;
+        }
+
+        if ahead == 0 {
+            if self.wait {
+                self.retransmit = true;
+                return FarmAction::Discard;
+            }
+
+            if !self.buffer_available {
+                self.retransmit = true;
+                self.wait = true;
+                return FarmAction::Discard;
+            }
+
+            self.receiver_sequence_number = self.receiver_sequence_number.wrapping_add(1);
+            self.retransmit = false;
+            return FarmAction::Accept;
+        }
+
+        if ahead < half_window {
+            // a frame has been lost, so ask for the frames from V(R) again
+            self.retransmit = true;
+        } else if behind > half_window {
+            self.lockout = true;
+        }
+
+        FarmAction::Discard
+    }
+}
+
+
+/// The FopState is the state of a FOP-1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FopState {
+    /// Type-AD frames are being sent.
+    Active,
+
+    /// The FARM-1 asked for frames to be sent again, and they have been.
+    RetransmitWithoutWait,
+
+    /// The FARM-1 asked for frames to be sent again, but has no buffer space, so they
+    /// are held until it does.
+    RetransmitWithWait,
+
+    /// Waiting for a CLCW confirming the FARM-1 is ready before becoming active.
+    InitializingWithoutBc,
+
+    /// Waiting for the FARM-1 to carry out a control command before becoming active.
+    InitializingWithBc,
+
+    /// The service is not running, and no Type-AD frames are sent.
+    Initial,
+}
+
+/// A FopAlert reports that the FOP-1 has stopped, returning to its initial state.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FopAlert {
+    /// The FARM-1 asked for a retransmission after the transmission limit was reached.
+    Limit,
+
+    /// The timer ran out after the transmission limit was reached, or while
+    /// initializing.
+    Timer,
+
+    /// The FARM-1 reported it is in lockout.
+    Lockout,
+
+    /// A CLCW was not a COP-1 report, or acknowledged frames which were never sent.
+    InvalidReport,
+
+    /// The service was terminated.
+    Terminated,
+}
+
+
+/// The FopConfig gives the managed parameters of a FOP-1.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FopConfig {
+    /// The virtual channel the frames are sent on.
+    pub vcid: u8,
+
+    /// The MAP ID given to frames, if the channel uses segment headers.
+    pub map_id: u8,
+
+    /// The largest number of frames which may be waiting for acknowledgement, K.
+    pub window_width: u8,
+
+    /// The largest number of times a frame is sent, including its first transmission.
+    pub transmission_limit: usize,
+
+    /// The time after a transmission when frames not yet acknowledged are sent again, T1.
+    pub timeout: Duration,
+}
+
+impl FopConfig {
+    /// Create a configuration for a virtual channel, with a window of 10 frames, each
+    /// sent at most 3 times, 5 seconds apart.
+    pub fn new(vcid: u8) -> FopConfig {
+        FopConfig {
+            vcid,
+            map_id: 0,
+            window_width: 10,
+            transmission_limit: 3,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+
+/// A Fop is the FOP-1 of a virtual channel on the ground. It numbers Type-AD frames with
+/// the sequence numbers of its TcFrameEncoder, keeps them until the FARM-1 acknowledges
+/// them, and sends them again when needed.
+#[derive(Debug, Clone)]
+pub struct Fop {
+    /// The configuration of the FOP-1.
+    pub config: FopConfig,
+
+    /// The encoder which builds the frames, and keeps V(S).
+    pub encoder: TcFrameEncoder,
+
+    state: FopState,
+
+    sent_queue: VecDeque<(u8, Vec<u8>)>,
+
+    wait_queue: Option<Vec<u8>>,
+
+    expected_ack: u8,
+
+    transmission_count: usize,
+
+    timer: Option<Duration>,
+
+    control_frame: Option<Vec<u8>>,
+
+    frames: VecDeque<Vec<u8>>,
+
+    alerts: VecDeque<FopAlert>,
+}
+
+impl Fop {
+    /// Create a FOP-1 in its initial state, building frames for the given TC channel.
+    pub fn new(config: FopConfig, frame_config: TcFrameConfig) -> Fop {
+        Fop {
+            config,
+            encoder: TcFrameEncoder::new(frame_config),
+            state: FopState::Initial,
+            sent_queue: VecDeque::new(),
+            wait_queue: None,
+            expected_ack: 0,
+            transmission_count: 0,
+            timer: None,
+            control_frame: None,
+            frames: VecDeque::new(),
+            alerts: VecDeque::new(),
+        }
+    }
+
+    /// The current state of the FOP-1.
+    pub fn state(&self) -> FopState {
+        self.state
+    }
+
+    /// The sequence number of the next new Type-AD frame, V(S).
+    pub fn next_sequence_number(&self) -> u8 {
+        self.encoder.next_sequence_number(self.config.vcid)
+    }
+
+    /// The sequence number of the oldest frame not yet acknowledged, NN(R).
+    pub fn expected_ack(&self) -> u8 {
+        self.expected_ack
+    }
+
+    /// The number of frames sent but not yet acknowledged.
+    pub fn num_unacknowledged(&self) -> usize {
+        self.sent_queue.len()
+    }
+
+    /// The time at which the timer runs out, if it is running.
+    pub fn timer(&self) -> Option<Duration> {
+        self.timer
+    }
+
+    /// Take the next frame to send, if there is one.
+    pub fn pull_frame(&mut self) -> Option<Vec<u8>> {
+        self.frames.pop_front()
+    }
+
+    /// The number of frames waiting to be sent.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Take the next alert, if there is one.
+    pub fn pull_alert(&mut self) -> Option<FopAlert> {
+        self.alerts.pop_front()
+    }
+
+    /// Start the service at once, without checking the state of the FARM-1.
+    pub fn initiate(&mut self) -> Result<(), CopError> {
+        self.initialize()?;
+        self.state = FopState::Active;
+        Ok(())
+    }
+
+    /// Start the service once a CLCW shows the FARM-1 expects the next frame, V(S).
+    pub fn initiate_with_clcw(&mut self, now: Duration) -> Result<(), CopError> {
+        self.initialize()?;
+        self.state = FopState::InitializingWithoutBc;
+        self.timer = Some(now + self.config.timeout);
+        Ok(())
+    }
+
+    /// Start the service by sending an Unlock command, once a CLCW shows it was
+    /// carried out.
+    pub fn initiate_with_unlock(&mut self, now: Duration) -> Result<(), CopError> {
+        self.initialize()?;
+        self.send_control_command(ControlCommand::Unlock, now)
+    }
+
+    /// Start the service by sending a Set V(R) command, so both sides number frames from
+    /// the given sequence number, once a CLCW shows it was carried out.
+    pub fn initiate_with_set_vr(&mut self, vr: u8, now: Duration) -> Result<(), CopError> {
+        self.initialize()?;
+        self.encoder.set_next_sequence_number(self.config.vcid, vr);
+        self.expected_ack = vr;
+        self.send_control_command(ControlCommand::SetVr(vr), now)
+    }
+
+    /// Stop the service, dropping any frames not yet acknowledged.
+    pub fn terminate(&mut self) {
+        self.stop(FopAlert::Terminated);
+    }
+
+    /// Send data in a Type-AD frame. Frames are sent while fewer than the window width
+    /// are waiting for acknowledgement. Beyond that, one more can wait to be sent,
+    /// and further data is rejected as busy.
+    pub fn transfer(&mut self, data: &[u8], now: Duration) -> Result<(), CopError> {
+        match self.state {
+            FopState::Active | FopState::RetransmitWithoutWait | FopState::RetransmitWithWait => (),
+            state => return Err(CopError::InvalidState(state)),
+        }
+
+        let frame_config = self.encoder.config;
+        if data.len() > frame_config.max_data_length() {
+            return Err(CopError::Frame(FrameError::FrameTooLong(data.len() + frame_config.overhead())));
+        }
+
+        if self.wait_queue.is_some() {
+            return Err(CopError::Busy);
+        }
+
+        self.wait_queue = Some(data.to_vec());
+        self.send_waiting(now)
+    }
+
+    /// Send data in a Type-BD frame at once, bypassing the sequence control.
+    pub fn transfer_expedited(&mut self, data: &[u8]) -> Result<(), CopError> {
+        let frame = self.encoder.encode(TcFrameType::BD, self.config.vcid, self.config.map_id, data)?;
+        self.frames.push_back(frame);
+        Ok(())
+    }
+
+    /// Process a CLCW received from the FARM-1. Reports for other virtual channels
+    /// are ignored.
+    pub fn recv_clcw(&mut self, clcw: &Clcw, now: Duration) -> Result<(), CopError> {
+        if clcw.vcid() != self.config.vcid || self.state == FopState::Initial {
+            return Ok(());
+        }
+
+        if clcw.control_word_type() != 0 || clcw.version() != CLCW_VERSION || clcw.cop_in_effect() != CLCW_COP_1 {
+            self.stop(FopAlert::InvalidReport);
+            return Ok(());
+        }
+
+        if clcw.lockout() {
+            self.stop(FopAlert::Lockout);
+            return Ok(());
+        }
+
+        // while initializing, reports are ignored until the FARM-1 expects V(S)
+        let report_value = clcw.report_value();
+        let next_sequence_number = self.next_sequence_number();
+        match self.state {
+            FopState::InitializingWithoutBc => {
+                if report_value == next_sequence_number && !clcw.retransmit() && !clcw.wait() {
+                    self.state = FopState::Active;
+                    self.timer = None;
+                }
+                return Ok(());
+            },
+
+            FopState::InitializingWithBc => {
+                if report_value == next_sequence_number && !clcw.retransmit() {
+                    self.state = FopState::Active;
+                    self.timer = None;
+                    self.control_frame = None;
+                }
+                return Ok(());
+            },
+
+            _ => (),
+        }
+
+        // only frames which have been sent can be acknowledged
+        if report_value.wrapping_sub(self.expected_ack) > next_sequence_number.wrapping_sub(self.expected_ack) {
+            self.stop(FopAlert::InvalidReport);
+            return Ok(());
+        }
+
+        let new_ack = report_value != self.expected_ack;
+        if new_ack {
+            while self.sent_queue.front().map_or(false, |(sequence_number, _)| *sequence_number != report_value) {
+                self.sent_queue.pop_front();
+            }
+            self.expected_ack = report_value;
+            self.transmission_count = 1;
+
+            self.timer = if self.sent_queue.is_empty() { None } else { Some(now + self.config.timeout) };
+        }
+
+        if !clcw.retransmit() {
+            self.state = FopState::Active;
+        } else if clcw.wait() {
+            if self.transmission_count >= self.config.transmission_limit {
+                self.stop(FopAlert::Limit);
+                return Ok(());
+            }
+            self.state = FopState::RetransmitWithWait;
+        } else if self.state != FopState::RetransmitWithoutWait || new_ack {
+            if self.transmission_count >= self.config.transmission_limit {
+                self.stop(FopAlert::Limit);
+                return Ok(());
+            }
+            self.retransmit(now);
+        }
+
+        self.send_waiting(now)
+    }
+
+    /// Check the timer at the current time. Once it runs out, unacknowledged frames are
+    /// sent again, or the service stops if they have been sent too many times.
+    pub fn tick(&mut self, now: Duration) -> Result<(), CopError> {
+        match self.timer {
+            Some(deadline) if now >= deadline => (),
+            _ => return Ok(()),
+        }
+
+        if self.state == FopState::InitializingWithoutBc || self.transmission_count >= self.config.transmission_limit {
+            self.stop(FopAlert::Timer);
+            return Ok(());
+        }
+
+        match self.state {
+            FopState::Active | FopState::RetransmitWithoutWait => self.retransmit(now),
+
+            FopState::InitializingWithBc => {
+                self.transmission_count += 1;
+                self.timer = Some(now + self.config.timeout);
+                if let Some(frame) = self.control_frame.clone() {
+                    self.frames.push_back(frame);
+                }
+            },
+
+            _ => {
+                // frames are held while the FARM-1 has no buffer space
+                self.transmission_count += 1;
+                self.timer = Some(now + self.config.timeout);
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Check a new service can be started, and clear any state left from before.
+    fn initialize(&mut self) -> Result<(), CopError> {
+        if self.state != FopState::Initial {
+            return Err(CopError::InvalidState(self.state));
+        }
+
+        self.sent_queue.clear();
+        self.wait_queue = None;
+        self.control_frame = None;
+        self.timer = None;
+        self.transmission_count = 1;
+        self.expected_ack = self.next_sequence_number();
+        Ok(())
+    }
+
+    fn send_control_command(&mut self, command: ControlCommand, now: Duration) -> Result<(), CopError> {
+        let frame = self.encoder.encode(TcFrameType::BC, self.config.vcid, self.config.map_id, &command.encode())?;
+
+        self.frames.push_back(frame.clone());
+        self.control_frame = Some(frame);
+        self.state = FopState::InitializingWithBc;
+        self.timer = Some(now + self.config.timeout);
+        Ok(())
+    }
+
+    /// Send the frame waiting to be sent, if the window has room for it.
+    fn send_waiting(&mut self, now: Duration) -> Result<(), CopError> {
+        if self.state == FopState::RetransmitWithWait || self.sent_queue.len() >= self.config.window_width as usize {
+            return Ok(());
+        }
+
+        let data = match self.wait_queue.take() {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+
+        let sequence_number = self.next_sequence_number();
+        let frame = self.encoder.encode(TcFrameType::AD, self.config.vcid, self.config.map_id, &data)?;
+
+        if self.sent_queue.is_empty() {
+            self.transmission_count = 1;
+        }
+
+        self.sent_queue.push_back((sequence_number, frame.clone()));
+        self.frames.push_back(frame);
+        self.timer = Some(now + self.config.timeout);
+        Ok(())
+    }
+
+    /// Send all unacknowledged frames again.
+    fn retransmit(&mut self, now: Duration) {
+        self.transmission_count += 1;
+        self.state = FopState::RetransmitWithoutWait;
+        self.timer = Some(now + self.config.timeout);
+
+        for (_, frame) in self.sent_queue.iter() {
+            self.frames.push_back(frame.clone());
+        }
+    }
+
+    /// Stop the service, returning to the initial state with an alert.
+    fn stop(&mut self, alert: FopAlert) {
+        self.state = FopState::Initial;
+        self.sent_queue.clear();
+        self.wait_queue = None;
+        self.control_frame = None;
+        self.timer = None;
+        self.alerts.push_back(alert);
+    }
+}
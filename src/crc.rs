@@ -0,0 +1,37 @@
+/// The generator polynomial of the CRC-16-CCITT, x^16 + x^12 + x^5 + 1.
+pub const CRC16_CCITT_POLY: u16 = 0x1021;
+
+/// The initial value of the CRC-16-CCITT register used by CCSDS and ECSS.
+pub const CRC16_CCITT_INIT: u16 = 0xFFFF;
+
+
+/// Calculate the CRC-16-CCITT of a byte slice, as used for the ECSS PUS Packet Error
+/// Control field and the CCSDS Frame Error Control Field. The register starts at
+/// 0xFFFF, and the result is neither reflected nor inverted.
+pub fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    crc16_ccitt_update(CRC16_CCITT_INIT, bytes)
+}
+
+/// Continue a CRC-16-CCITT calculation with more bytes, starting from a previous result.
+pub fn crc16_ccitt_update(crc: u16, bytes: &[u8]) -> u16 {
+    let mut crc = crc;
+
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if (crc & 0x8000) != 0 {
+                (crc << 1) ^ CRC16_CCITT_POLY
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Check a byte slice ending in a big endian CRC-16-CCITT of the bytes before it.
+/// Slices too short to hold a CRC are never valid.
+pub fn crc16_ccitt_is_valid(bytes: &[u8]) -> bool {
+    bytes.len() >= 2 && crc16_ccitt(bytes) == 0
+}
@@ -0,0 +1,181 @@
+/*!
+The frame module holds what is shared by the transfer frame modules, such as
+tm_frame and aos_frame: the errors reported when reading frames, the Frame Error
+Control Field used by every kind of frame, and the PacketExtractor which reassembles
+space packets carried in the data fields of a sequence of frames.
+*/
+use std::collections::VecDeque;
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+
+use crc::*;
+use primary_header::*;
+
+
+/// The APID of idle packets, which carry no data and are used to fill frames.
+pub const CCSDS_IDLE_APID: u16 = 0x07FF;
+
+/// The size of the Frame Error Control Field, which is the same for TM, TC, AOS and
+/// USLP frames.
+pub const FECF_SIZE_BYTES: usize = 2;
+
+/// A FrameError describes why a transfer frame could not be read.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FrameError {
+    /// The frame is shorter than its header, or than the configured frame length.
+    NotEnoughBytes,
+
+    /// The frame's length does not match the configured frame length or the
+    /// length given in its header.
+    WrongLength { expected: usize, actual: usize },
+
+    /// The frame's transfer frame version number is not the one expected.
+    InvalidVersion(u8),
+
+    /// The frame's Frame Error Control Field does not match its contents.
+    InvalidFecf,
+
+    /// The frame's primary header has more errors than its Frame Header Error
+    /// Control field can correct.
+    InvalidFhec,
+
+    /// A header field points outside of the frame, such as a first header
+    /// pointer past the end of the data field.
+    InvalidHeader,
+
+    /// The frame is for a different spacecraft than the one configured.
+    WrongSpacecraft(u16),
+
+    /// A frame being built would be longer than the largest frame allowed,
+    /// given as the length of the frame.
+    FrameTooLong(usize),
+}
+
+
+/// Append the Frame Error Control Field to a frame being built. The FECF is the
+/// CRC-16-CCITT of the whole frame before it, big endian.
+pub fn append_fecf(frame: &mut Vec<u8>) {
+    let mut fecf = [0; FECF_SIZE_BYTES];
+    BigEndian::write_u16(&mut fecf, crc16_ccitt(frame));
+    frame.extend_from_slice(&fecf);
+}
+
+/// Check the Frame Error Control Field at the end of a frame.
+pub fn fecf_is_valid(frame: &[u8]) -> bool {
+    crc16_ccitt_is_valid(frame)
+}
+
+
+/// A PacketExtractor reassembles space packets from the data fields of consecutive
+/// frames of a virtual channel. Packets may start in one frame and end in a later
+/// frame. Each frame gives the offset of the first packet header starting in its
+/// data field, if any, which lets the extractor recover after a lost frame.
+#[derive(Debug, Clone)]
+pub struct PacketExtractor {
+    /// Whether idle packets are kept. They are dropped by default.
+    pub keep_idle_packets: bool,
+
+    /// The number of partial packets dropped because a frame was lost, or because
+    /// a first header pointer did not agree with the length of the packet before it.
+    pub dropped_partial_packets: usize,
+
+    buffer: Vec<u8>,
+
+    in_sync: bool,
+
+    packets: VecDeque<BytesMut>,
+}
+
+impl Default for PacketExtractor {
+    fn default() -> PacketExtractor {
+        PacketExtractor::new()
+    }
+}
+
+impl PacketExtractor {
+    /// Create a PacketExtractor with no partial packet.
+    pub fn new() -> PacketExtractor {
+        PacketExtractor {
+            keep_idle_packets: false,
+            dropped_partial_packets: 0,
+            buffer: Vec::new(),
+            in_sync: false,
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Give the extractor the packet zone of the next frame. The first header pointer
+    /// is the offset in the data of the first packet header, or None if no packet
+    /// starts in this frame.
+    pub fn recv_data(&mut self, data: &[u8], first_header_pointer: Option<usize>) {
+        match first_header_pointer {
+            Some(pointer) => {
+                let pointer = pointer.min(data.len());
+
+                if self.in_sync {
+                    self.buffer.extend_from_slice(&data[0..pointer]);
+                    self.extract_packets();
+                }
+
+                // anything left over before the first header pointer is a packet
+                // whose length did not agree with the pointer
+                if !self.buffer.is_empty() {
+                    self.dropped_partial_packets += 1;
+                }
+
+                self.buffer.clear();
+                self.buffer.extend_from_slice(&data[pointer..]);
+                self.in_sync = true;
+                self.extract_packets();
+            },
+
+            None => {
+                if self.in_sync {
+                    self.buffer.extend_from_slice(data);
+                    self.extract_packets();
+                }
+            },
+        }
+    }
+
+    /// Drop any partial packet, such as when a frame has been lost. Packets are
+    /// extracted again from the next frame with a first header pointer.
+    pub fn reset(&mut self) {
+        if !self.buffer.is_empty() {
+            self.dropped_partial_packets += 1;
+        }
+
+        self.buffer.clear();
+        self.in_sync = false;
+    }
+
+    /// Take the next complete packet, if there is one.
+    pub fn pull_packet(&mut self) -> Option<BytesMut> {
+        self.packets.pop_front()
+    }
+
+    /// The number of complete packets waiting to be pulled.
+    pub fn num_packets(&self) -> usize {
+        self.packets.len()
+    }
+
+    fn extract_packets(&mut self) {
+        let mut start = 0;
+
+        while let Some(pri_header) = PrimaryHeader::from_slice(&self.buffer[start..]) {
+            let packet_length = pri_header.packet_length() as usize;
+            if self.buffer.len() - start < packet_length {
+                break;
+            }
+
+            if self.keep_idle_packets || pri_header.control.apid() != CCSDS_IDLE_APID {
+                self.packets.push_back(BytesMut::from(&self.buffer[start..start + packet_length]));
+            }
+
+            start += packet_length;
+        }
+
+        self.buffer.drain(0..start);
+    }
+}
@@ -0,0 +1,431 @@
+/*!
+The frame_sync module finds fixed length frames in a received bit stream, using the
+Attached Sync Marker (ASM) sent before each frame, as defined in CCSDS 131.0, TM
+Synchronization and Channel Coding.
+
+Unlike the sync bytes of a CcsdsParser, the marker may start at any bit of the stream,
+may contain bit errors, and may be inverted when a BPSK receiver locks onto the wrong
+phase. Frames are output byte aligned, with any inversion corrected, and can be
+derandomized before they are handed to a frame decoder.
+
+The synchronizer searches for the marker, checks that it is found again one frame later,
+and then locks onto the stream. Frames found while checking are held back until the lock
+is confirmed, so a marker found by chance in the data does not output a false frame. Once locked, a marker with too many bit errors does not
+immediately lose the lock: the synchronizer flywheels, assuming frames are still where
+they are expected, until too many markers in a row have been missed.
+
+Frames coded with Reed-Solomon can be decoded once found, so that frames with too many
+errors to correct are dropped rather than handed on to packet extraction.
+*/
+use std::collections::VecDeque;
+
+use bytes::BytesMut;
+
+use randomizer::*;
+use reed_solomon::*;
+
+
+/// The Attached Sync Marker of CCSDS telemetry frames.
+pub const CCSDS_ASM: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D];
+
+
+/// The SyncState is the state of a FrameSync.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SyncState {
+    /// Searching every bit offset for the marker.
+    Search,
+
+    /// The marker has been found, and is being checked at the start of following frames.
+    Check,
+
+    /// The marker has been found at the start of enough frames to lock onto the stream.
+    Lock,
+
+    /// The marker was missed while locked, and frames are assumed to be where expected.
+    Flywheel,
+}
+
+/// The FrameSyncConfig gives the marker and frame length of a stream, and how
+/// tolerant the synchronizer is of errors.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrameSyncConfig {
+    /// The sync marker before each frame. This is CCSDS_ASM by default.
+    pub asm: Vec<u8>,
+
+    /// The length of each frame following a marker, in bytes.
+    pub frame_length: usize,
+
+    /// The number of bit errors allowed in a marker found while searching.
+    pub search_tolerance: u32,
+
+    /// The number of bit errors allowed in a marker where one is expected.
+    pub lock_tolerance: u32,
+
+    /// The number of markers that must be found after the first before the stream is locked.
+    pub check_frames: usize,
+
+    /// The number of markers in a row which can be missed while locked before the
+    /// synchronizer searches again.
+    pub flywheel_frames: usize,
+
+    /// The number of bits a marker may have slipped from where it is expected, such as
+    /// when the receiver has dropped or repeated a bit, while still keeping the lock.
+    pub max_bit_slip: usize,
+
+    /// Whether to search for the inverted marker, and invert the frames which follow it.
+    pub detect_inversion: bool,
+
+    /// Whether the marker is kept at the start of each output frame.
+    pub keep_asm: bool,
+
+    /// The randomizer to remove from each frame once it is found, if the frames were
+    /// randomized before they were sent. The marker itself is never randomized.
+    pub derandomizer: Option<Randomizer>,
+
+    /// The Reed-Solomon coding of each frame, decoded after derandomizing. The frame
+    /// length is then the codeblock length, and the check symbols are removed from
+    /// the output frames.
+    pub reed_solomon: Option<RsConfig>,
+}
+
+impl FrameSyncConfig {
+    /// Create a configuration for frames of the given length following the CCSDS ASM.
+    pub fn new(frame_length: usize) -> FrameSyncConfig {
+        FrameSyncConfig {
+            asm: CCSDS_ASM.to_vec(),
+            frame_length,
+            search_tolerance: 2,
+            lock_tolerance: 4,
+            check_frames: 1,
+            flywheel_frames: 2,
+            max_bit_slip: 1,
+            detect_inversion: true,
+            keep_asm: false,
+            derandomizer: None,
+            reed_solomon: None,
+        }
+    }
+}
+
+
+/// A FrameSync finds frames in a stream of bytes which are not necessarily aligned to
+/// the frames. Bytes are given to the synchronizer as they are received, and frames
+/// are pulled out once complete.
+#[derive(Debug, Clone)]
+pub struct FrameSync {
+    /// The configuration of the stream.
+    pub config: FrameSyncConfig,
+
+    /// The number of times the lock was lost.
+    pub sync_losses: usize,
+
+    /// The number of bit errors in the markers of the frames output.
+    pub asm_bit_errors: usize,
+
+    /// The number of markers found slipped from where they were expected.
+    pub bit_slips: usize,
+
+    /// The number of symbols corrected by Reed-Solomon decoding.
+    pub corrected_symbols: usize,
+
+    /// The number of frames dropped because they had too many errors to correct.
+    pub uncorrectable_frames: usize,
+
+    state: SyncState,
+
+    inverted: bool,
+
+    marker_found: bool,
+
+    count: usize,
+
+    check_start: usize,
+
+    held_frames: Vec<BytesMut>,
+
+    buffer: Vec<u8>,
+
+    bit_pos: usize,
+
+    frames: VecDeque<BytesMut>,
+
+    reed_solomon: Option<ReedSolomon>,
+}
+
+impl FrameSync {
+    /// Create a synchronizer which starts by searching for the marker.
+    pub fn new(config: FrameSyncConfig) -> FrameSync {
+        FrameSync {
+            config,
+            sync_losses: 0,
+            asm_bit_errors: 0,
+            bit_slips: 0,
+            corrected_symbols: 0,
+            uncorrectable_frames: 0,
+            state: SyncState::Search,
+            inverted: false,
+            marker_found: false,
+            count: 0,
+            check_start: 0,
+            held_frames: Vec::new(),
+            buffer: Vec::new(),
+            bit_pos: 0,
+            frames: VecDeque::new(),
+            reed_solomon: None,
+        }
+    }
+
+    /// Give the synchronizer the next bytes of the stream, finding any frames they complete.
+    pub fn recv_slice(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+
+        loop {
+            let progress = if self.state == SyncState::Search {
+                self.search()
+            } else {
+                self.track()
+            };
+
+            if !progress {
+                break;
+            }
+        }
+
+        // keep enough of the bits already read to find a marker which has slipped back,
+        // and while checking, to search again from just after the first marker
+        let keep_from = if self.state == SyncState::Check {
+            self.check_start.min(self.bit_pos)
+        } else {
+            self.bit_pos
+        };
+        let consumed = keep_from.saturating_sub(self.config.max_bit_slip) / 8;
+        self.buffer.drain(0..consumed);
+        self.bit_pos -= consumed * 8;
+        self.check_start = self.check_start.saturating_sub(consumed * 8);
+    }
+
+    /// Take the next frame, if there is one.
+    pub fn pull_frame(&mut self) -> Option<BytesMut> {
+        self.frames.pop_front()
+    }
+
+    /// The number of frames waiting to be pulled.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The current state of the synchronizer.
+    pub fn state(&self) -> SyncState {
+        self.state
+    }
+
+    /// Whether the stream is inverted, so that frames are inverted when output.
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Search each bit offset for the marker, or its inverse. Returns true if it was
+    /// found, leaving the stream position at the marker.
+    fn search(&mut self) -> bool {
+        let marker_bits = self.config.asm.len() * 8;
+
+        while self.available_bits() >= marker_bits {
+            for &inverted in [false, true].iter() {
+                if inverted && !self.config.detect_inversion {
+                    continue;
+                }
+
+                if self.marker_errors(self.bit_pos, inverted) <= self.config.search_tolerance {
+                    self.inverted = inverted;
+                    self.marker_found = true;
+                    self.count = 0;
+                    self.check_start = self.bit_pos;
+                    self.held_frames.clear();
+                    self.state = if self.config.check_frames == 0 { SyncState::Lock } else { SyncState::Check };
+                    return true;
+                }
+            }
+
+            self.bit_pos += 1;
+        }
+
+        false
+    }
+
+    /// Check for the marker where it is expected and output the frame following it, or
+    /// hold it back while the lock is being checked. Returns false if there are not yet
+    /// enough bits for the frame.
+    fn track(&mut self) -> bool {
+        let marker_bits = self.config.asm.len() * 8;
+        let frame_bits = self.config.frame_length * 8;
+        if self.available_bits() < marker_bits + frame_bits {
+            return false;
+        }
+
+        let errors = self.marker_errors(self.bit_pos, self.inverted);
+        let marker = if self.marker_found || errors <= self.config.lock_tolerance {
+            Some((self.bit_pos, errors))
+        } else if self.state == SyncState::Check {
+            None
+        } else if self.available_bits() < marker_bits + frame_bits + self.config.max_bit_slip {
+            // wait for enough bits to look for a marker which has slipped forward
+            return false;
+        } else {
+            self.find_slipped_marker()
+        };
+
+        match marker {
+            Some((bit_pos, errors)) => {
+                if bit_pos != self.bit_pos {
+                    self.bit_slips += 1;
+                }
+
+                self.bit_pos = bit_pos;
+                self.asm_bit_errors += errors as usize;
+
+                match self.state {
+                    SyncState::Check if !self.marker_found => {
+                        self.count += 1;
+                        if self.count >= self.config.check_frames {
+                            self.state = SyncState::Lock;
+                            self.frames.extend(self.held_frames.drain(..));
+                        }
+                    },
+
+                    SyncState::Flywheel => {
+                        self.state = SyncState::Lock;
+                    },
+
+                    _ => (),
+                }
+            },
+
+            None => {
+                match self.state {
+                    SyncState::Check => {
+                        // the first marker was found by chance, so drop the frames held
+                        // behind it, and search again from the bit after it, as a real
+                        // marker may be inside the frame that was skipped
+                        self.state = SyncState::Search;
+                        self.bit_pos = self.check_start + 1;
+                        self.held_frames.clear();
+                        return true;
+                    },
+
+                    SyncState::Lock => {
+                        self.state = SyncState::Flywheel;
+                        self.count = 1;
+                    },
+
+                    _ => {
+                        self.count += 1;
+                    },
+                }
+
+                if self.count > self.config.flywheel_frames {
+                    self.state = SyncState::Search;
+                    self.sync_losses += 1;
+                    return true;
+                }
+            },
+        }
+
+        self.marker_found = false;
+        self.output_frame();
+        true
+    }
+
+    /// Find the marker within the allowed bit slip of where it is expected, giving its
+    /// position and number of bit errors. Smaller slips are tried first.
+    fn find_slipped_marker(&self) -> Option<(usize, u32)> {
+        for slip in 1..=self.config.max_bit_slip {
+            let mut positions = vec![self.bit_pos + slip];
+            if self.bit_pos >= slip {
+                positions.push(self.bit_pos - slip);
+            }
+
+            for bit_pos in positions {
+                let errors = self.marker_errors(bit_pos, self.inverted);
+                if errors <= self.config.lock_tolerance {
+                    return Some((bit_pos, errors));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Output the frame at the stream position, moving past it. A frame which cannot be
+    /// corrected is dropped, and frames found while checking are held.
+    fn output_frame(&mut self) {
+        let marker_bits = self.config.asm.len() * 8;
+
+        let (start, length) = if self.config.keep_asm {
+            (self.bit_pos, self.config.asm.len() + self.config.frame_length)
+        } else {
+            (self.bit_pos + marker_bits, self.config.frame_length)
+        };
+
+        let mask = if self.inverted { 0xFF } else { 0x00 };
+        let mut frame: Vec<u8> = (0..length).map(|index| self.byte_at(start + index * 8) ^ mask).collect();
+
+        if let Some(derandomizer) = self.config.derandomizer {
+            let frame_start = length - self.config.frame_length;
+            derandomizer.derandomize(&mut frame[frame_start..]);
+        }
+
+        self.bit_pos += marker_bits + self.config.frame_length * 8;
+
+        if let Some(rs_config) = self.config.reed_solomon {
+            if self.reed_solomon.as_ref().map(|rs| rs.config()) != Some(rs_config) {
+                self.reed_solomon = ReedSolomon::new(rs_config).ok();
+            }
+
+            let frame_start = length - self.config.frame_length;
+            let report = self.reed_solomon.as_ref().and_then(|rs| rs.decode(&mut frame[frame_start..]).ok());
+            match report {
+                Some(ref report) if report.is_correctable() => {
+                    self.corrected_symbols += report.total_corrected();
+                    frame.truncate(frame_start + rs_config.frame_length());
+                },
+
+                _ => {
+                    self.uncorrectable_frames += 1;
+                    return;
+                },
+            }
+        }
+
+        if self.state == SyncState::Check {
+            self.held_frames.push(BytesMut::from(frame));
+        } else {
+            self.frames.push_back(BytesMut::from(frame));
+        }
+    }
+
+    /// The number of bit errors in the marker at a bit position.
+    fn marker_errors(&self, bit_pos: usize, inverted: bool) -> u32 {
+        let mask = if inverted { 0xFF } else { 0x00 };
+
+        self.config.asm.iter()
+                       .enumerate()
+                       .map(|(index, byte)| (self.byte_at(bit_pos + index * 8) ^ byte ^ mask).count_ones())
+                       .sum()
+    }
+
+    /// The 8 bits of the buffer starting at a bit position.
+    fn byte_at(&self, bit_pos: usize) -> u8 {
+        let index = bit_pos / 8;
+        let shift = bit_pos % 8;
+
+        if shift == 0 {
+            self.buffer[index]
+        } else {
+            (self.buffer[index] << shift) | (self.buffer[index + 1] >> (8 - shift))
+        }
+    }
+
+    fn available_bits(&self) -> usize {
+        (self.buffer.len() * 8).saturating_sub(self.bit_pos)
+    }
+}
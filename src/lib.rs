@@ -25,13 +25,38 @@ extern crate byteorder;
 
 extern crate bytes;
 
-#[cfg(test)]
-extern crate rand;
+#[cfg(any(feature = "toml", feature = "json"))]
+extern crate serde;
 
-#[cfg(test)]
+#[cfg(any(feature = "toml", feature = "json"))]
 #[macro_use]
-extern crate quickcheck;
+extern crate serde_derive;
+
+#[cfg(feature = "json")]
+extern crate serde_json;
+
+#[cfg(feature = "toml")]
+extern crate toml;
 
 pub mod primary_header;
 pub mod parser;
-
+pub mod apid_filter;
+pub mod config;
+pub mod merge;
+pub mod time;
+pub mod secondary_header;
+pub mod space_packet;
+pub mod crc;
+pub mod pus;
+pub mod cfe;
+pub mod frame;
+pub mod tm_frame;
+pub mod tc_frame;
+pub mod aos_frame;
+pub mod uslp_frame;
+pub mod frame_sync;
+pub mod randomizer;
+pub mod reed_solomon;
+pub mod cltu;
+pub mod clcw;
+pub mod cop1;
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use bytes::BytesMut;
+
+use primary_header::*;
+use parser::*;
+
+
+/// A station ID identifies which packet stream a packet was received on.
+/// When merging packets from several CcsdsParsers, this is the index of the parser.
+pub type StationId = usize;
+
+/// The sequence count is a 14 bit field, so sequence counts wrap at this value.
+const SEQUENCE_COUNT_MODULUS: u16 = 0x4000;
+
+/// A MergedPacket is a packet produced by a PacketMerger, along with the station
+/// whose copy of the packet was kept.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MergedPacket {
+    /// The station the packet was received from.
+    pub station: StationId,
+
+    /// The packet bytes, starting with the primary header.
+    pub packet: BytesMut,
+}
+
+/// A packet waiting in a reorder window, along with the key used to detect duplicates.
+#[derive(Debug, Clone)]
+struct PendingPacket {
+    sequence_count: u16,
+    hash: u64,
+    merged: MergedPacket,
+}
+
+/// The reorder state for a single APID.
+#[derive(Debug, Clone, Default)]
+struct ApidStream {
+    /// The sequence count of the next packet to emit, once the stream has started.
+    next_sequence_count: Option<u16>,
+
+    /// Packets received but not yet emitted.
+    pending: Vec<PendingPacket>,
+
+    /// Sequence counts and content hashes of recently emitted packets.
+    history: VecDeque<(u16, u64)>,
+
+    /// Packets received in a row which were behind the stream, kept in case the
+    /// sequence count has been reset.
+    late: Vec<PendingPacket>,
+}
+
+/// A PacketMerger combines packet streams carrying the same packets, such as telemetry
+/// received by redundant ground stations during a pass, into a single stream.
+///
+/// Packets are duplicates if they have the same APID, sequence count and content.
+/// Only the first copy of a packet is kept. Packets are emitted in sequence count order
+/// for each APID, using a reorder window to wait for packets that arrive out of order.
+/// If the window fills up, the merger assumes the missing packets were lost and moves on.
+///
+/// The first packets of each APID are held until the reorder window fills, and the
+/// stream starts at the earliest sequence count among them. Otherwise a station which
+/// is slightly behind the others would have its first packets dropped as late.
+///
+/// If more packets than fit in the reorder window arrive in a row behind the stream,
+/// such as when the spacecraft resets its sequence counters, the stream is started
+/// again from those packets.
+#[derive(Debug, Clone)]
+pub struct PacketMerger {
+    /// The maximum number of packets held for each APID while waiting for a missing
+    /// sequence count.
+    pub reorder_window: usize,
+
+    /// The number of emitted packets remembered for each APID to detect duplicates
+    /// that arrive after the original has been emitted.
+    pub history_size: usize,
+
+    /// The number of duplicate packets that have been dropped.
+    pub duplicate_packets: usize,
+
+    /// The number of packets dropped because they arrived after later packets of the
+    /// same APID had already been emitted.
+    pub late_packets: usize,
+
+    /// The number of packets dropped because they were too short to hold a primary header.
+    pub invalid_packets: usize,
+
+    /// The number of times an APID's stream was started again after its sequence count
+    /// was reset.
+    pub stream_resets: usize,
+
+    streams: BTreeMap<u16, ApidStream>,
+
+    output: VecDeque<MergedPacket>,
+}
+
+impl PacketMerger {
+    /// Create a new merger with the given reorder window, in packets per APID.
+    pub fn new(reorder_window: usize) -> PacketMerger {
+        PacketMerger {
+            reorder_window,
+            history_size: 64,
+            duplicate_packets: 0,
+            late_packets: 0,
+            invalid_packets: 0,
+            stream_resets: 0,
+            streams: BTreeMap::new(),
+            output: VecDeque::new(),
+        }
+    }
+
+    /// Add a packet received from the given station.
+    pub fn recv_packet(&mut self, station: StationId, packet: BytesMut) {
+        let pri_header = match PrimaryHeader::from_slice(&packet) {
+            Some(pri_header) => pri_header,
+            None => {
+                self.invalid_packets += 1;
+                return;
+            },
+        };
+
+        let mut hasher = DefaultHasher::new();
+        packet.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let sequence_count = pri_header.sequence.sequence_count();
+        let merged = MergedPacket { station, packet };
+
+        let reorder_window = self.reorder_window;
+        let history_size = self.history_size;
+        let output = &mut self.output;
+        let stream = self.streams.entry(pri_header.control.apid()).or_default();
+
+        let duplicate =
+            stream.history.contains(&(sequence_count, hash)) ||
+            stream.pending.iter().chain(stream.late.iter())
+                                 .any(|pending| pending.sequence_count == sequence_count &&
+                                                pending.hash == hash);
+        if duplicate {
+            self.duplicate_packets += 1;
+            return;
+        }
+
+        let pending = PendingPacket { sequence_count, hash, merged };
+
+        if let Some(next_sequence_count) = stream.next_sequence_count {
+            if sequence_distance(next_sequence_count, sequence_count) >= SEQUENCE_COUNT_MODULUS / 2 {
+                self.late_packets += 1;
+                stream.late.push(pending);
+
+                if stream.late.len() > reorder_window {
+                    // the late packets are kept after all, as they start the new stream
+                    self.late_packets -= stream.late.len();
+                    self.stream_resets += 1;
+                    stream.reset(history_size, output);
+                    stream.release(reorder_window, history_size, output);
+                }
+                return;
+            }
+        }
+
+        stream.late.clear();
+        stream.pending.push(pending);
+
+        stream.release(reorder_window, history_size, output);
+    }
+
+    /// Pull all available packets from a set of parsers. Each parser is treated
+    /// as a station, whose StationId is its index in the slice.
+    pub fn recv_from_parsers(&mut self, parsers: &mut [CcsdsParser]) {
+        for (station, parser) in parsers.iter_mut().enumerate() {
+            while let Some(packet) = parser.pull_packet() {
+                self.recv_packet(station, packet);
+            }
+        }
+    }
+
+    /// Retrieve the next merged packet, if one is available.
+    pub fn pull_packet(&mut self) -> Option<MergedPacket> {
+        self.output.pop_front()
+    }
+
+    /// Emit all packets waiting in reorder windows, in sequence count order for each
+    /// APID. This is used at the end of a pass, when no more packets will arrive.
+    pub fn flush(&mut self) {
+        let history_size = self.history_size;
+        for stream in self.streams.values_mut() {
+            stream.release(0, history_size, &mut self.output);
+        }
+    }
+}
+
+impl ApidStream {
+    /// Start the stream again from its late packets, after emitting the packets
+    /// pending from before the reset.
+    fn reset(&mut self, history_size: usize, output: &mut VecDeque<MergedPacket>) {
+        self.release(0, history_size, output);
+
+        self.next_sequence_count = None;
+        self.history.clear();
+        self.pending = self.late.split_off(0);
+    }
+
+    /// Emit packets that are next in sequence, skipping over missing sequence counts
+    /// while more packets are pending than fit in the reorder window.
+    fn release(&mut self, reorder_window: usize, history_size: usize, output: &mut VecDeque<MergedPacket>) {
+        if self.next_sequence_count.is_none() && self.pending.len() > reorder_window {
+            self.next_sequence_count = earliest_sequence_count(&self.pending);
+        }
+
+        while let Some(next_sequence_count) = self.next_sequence_count {
+            if self.pending.iter().any(|pending| pending.sequence_count == next_sequence_count) {
+                // emit every copy of this sequence count, as copies with different
+                // contents are not duplicates
+                let mut index = 0;
+                while index < self.pending.len() {
+                    if self.pending[index].sequence_count == next_sequence_count {
+                        let pending = self.pending.remove(index);
+
+                        self.history.push_back((pending.sequence_count, pending.hash));
+                        if self.history.len() > history_size {
+                            self.history.pop_front();
+                        }
+
+                        output.push_back(pending.merged);
+                    } else {
+                        index += 1;
+                    }
+                }
+
+                self.next_sequence_count = Some((next_sequence_count + 1) % SEQUENCE_COUNT_MODULUS);
+            } else if !self.pending.is_empty() && self.pending.len() > reorder_window {
+                // the window is full, so give up on the missing packet and move to the
+                // next sequence count we have
+                let closest = self.pending.iter()
+                                          .map(|pending| pending.sequence_count)
+                                          .min_by_key(|count| sequence_distance(next_sequence_count, *count))
+                                          .unwrap();
+                self.next_sequence_count = Some(closest);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// The earliest sequence count of a set of packets, accounting for the sequence
+/// count wrapping.
+fn earliest_sequence_count(pending: &[PendingPacket]) -> Option<u16> {
+    let mut counts = pending.iter().map(|pending| pending.sequence_count);
+    let first = counts.next()?;
+
+    Some(counts.fold(first, |earliest, count| {
+        if sequence_distance(count, earliest) < SEQUENCE_COUNT_MODULUS / 2 {
+            count
+        } else {
+            earliest
+        }
+    }))
+}
+
+/// The number of sequence counts from one count forward to another, accounting
+/// for the sequence count wrapping.
+fn sequence_distance(from: u16, to: u16) -> u16 {
+    to.wrapping_sub(from) % SEQUENCE_COUNT_MODULUS
+}
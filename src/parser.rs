@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use bytes::{Bytes, BytesMut};
 
 use primary_header::*;
+use apid_filter::*;
+use secondary_header::*;
 
 
 /// A CcsdsParserStatus is the current state of a CcsdsParser. The parser can determine
@@ -29,11 +33,55 @@ pub enum CcsdsParserStatus {
     /// The secondary header flag was not set, when configured as a required field
     SecondaryHeaderInvalid,
 
+    /// The packet's data field was too short to hold the secondary header expected
+    /// for its APID or packet type
+    SecondaryHeaderTooShort,
+
     /// The APID was not in the list of allowed APIDs
     ApidNotAllowed,
 
     /// The sync was not found, for packets where a sync has been configured
     SyncNotFound,
+
+    /// The packet length did not match the expected length configured for its APID
+    PacketLengthMismatch,
+
+    /// A user provided validator rejected the packet. The reason code is the
+    /// value returned by the validator.
+    RejectedByValidator(u32),
+}
+
+/// A PacketValidator is a user provided check run on each packet after the
+/// built-in checks have passed. It is given the packet's primary header and
+/// the bytes of the CCSDS packet, starting with the primary header.
+///
+/// A validator returns Ok(()) to accept a packet, or Err with a reason code to
+/// reject it. The reason code is reported in CcsdsParserStatus::RejectedByValidator.
+pub type PacketValidator = Box<dyn Fn(&PrimaryHeader, &[u8]) -> Result<(), u32> + Send>;
+
+/// The ExpectedLength of a packet is either an exact number of bytes, or an
+/// inclusive range of lengths. Lengths are CCSDS packet lengths, including
+/// the primary header.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ExpectedLength {
+    /// The packet must be exactly this many bytes long.
+    Exact(u32),
+
+    /// The packet must be between the min and max number of bytes long, inclusive.
+    Range {
+        min: u32,
+        max: u32,
+    },
+}
+
+impl ExpectedLength {
+    /// Check whether a packet length is allowed by this expected length.
+    pub fn matches(&self, packet_length: u32) -> bool {
+        match *self {
+            ExpectedLength::Exact(length) => packet_length == length,
+            ExpectedLength::Range { min, max } => packet_length >= min && packet_length <= max,
+        }
+    }
 }
 
 /// The CcsdsParserConfig struct provides all configuration used by a CcsdsParser.
@@ -41,12 +89,13 @@ pub enum CcsdsParserStatus {
 /// manipulated independantly of a particular CcsdsParser.
 #[derive(Debug, PartialEq, Clone)]
 pub struct CcsdsParserConfig {
-    /// The allowed APIDs list is either None, meaning any APID is valid,
-    /// or a Vec of allowed APIDs.
-    /// Note that if an APId is not in the allowed APID list, the packet
+    /// The allowed APIDs filter is either None, meaning any APID is valid,
+    /// or an ApidFilter of allowed APIDs, which may include ranges, masks, deny
+    /// rules and packet type specific rules.
+    /// Note that if an APId is not allowed by the filter, the packet
     /// is considered improperly formatted, rather then being a valid
     /// packet with an unexpected APID.
-    pub allowed_apids: Option<Vec<u16>>,
+    pub allowed_apids: Option<ApidFilter>,
 
     /// The max packet length is either None, meaning any packet length is valid,
     /// or a given number of bytes. This applies to the CCSDS packet length.
@@ -60,12 +109,25 @@ pub struct CcsdsParserConfig {
     /// formatted.
     pub min_packet_length: Option<u32>,
 
+    /// The APID lengths table maps APIDs to their expected packet length.
+    /// For APIDs in this table, the expected length is used in place of the
+    /// max and min packet length, and a packet with a different length is
+    /// considered improperly formatted. APIDs not in the table are checked
+    /// against the max and min packet length as usual.
+    pub apid_lengths: BTreeMap<u16, ExpectedLength>,
+
     /// The secondary header bit may or may not be set in a particular CCSDS 
     /// packet. For some projects, all packets have a secondary header. In this
     /// case, this flag can be set to indicate that a properly formatted packet
     /// must have this flag set.
     pub secondary_header_required: bool,
 
+    /// The secondary header sizes give the expected size of the secondary header for
+    /// APIDs or packet types. If a packet has its secondary header flag set, but its
+    /// data field is too short to hold the expected secondary header, it is considered
+    /// improperly formatted. These sizes can be created from a SecondaryHeaderRegistry.
+    pub secondary_header_sizes: SecondaryHeaderSizes,
+
     /// The sync bytes are a Vec of bytes that must proceed a packet for
     /// it to be valid. This is useful when there is a sync marker before each 
     /// packet.
@@ -103,7 +165,9 @@ impl CcsdsParserConfig {
             allowed_apids: None,
             max_packet_length: None,
             min_packet_length: None,
+            apid_lengths: BTreeMap::new(),
             secondary_header_required: false,
+            secondary_header_sizes: SecondaryHeaderSizes::new(),
             sync_bytes: Vec::new(),
             keep_sync: false,
             num_header_bytes: 0,
@@ -139,6 +203,11 @@ pub struct CcsdsParser {
     /// of invalid data.
     pub skipped_bytes: usize,
 
+    /// The validators are additional, project specific checks on each packet.
+    /// They are run in the order they were added, and the first to reject a packet
+    /// determines the reason code reported. See add_validator.
+    pub validators: Vec<PacketValidator>,
+
     /// This private field is used when running the parser as an iterator. This allows
     /// the parser to know if it is being called after apparently running out of bytes.
     reached_end: bool,
@@ -190,6 +259,7 @@ impl CcsdsParser {
             bytes: BytesMut::new(),
             config: CcsdsParserConfig::new(),
             skipped_bytes: 0,
+            validators: Vec::new(),
             reached_end: false,
         }
     }
@@ -200,26 +270,50 @@ impl CcsdsParser {
             bytes: BytesMut::new(),
             config: config,
             skipped_bytes: 0,
+            validators: Vec::new(),
             reached_end: false,
         }
     }
 
     /// Allow a particular APID. If the allowed_apids field is None, it is
-    /// turned into a Vec with a single element.
+    /// turned into an ApidFilter allowing only this APID.
     pub fn allow_apid(&mut self, apid: u16) {
         match self.config.allowed_apids {
             None => {
-                let mut apids = Vec::new();
-                apids.push(apid);
-                self.config.allowed_apids = Some(apids);
+                self.config.allowed_apids = Some(ApidFilter::from_apids(&[apid]));
             },
 
             Some(ref mut apids) => {
-                apids.push(apid);
+                apids.allow_apid(apid);
             },
         }
     }
 
+    /// Add an APID rule to the parser's filter. If the allowed_apids field is None,
+    /// it is turned into an ApidFilter allowing only the APIDs in this rule.
+    pub fn allow_apids(&mut self, rule: ApidRule) {
+        self.config.allowed_apids.get_or_insert_with(ApidFilter::new).allow(rule);
+    }
+
+    /// Deny the APIDs in an APID rule. If the allowed_apids field is None,
+    /// it is turned into an ApidFilter allowing every APID except those denied.
+    pub fn deny_apids(&mut self, rule: ApidRule) {
+        self.config.allowed_apids.get_or_insert_with(ApidFilter::allow_all).deny(rule);
+    }
+
+    /// Set the expected packet length for an APID. This overrides the max and min
+    /// packet length for packets with this APID.
+    pub fn expect_length(&mut self, apid: u16, expected_length: ExpectedLength) {
+        self.config.apid_lengths.insert(apid, expected_length);
+    }
+
+    /// Add a validator to the parser. Validators are run after all configured checks
+    /// pass, and can reject a packet with a reason code of the user's choosing.
+    pub fn add_validator<F>(&mut self, validator: F)
+        where F: Fn(&PrimaryHeader, &[u8]) -> Result<(), u32> + Send + 'static {
+        self.validators.push(Box::new(validator));
+    }
+
     /// The recv_bytes function allows the user to feed additional bytes to a
     /// parser. These may come from a byte stream such as TCP, where we may or may
     /// not get a full packet, or we may get multiple packets.
@@ -278,24 +372,71 @@ impl CcsdsParser {
             }
         }
 
-        // a packet length that exceeds the maximum is not a valid packet
-        if let Some(max_length) = self.config.max_packet_length {
-            if pri_header.packet_length() > max_length {
-                return CcsdsParserStatus::ExceedsMaxPacketLength;
-            }
+        if let Some(status) = self.length_status(&pri_header) {
+            return status;
         }
 
-        // a packet length that is smaller than the minimum length is not a valid packet
-        if let Some(min_length) = self.config.min_packet_length {
-            if pri_header.packet_length() < min_length {
-                return CcsdsParserStatus::BelowMinPacketLength;
-            }
+        if self.bytes.len() < self.full_packet_length() {
+            return CcsdsParserStatus::NotEnoughBytesPacketLength;
         }
 
-        if self.bytes.len() < self.full_packet_length() {
+        let start_of_packet = self.config.sync_bytes.len() + self.config.num_header_bytes as usize;
+        let end_of_packet = start_of_packet + pri_header.packet_length() as usize;
+
+        self.contents_status(&pri_header, &self.bytes[start_of_packet..end_of_packet])
+    }
+
+    /// The status of a complete packet which did not come from this parser's byte stream,
+    /// such as a packet extracted from a transfer frame. The packet starts with its primary
+    /// header, without any sync, header or footer bytes, and is checked in the same way as
+    /// the parser's current packet. Any bytes after the packet are ignored.
+    pub fn packet_status(&self, packet: &[u8]) -> CcsdsParserStatus {
+        let pri_header = match PrimaryHeader::from_slice(packet) {
+            Some(pri_header) => pri_header,
+            None => return CcsdsParserStatus::NotEnoughBytesForHeader,
+        };
+
+        if let Some(status) = self.length_status(&pri_header) {
+            return status;
+        }
+
+        let packet_length = pri_header.packet_length() as usize;
+        if packet.len() < packet_length {
             return CcsdsParserStatus::NotEnoughBytesPacketLength;
         }
 
+        self.contents_status(&pri_header, &packet[0..packet_length])
+    }
+
+    /// Check a packet's length against the configured lengths, returning the status
+    /// of a packet with a length that is not allowed.
+    fn length_status(&self, pri_header: &PrimaryHeader) -> Option<CcsdsParserStatus> {
+        if let Some(expected_length) = self.config.apid_lengths.get(&pri_header.control.apid()) {
+            // if the APID has an expected length, it replaces the max and min lengths
+            if !expected_length.matches(pri_header.packet_length()) {
+                return Some(CcsdsParserStatus::PacketLengthMismatch);
+            }
+        } else {
+            // a packet length that exceeds the maximum is not a valid packet
+            if let Some(max_length) = self.config.max_packet_length {
+                if pri_header.packet_length() > max_length {
+                    return Some(CcsdsParserStatus::ExceedsMaxPacketLength);
+                }
+            }
+
+            // a packet length that is smaller than the minimum length is not a valid packet
+            if let Some(min_length) = self.config.min_packet_length {
+                if pri_header.packet_length() < min_length {
+                    return Some(CcsdsParserStatus::BelowMinPacketLength);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Check the header and contents of a complete packet, which starts with its primary header.
+    fn contents_status(&self, pri_header: &PrimaryHeader, packet: &[u8]) -> CcsdsParserStatus {
         // if the version is not 0, assume that the packet is malformed.
         if pri_header.control.version() as u8 != CCSDS_VERSION {
             return CcsdsParserStatus::InvalidCcsdsVersion;
@@ -308,15 +449,29 @@ impl CcsdsParser {
             return CcsdsParserStatus::SecondaryHeaderInvalid;
         }
 
+        // if the packet claims to have a secondary header, it must be long enough to hold it
+        if let Some(secondary_header_size) = self.config.secondary_header_sizes.size_for(pri_header) {
+            if pri_header.data_length() < secondary_header_size {
+                return CcsdsParserStatus::SecondaryHeaderTooShort;
+            }
+        }
+
         // check if the APID is allowed
-        if let Some(ref apid_list) = self.config.allowed_apids {
-            if !apid_list.contains(&pri_header.control.apid()) {
+        if let Some(ref apid_filter) = self.config.allowed_apids {
+            if !apid_filter.allows_header(pri_header) {
                 // enough bytes, APID not allowed
                 //self.bytes.advance(pri_header.packet_length() as usize);
                 return CcsdsParserStatus::ApidNotAllowed;
             }
         }
 
+        // run any user provided checks on the packet itself
+        for validator in self.validators.iter() {
+            if let Err(reason) = validator(pri_header, packet) {
+                return CcsdsParserStatus::RejectedByValidator(reason);
+            }
+        }
+
         CcsdsParserStatus::ValidPacket
     }
 
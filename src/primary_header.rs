@@ -293,6 +293,19 @@ impl PrimaryHeader {
         }
     }
 
+    /// Get the raw bytes of the PrimaryHeader, as sent at the start of a packet.
+    /// This is the inverse of new.
+    pub fn to_bytes(&self) -> [u8;6] {
+        let mut bytes: [u8;6] = [0; 6];
+
+        // copy the primary header byte-for-byte into the array
+        bytes[0..2].copy_from_slice(&self.control.0);
+        bytes[2..4].copy_from_slice(&self.sequence.0);
+        bytes[4..6].copy_from_slice(&self.length.0);
+
+        return bytes;
+    }
+
     /// Get the length of the packet in bytes, including the primary header.
     /// The length is returned as a u32 because the CCSDS standard allows the total 
     /// packet length to exceed 65535.
@@ -308,8 +321,13 @@ impl PrimaryHeader {
     }
 
     /// Set the length of the packet in bytes, including the primary header.
+    /// This is the inverse of packet_length, so the length field is set to
+    /// the packet length less the primary header and the 1 byte minimum data length.
+    /// No packet can be shorter than CCSDS_MIN_LENGTH, so shorter lengths are clamped:
+    /// the length field is set to 0 and packet_length then returns CCSDS_MIN_LENGTH.
     pub fn set_packet_length(&mut self, packet_length: u16) {
-        BigEndian::write_u16(&mut self.length.0, packet_length);
+        let length_field = (packet_length as u32).saturating_sub(CCSDS_MIN_LENGTH);
+        self.length.set_length_field(length_field as u16);
     }
 }
 
@@ -0,0 +1,133 @@
+/*!
+The pus module provides the secondary headers of the ECSS Packet Utilization Standard
+(PUS), ECSS-E-ST-70-41C. PUS packets are CCSDS space packets whose secondary header
+gives the service type and subtype of the packet, along with a time for telemetry.
+
+Several fields of the PUS headers have mission defined sizes, so each header is
+read and written with a configuration that gives these sizes and the time code format.
+*/
+use byteorder::{BigEndian, ByteOrder};
+
+use time::*;
+use primary_header::*;
+use crc::*;
+
+pub mod tm;
+pub mod tc;
+pub mod verification;
+pub mod services;
+pub mod pus_a;
+
+pub use self::tm::*;
+pub use self::tc::*;
+pub use self::verification::*;
+pub use self::services::*;
+pub use self::pus_a::*;
+
+
+/// The PUS version number of packets defined by ECSS-E-ST-70-41C (PUS-C).
+pub const PUS_C_VERSION: u8 = 2;
+
+/// The number of bytes in the Packet Error Control field at the end of a PUS packet.
+pub const PUS_PEC_BYTES: usize = 2;
+
+/// A PusError describes why a PUS header could not be read or written.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PusError {
+    /// There were not enough bytes to read or write the header.
+    NotEnoughBytes,
+
+    /// The packet's type does not match the header, such as a telemetry header
+    /// read from a command packet.
+    WrongPacketType,
+
+    /// The packet does not have its secondary header flag set.
+    NoSecondaryHeader,
+
+    /// The header's PUS version number is not supported.
+    InvalidVersion(u8),
+
+    /// The configuration is not valid, such as a field width the standard does not allow.
+    InvalidConfig,
+
+    /// A field's value does not fit in the width given by the configuration.
+    FieldOutOfRange,
+
+    /// The packet's Packet Error Control field does not match its contents.
+    InvalidPec,
+
+    /// A packet's message subtype is not defined for its service type, given as
+    /// the service type and message subtype.
+    UnknownSubtype(u8, u8),
+
+    /// A field can not be represented in the header being converted to, such as
+    /// a value too large for the field's width in the other header.
+    NotRepresentable(PusField),
+
+    /// The header's time could not be read or written.
+    Time(TimeError),
+}
+
+/// A PusField names a field of a PUS secondary header, to report which field could
+/// not be converted between PUS versions.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PusField {
+    TimeReferenceStatus,
+    MessageTypeCounter,
+    DestinationId,
+    SourceId,
+}
+
+impl From<TimeError> for PusError {
+    fn from(err: TimeError) -> PusError {
+        PusError::Time(err)
+    }
+}
+
+
+/// Append the Packet Error Control field, a CRC-16-CCITT of the packet, to the end
+/// of a packet. The packet's length field should already include the field.
+pub fn append_pec(packet: &mut Vec<u8>) {
+    let pec = crc16_ccitt(packet);
+    packet.push((pec >> 8) as u8);
+    packet.push(pec as u8);
+}
+
+/// Check the Packet Error Control field of a packet, which is the last two bytes of
+/// the packet given by its primary header's length. Bytes after the packet are ignored.
+pub fn has_valid_pec(packet: &[u8]) -> bool {
+    match PrimaryHeader::from_slice(packet) {
+        Some(pri_header) => {
+            let packet_length = pri_header.packet_length() as usize;
+            packet.len() >= packet_length && crc16_ccitt_is_valid(&packet[0..packet_length])
+        },
+
+        None => false,
+    }
+}
+
+
+/// Read a big endian field of 0, 1 or 2 bytes. A field of 0 bytes is always 0.
+pub(crate) fn read_field(bytes: &[u8], num_bytes: usize) -> u16 {
+    match num_bytes {
+        0 => 0,
+        1 => bytes[0] as u16,
+        _ => BigEndian::read_u16(bytes),
+    }
+}
+
+/// Write a big endian field of 0, 1 or 2 bytes, checking that the value fits.
+pub(crate) fn write_field(bytes: &mut [u8], num_bytes: usize, value: u16) -> Result<(), PusError> {
+    match num_bytes {
+        0 if value == 0 => Ok(()),
+        1 if value <= 0xFF => {
+            bytes[0] = value as u8;
+            Ok(())
+        },
+        2 => {
+            BigEndian::write_u16(bytes, value);
+            Ok(())
+        },
+        _ => Err(PusError::FieldOutOfRange),
+    }
+}
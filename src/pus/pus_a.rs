@@ -0,0 +1,464 @@
+use time::*;
+use pus::*;
+
+
+/// The PUS version number of packets defined by ECSS-E-70-41A (PUS-A).
+pub const PUS_A_VERSION: u8 = 1;
+
+/// The size of the fixed part of a PUS-A secondary header: the version, the
+/// acknowledgement flags for telecommands, the service type and message subtype.
+pub const PUS_A_FIXED_HEADER_BYTES: usize = 3;
+
+/// A PusATmConfig gives the mission defined parts of the PUS-A telemetry secondary
+/// header. The packet subcounter and destination ID are optional in PUS-A, and are
+/// left out of the header when their width is 0.
+///
+/// PUS-A also allows the time to be left out, but a PUS-C header always has a
+/// time, so only headers with a time are supported.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusATmConfig<F: TimeCodeFormat> {
+    /// The number of bytes in the packet subcounter, from 0 to 2.
+    pub packet_subcounter_bytes: usize,
+
+    /// The number of bytes in the destination ID, from 0 to 2.
+    pub destination_id_bytes: usize,
+
+    /// The format of the time field.
+    pub time_format: F,
+
+    /// The number of spare bytes following the time. Spare bytes are written as 0.
+    pub spare_bytes: usize,
+}
+
+impl<F: TimeCodeFormat> PusATmConfig<F> {
+    /// Create a configuration with a 1 byte packet subcounter, no destination ID,
+    /// and no spare bytes.
+    pub fn new(time_format: F) -> PusATmConfig<F> {
+        PusATmConfig {
+            packet_subcounter_bytes: 1,
+            destination_id_bytes: 0,
+            time_format,
+            spare_bytes: 0,
+        }
+    }
+
+    /// Check that the field widths are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.packet_subcounter_bytes <= 2 && self.destination_id_bytes <= 2
+    }
+
+    /// The number of bytes in a secondary header with this configuration.
+    pub fn header_len(&self) -> usize {
+        PUS_A_FIXED_HEADER_BYTES +
+            self.packet_subcounter_bytes +
+            self.destination_id_bytes +
+            self.time_format.encoded_len() +
+            self.spare_bytes
+    }
+}
+
+/// A PusATmHeader is the secondary header of a PUS-A telemetry packet.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PusATmHeader<F: TimeCodeFormat> {
+    /// The TM packet PUS version number, which is 1 for PUS-A.
+    pub version: u8,
+
+    /// The service type of the report.
+    pub service_type: u8,
+
+    /// The service subtype of the report.
+    pub message_subtype: u8,
+
+    /// The packet subcounter, counting reports sent by the application process.
+    pub packet_subcounter: u16,
+
+    /// The ID of the application process the report is sent to.
+    pub destination_id: u16,
+
+    /// The time the report was generated.
+    pub time: F::Time,
+}
+
+impl<F: TimeCodeFormat> PusATmHeader<F> {
+    /// Read a header from the start of a byte slice, which should start just after
+    /// the primary header.
+    pub fn decode(config: &PusATmConfig<F>, bytes: &[u8]) -> Result<PusATmHeader<F>, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if bytes.len() < config.header_len() {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        let version = (bytes[0] >> 4) & 0x07;
+        if version != PUS_A_VERSION {
+            return Err(PusError::InvalidVersion(version));
+        }
+
+        let mut index = PUS_A_FIXED_HEADER_BYTES;
+        let packet_subcounter = read_field(&bytes[index..], config.packet_subcounter_bytes);
+        index += config.packet_subcounter_bytes;
+
+        let destination_id = read_field(&bytes[index..], config.destination_id_bytes);
+        index += config.destination_id_bytes;
+
+        let (time, _) = config.time_format.decode(&bytes[index..])?;
+
+        Ok(PusATmHeader {
+            version,
+            service_type: bytes[1],
+            message_subtype: bytes[2],
+            packet_subcounter,
+            destination_id,
+            time,
+        })
+    }
+
+    /// Write the header to the start of a byte slice, returning the number of bytes written.
+    pub fn encode(&self, config: &PusATmConfig<F>, bytes: &mut [u8]) -> Result<usize, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        let header_len = config.header_len();
+        if bytes.len() < header_len {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        if self.version > 0x07 {
+            return Err(PusError::FieldOutOfRange);
+        }
+
+        bytes[0] = self.version << 4;
+        bytes[1] = self.service_type;
+        bytes[2] = self.message_subtype;
+
+        let mut index = PUS_A_FIXED_HEADER_BYTES;
+        write_field(&mut bytes[index..], config.packet_subcounter_bytes, self.packet_subcounter)?;
+        index += config.packet_subcounter_bytes;
+
+        write_field(&mut bytes[index..], config.destination_id_bytes, self.destination_id)?;
+        index += config.destination_id_bytes;
+
+        index += config.time_format.encode(&self.time, &mut bytes[index..])?;
+
+        for byte in bytes[index..header_len].iter_mut() {
+            *byte = 0;
+        }
+
+        Ok(header_len)
+    }
+
+    /// Convert to a PUS-C header with the given configuration. The packet subcounter
+    /// becomes the message type counter, and the time reference status is 0.
+    pub fn to_pus_c(&self, config: &PusTmConfig<F>) -> Result<PusTmHeader<F>, PusError> {
+        check_field(self.packet_subcounter, config.message_counter_bytes, PusField::MessageTypeCounter)?;
+        check_field(self.destination_id, config.destination_id_bytes, PusField::DestinationId)?;
+
+        Ok(PusTmHeader {
+            version: PUS_C_VERSION,
+            time_reference_status: 0,
+            service_type: self.service_type,
+            message_subtype: self.message_subtype,
+            message_type_counter: self.packet_subcounter,
+            destination_id: self.destination_id,
+            time: self.time,
+        })
+    }
+
+    /// Convert from a PUS-C header, for a PUS-A header with the given configuration.
+    /// PUS-A has no time reference status, so it must be 0.
+    pub fn from_pus_c(header: &PusTmHeader<F>, config: &PusATmConfig<F>) -> Result<PusATmHeader<F>, PusError> {
+        if header.time_reference_status != 0 {
+            return Err(PusError::NotRepresentable(PusField::TimeReferenceStatus));
+        }
+        check_field(header.message_type_counter, config.packet_subcounter_bytes, PusField::MessageTypeCounter)?;
+        check_field(header.destination_id, config.destination_id_bytes, PusField::DestinationId)?;
+
+        Ok(PusATmHeader {
+            version: PUS_A_VERSION,
+            service_type: header.service_type,
+            message_subtype: header.message_subtype,
+            packet_subcounter: header.message_type_counter,
+            destination_id: header.destination_id,
+            time: header.time,
+        })
+    }
+}
+
+
+/// A PusATcConfig gives the mission defined parts of the PUS-A telecommand secondary
+/// header. The source ID is optional in PUS-A, and is left out when its width is 0.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusATcConfig {
+    /// The number of bytes in the source ID, from 0 to 2.
+    pub source_id_bytes: usize,
+
+    /// The number of spare bytes following the source ID. Spare bytes are written as 0.
+    pub spare_bytes: usize,
+}
+
+impl Default for PusATcConfig {
+    fn default() -> PusATcConfig {
+        PusATcConfig::new()
+    }
+}
+
+impl PusATcConfig {
+    /// Create a configuration with a 1 byte source ID and no spare bytes.
+    pub fn new() -> PusATcConfig {
+        PusATcConfig {
+            source_id_bytes: 1,
+            spare_bytes: 0,
+        }
+    }
+
+    /// Check that the field widths are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.source_id_bytes <= 2
+    }
+
+    /// The number of bytes in a secondary header with this configuration.
+    pub fn header_len(&self) -> usize {
+        PUS_A_FIXED_HEADER_BYTES + self.source_id_bytes + self.spare_bytes
+    }
+}
+
+/// A PusATcHeader is the secondary header of a PUS-A telecommand packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusATcHeader {
+    /// The TC packet PUS version number, which is 1 for PUS-A.
+    pub version: u8,
+
+    /// The acknowledgements requested for the command.
+    pub ack_flags: AckFlags,
+
+    /// The service type of the request.
+    pub service_type: u8,
+
+    /// The service subtype of the request.
+    pub message_subtype: u8,
+
+    /// The ID of the source of the request.
+    pub source_id: u16,
+}
+
+impl PusATcHeader {
+    /// Read a header from the start of a byte slice, which should start just after
+    /// the primary header.
+    pub fn decode(config: &PusATcConfig, bytes: &[u8]) -> Result<PusATcHeader, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if bytes.len() < config.header_len() {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        let version = (bytes[0] >> 4) & 0x07;
+        if version != PUS_A_VERSION {
+            return Err(PusError::InvalidVersion(version));
+        }
+
+        Ok(PusATcHeader {
+            version,
+            ack_flags: AckFlags::from_bits(bytes[0] & 0x0F),
+            service_type: bytes[1],
+            message_subtype: bytes[2],
+            source_id: read_field(&bytes[PUS_A_FIXED_HEADER_BYTES..], config.source_id_bytes),
+        })
+    }
+
+    /// Write the header to the start of a byte slice, returning the number of bytes written.
+    pub fn encode(&self, config: &PusATcConfig, bytes: &mut [u8]) -> Result<usize, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        let header_len = config.header_len();
+        if bytes.len() < header_len {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        if self.version > 0x07 {
+            return Err(PusError::FieldOutOfRange);
+        }
+
+        bytes[0] = (self.version << 4) | self.ack_flags.bits();
+        bytes[1] = self.service_type;
+        bytes[2] = self.message_subtype;
+
+        let index = PUS_A_FIXED_HEADER_BYTES;
+        write_field(&mut bytes[index..], config.source_id_bytes, self.source_id)?;
+
+        for byte in bytes[index + config.source_id_bytes..header_len].iter_mut() {
+            *byte = 0;
+        }
+
+        Ok(header_len)
+    }
+
+    /// Convert to a PUS-C header with the given configuration.
+    pub fn to_pus_c(&self, config: &PusTcConfig) -> Result<PusTcHeader, PusError> {
+        check_field(self.source_id, config.source_id_bytes, PusField::SourceId)?;
+
+        Ok(PusTcHeader {
+            version: PUS_C_VERSION,
+            ack_flags: self.ack_flags,
+            service_type: self.service_type,
+            message_subtype: self.message_subtype,
+            source_id: self.source_id,
+        })
+    }
+
+    /// Convert from a PUS-C header, for a PUS-A header with the given configuration.
+    pub fn from_pus_c(header: &PusTcHeader, config: &PusATcConfig) -> Result<PusATcHeader, PusError> {
+        check_field(header.source_id, config.source_id_bytes, PusField::SourceId)?;
+
+        Ok(PusATcHeader {
+            version: PUS_A_VERSION,
+            ack_flags: header.ack_flags,
+            service_type: header.service_type,
+            message_subtype: header.message_subtype,
+            source_id: header.source_id,
+        })
+    }
+}
+
+
+/// Convert a PUS-A telemetry packet to PUS-C. If has_pec is set, the packet's Packet
+/// Error Control field is checked, and a new one is written for the converted packet.
+pub fn pus_a_tm_to_c<F: TimeCodeFormat>(packet: &[u8],
+                                        from: &PusATmConfig<F>,
+                                        to: &PusTmConfig<F>,
+                                        has_pec: bool) -> Result<Vec<u8>, PusError> {
+    let (pri_header, user_data) = split_packet(packet, PacketType::Data, from.header_len(), has_pec)?;
+    let header = PusATmHeader::decode(from, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])?.to_pus_c(to)?;
+
+    let mut header_bytes = vec![0; to.header_len()];
+    header.encode(to, &mut header_bytes)?;
+
+    build_packet(pri_header, &header_bytes, user_data, has_pec)
+}
+
+/// Convert a PUS-C telemetry packet to PUS-A. If has_pec is set, the packet's Packet
+/// Error Control field is checked, and a new one is written for the converted packet.
+pub fn pus_c_tm_to_a<F: TimeCodeFormat>(packet: &[u8],
+                                        from: &PusTmConfig<F>,
+                                        to: &PusATmConfig<F>,
+                                        has_pec: bool) -> Result<Vec<u8>, PusError> {
+    let (pri_header, user_data) = split_packet(packet, PacketType::Data, from.header_len(), has_pec)?;
+    let header = PusTmHeader::decode(from, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])?;
+    let header = PusATmHeader::from_pus_c(&header, to)?;
+
+    let mut header_bytes = vec![0; to.header_len()];
+    header.encode(to, &mut header_bytes)?;
+
+    build_packet(pri_header, &header_bytes, user_data, has_pec)
+}
+
+/// Convert a PUS-A telecommand packet to PUS-C. If has_pec is set, the packet's Packet
+/// Error Control field is checked, and a new one is written for the converted packet.
+pub fn pus_a_tc_to_c(packet: &[u8],
+                     from: &PusATcConfig,
+                     to: &PusTcConfig,
+                     has_pec: bool) -> Result<Vec<u8>, PusError> {
+    let (pri_header, user_data) = split_packet(packet, PacketType::Command, from.header_len(), has_pec)?;
+    let header = PusATcHeader::decode(from, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])?.to_pus_c(to)?;
+
+    let mut header_bytes = vec![0; to.header_len()];
+    header.encode(to, &mut header_bytes)?;
+
+    build_packet(pri_header, &header_bytes, user_data, has_pec)
+}
+
+/// Convert a PUS-C telecommand packet to PUS-A. If has_pec is set, the packet's Packet
+/// Error Control field is checked, and a new one is written for the converted packet.
+pub fn pus_c_tc_to_a(packet: &[u8],
+                     from: &PusTcConfig,
+                     to: &PusATcConfig,
+                     has_pec: bool) -> Result<Vec<u8>, PusError> {
+    let (pri_header, user_data) = split_packet(packet, PacketType::Command, from.header_len(), has_pec)?;
+    let header = PusTcHeader::decode(from, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])?;
+    let header = PusATcHeader::from_pus_c(&header, to)?;
+
+    let mut header_bytes = vec![0; to.header_len()];
+    header.encode(to, &mut header_bytes)?;
+
+    build_packet(pri_header, &header_bytes, user_data, has_pec)
+}
+
+
+/// Check that a field value can be written with the given width.
+fn check_field(value: u16, num_bytes: usize, field: PusField) -> Result<(), PusError> {
+    let fits = match num_bytes {
+        0 => value == 0,
+        1 => value <= 0xFF,
+        _ => true,
+    };
+
+    if fits { Ok(()) } else { Err(PusError::NotRepresentable(field)) }
+}
+
+/// Split a PUS packet into its primary header and the user data following a secondary
+/// header of the given length, checking the packet type and PEC.
+fn split_packet(packet: &[u8],
+                packet_type: PacketType,
+                header_len: usize,
+                has_pec: bool) -> Result<(PrimaryHeader, &[u8]), PusError> {
+    let pri_header = PrimaryHeader::from_slice(packet).ok_or(PusError::NotEnoughBytes)?;
+
+    if pri_header.control.packet_type() != packet_type {
+        return Err(PusError::WrongPacketType);
+    }
+
+    if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+        return Err(PusError::NoSecondaryHeader);
+    }
+
+    let mut end = pri_header.packet_length() as usize;
+    if packet.len() < end {
+        return Err(PusError::NotEnoughBytes);
+    }
+
+    if has_pec {
+        if !has_valid_pec(packet) {
+            return Err(PusError::InvalidPec);
+        }
+        end -= PUS_PEC_BYTES;
+    }
+
+    let start = CCSDS_PRI_HEADER_SIZE_BYTES as usize + header_len;
+    if end < start {
+        return Err(PusError::NotEnoughBytes);
+    }
+
+    Ok((pri_header, &packet[start..end]))
+}
+
+/// Build a packet from a primary header, a new secondary header and the user data,
+/// updating the primary header's length and appending a new PEC if requested.
+fn build_packet(mut pri_header: PrimaryHeader,
+                header_bytes: &[u8],
+                user_data: &[u8],
+                has_pec: bool) -> Result<Vec<u8>, PusError> {
+    let pec_len = if has_pec { PUS_PEC_BYTES } else { 0 };
+    let packet_length = CCSDS_PRI_HEADER_SIZE_BYTES as usize + header_bytes.len() + user_data.len() + pec_len;
+
+    if packet_length > u16::MAX as usize {
+        return Err(PusError::FieldOutOfRange);
+    }
+    pri_header.set_packet_length(packet_length as u16);
+
+    let mut packet = Vec::with_capacity(packet_length);
+    packet.extend_from_slice(&pri_header.to_bytes());
+    packet.extend_from_slice(header_bytes);
+    packet.extend_from_slice(user_data);
+
+    if has_pec {
+        append_pec(&mut packet);
+    }
+
+    Ok(packet)
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+
+use time::*;
+use pus::*;
+
+
+/// The service type of PUS service 3, housekeeping.
+pub const PUS_SERVICE_HOUSEKEEPING: u8 = 3;
+
+/// The service type of PUS service 5, event reporting.
+pub const PUS_SERVICE_EVENT: u8 = 5;
+
+/// The service type of PUS service 17, test.
+pub const PUS_SERVICE_TEST: u8 = 17;
+
+/// The size of the APID field of on-board connection test reports.
+pub const PUS_TEST_APID_BYTES: usize = 2;
+
+
+/// A HousekeepingReport is the user data of a service 3 housekeeping or diagnostic
+/// parameter report (TM[3,25] and TM[3,26]).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HousekeepingReport {
+    /// Whether this is a diagnostic parameter report rather than a housekeeping
+    /// parameter report.
+    pub diagnostic: bool,
+
+    /// The ID of the report structure, which defines the parameters in the report.
+    pub structure_id: u16,
+
+    /// The parameter values, in the order given by the report structure.
+    pub parameters: Vec<u8>,
+}
+
+impl HousekeepingReport {
+    /// Split the parameter block into the values of each parameter, given the size
+    /// in bytes of each parameter in the report structure. If the parameter block
+    /// does not match the sizes then None is returned.
+    pub fn parameter_values(&self, sizes: &[usize]) -> Option<Vec<&[u8]>> {
+        if sizes.iter().sum::<usize>() != self.parameters.len() {
+            return None;
+        }
+
+        let mut values = Vec::with_capacity(sizes.len());
+        let mut index = 0;
+        for size in sizes {
+            values.push(&self.parameters[index..index + size]);
+            index += size;
+        }
+
+        Some(values)
+    }
+}
+
+/// The EventSeverity of a service 5 event report is given by its message subtype.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum EventSeverity {
+    /// An informative event report, TM[5,1].
+    Informative,
+
+    /// A low severity anomaly report, TM[5,2].
+    Low,
+
+    /// A medium severity anomaly report, TM[5,3].
+    Medium,
+
+    /// A high severity anomaly report, TM[5,4].
+    High,
+}
+
+impl EventSeverity {
+    /// The severity of an event report with the given message subtype.
+    pub fn from_subtype(message_subtype: u8) -> Option<EventSeverity> {
+        match message_subtype {
+            1 => Some(EventSeverity::Informative),
+            2 => Some(EventSeverity::Low),
+            3 => Some(EventSeverity::Medium),
+            4 => Some(EventSeverity::High),
+            _ => None,
+        }
+    }
+
+    /// The message subtype of event reports with this severity.
+    pub fn subtype(&self) -> u8 {
+        match *self {
+            EventSeverity::Informative => 1,
+            EventSeverity::Low => 2,
+            EventSeverity::Medium => 3,
+            EventSeverity::High => 4,
+        }
+    }
+}
+
+/// An EventReport is the user data of a service 5 event report.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EventReport {
+    /// The severity of the event.
+    pub severity: EventSeverity,
+
+    /// The event definition ID.
+    pub event_id: u16,
+
+    /// The auxiliary data of the event, defined by the mission for each event.
+    pub data: Vec<u8>,
+}
+
+/// A TestReport is a service 17 test report.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TestReport {
+    /// An are-you-alive connection test report, TM[17,2].
+    AreYouAlive,
+
+    /// An on-board connection test report, TM[17,4], giving the APID of the
+    /// application process that was tested.
+    ConnectionTest { apid: u16 },
+}
+
+
+/// A PusReport is the decoded user data of a PUS telemetry packet. Reports of
+/// services with a decoder registered by the mission are given as Custom, and
+/// reports of any other service are given as Unknown with their raw user data.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PusReport<C> {
+    Verification(VerificationReport),
+    Housekeeping(HousekeepingReport),
+    Event(EventReport),
+    Test(TestReport),
+    Custom(C),
+    Unknown { service_type: u8, message_subtype: u8, data: Vec<u8> },
+}
+
+/// A ServiceDecoder decodes the user data of a mission defined service, given the
+/// message subtype and user data of a report.
+pub type ServiceDecoder<C> = Box<dyn Fn(u8, &[u8]) -> Result<C, PusError> + Send>;
+
+/// A ServiceRegistry decodes the user data of PUS telemetry packets by service type.
+/// Services 1, 3, 5 and 17 are decoded by the registry, using the mission defined
+/// field widths it holds. Missions can register decoders for their own services,
+/// or to replace the decoding of a standard service, producing reports of type C.
+pub struct ServiceRegistry<C> {
+    /// The number of bytes in the step ID of service 1 progress reports.
+    pub step_id_bytes: usize,
+
+    /// The number of bytes in the failure code of service 1 failure reports.
+    pub failure_code_bytes: usize,
+
+    /// The number of bytes in the structure ID of service 3 reports.
+    pub structure_id_bytes: usize,
+
+    /// The number of bytes in the event definition ID of service 5 reports.
+    pub event_id_bytes: usize,
+
+    decoders: BTreeMap<u8, ServiceDecoder<C>>,
+}
+
+impl<C> Default for ServiceRegistry<C> {
+    fn default() -> ServiceRegistry<C> {
+        ServiceRegistry::new()
+    }
+}
+
+impl<C> ServiceRegistry<C> {
+    /// Create a registry with no custom decoders. Reports are expected to have a 1 byte
+    /// step ID, a 2 byte failure code, a 2 byte structure ID and a 2 byte event ID.
+    pub fn new() -> ServiceRegistry<C> {
+        ServiceRegistry {
+            step_id_bytes: 1,
+            failure_code_bytes: 2,
+            structure_id_bytes: 2,
+            event_id_bytes: 2,
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Register a decoder for a service type, replacing any previous decoder.
+    pub fn register<F>(&mut self, service_type: u8, decoder: F)
+        where F: Fn(u8, &[u8]) -> Result<C, PusError> + Send + 'static {
+        self.decoders.insert(service_type, Box::new(decoder));
+    }
+
+    /// Decode the user data of a report with the given service type and message subtype.
+    pub fn decode(&self, service_type: u8, message_subtype: u8, user_data: &[u8]) -> Result<PusReport<C>, PusError> {
+        if let Some(decoder) = self.decoders.get(&service_type) {
+            return decoder(message_subtype, user_data).map(PusReport::Custom);
+        }
+
+        match service_type {
+            PUS_SERVICE_VERIFICATION => {
+                VerificationReport::decode(message_subtype, user_data, self.step_id_bytes, self.failure_code_bytes)
+                    .map(PusReport::Verification)
+            },
+
+            PUS_SERVICE_HOUSEKEEPING => self.decode_housekeeping(message_subtype, user_data),
+
+            PUS_SERVICE_EVENT => self.decode_event(message_subtype, user_data),
+
+            PUS_SERVICE_TEST => decode_test(message_subtype, user_data),
+
+            _ => Ok(PusReport::Unknown { service_type, message_subtype, data: user_data.to_vec() }),
+        }
+    }
+
+    /// Decode a telemetry packet, starting with its primary header, using the given
+    /// secondary header configuration.
+    pub fn decode_packet<F: TimeCodeFormat>(&self,
+                                            tm_config: &PusTmConfig<F>,
+                                            packet: &[u8],
+                                            has_pec: bool) -> Result<(PusTmHeader<F>, PusReport<C>), PusError> {
+        let tm_header = PusTmHeader::from_packet(tm_config, packet)?;
+        let user_data = pus_tm_user_data(tm_config, packet, has_pec)?;
+        let report = self.decode(tm_header.service_type, tm_header.message_subtype, user_data)?;
+
+        Ok((tm_header, report))
+    }
+
+    fn decode_housekeeping(&self, message_subtype: u8, user_data: &[u8]) -> Result<PusReport<C>, PusError> {
+        let diagnostic = match message_subtype {
+            25 => false,
+            26 => true,
+            _ => return Err(PusError::UnknownSubtype(PUS_SERVICE_HOUSEKEEPING, message_subtype)),
+        };
+
+        if self.structure_id_bytes > 2 {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if user_data.len() < self.structure_id_bytes {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        Ok(PusReport::Housekeeping(HousekeepingReport {
+            diagnostic,
+            structure_id: read_field(user_data, self.structure_id_bytes),
+            parameters: user_data[self.structure_id_bytes..].to_vec(),
+        }))
+    }
+
+    fn decode_event(&self, message_subtype: u8, user_data: &[u8]) -> Result<PusReport<C>, PusError> {
+        let severity = EventSeverity::from_subtype(message_subtype)
+                           .ok_or(PusError::UnknownSubtype(PUS_SERVICE_EVENT, message_subtype))?;
+
+        if self.event_id_bytes > 2 {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if user_data.len() < self.event_id_bytes {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        Ok(PusReport::Event(EventReport {
+            severity,
+            event_id: read_field(user_data, self.event_id_bytes),
+            data: user_data[self.event_id_bytes..].to_vec(),
+        }))
+    }
+}
+
+fn decode_test<C>(message_subtype: u8, user_data: &[u8]) -> Result<PusReport<C>, PusError> {
+    match message_subtype {
+        2 => Ok(PusReport::Test(TestReport::AreYouAlive)),
+
+        4 => {
+            if user_data.len() < PUS_TEST_APID_BYTES {
+                return Err(PusError::NotEnoughBytes);
+            }
+            let apid = read_field(user_data, PUS_TEST_APID_BYTES) & 0x07FF;
+            Ok(PusReport::Test(TestReport::ConnectionTest { apid }))
+        },
+
+        _ => Err(PusError::UnknownSubtype(PUS_SERVICE_TEST, message_subtype)),
+    }
+}
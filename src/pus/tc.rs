@@ -0,0 +1,253 @@
+use primary_header::*;
+use secondary_header::*;
+use pus::*;
+
+
+/// The size of the fixed part of a PUS-C telecommand secondary header: the version,
+/// acknowledgement flags, service type and message subtype.
+pub const PUS_TC_FIXED_HEADER_BYTES: usize = 3;
+
+/// The AckFlags of a telecommand request reports of its progress through service 1,
+/// request verification.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct AckFlags {
+    /// Report successful acceptance of the request.
+    pub acceptance: bool,
+
+    /// Report successful start of execution.
+    pub start: bool,
+
+    /// Report successful progress of execution.
+    pub progress: bool,
+
+    /// Report successful completion of execution.
+    pub completion: bool,
+}
+
+impl AckFlags {
+    /// Request every acknowledgement.
+    pub fn all() -> AckFlags {
+        AckFlags::from_bits(0x0F)
+    }
+
+    /// Request no acknowledgements. Failures are reported regardless of the flags.
+    pub fn none() -> AckFlags {
+        AckFlags::from_bits(0x00)
+    }
+
+    /// Read the flags from the 4 bit field of the secondary header, where acceptance
+    /// is the least significant bit and completion the most significant bit.
+    pub fn from_bits(bits: u8) -> AckFlags {
+        AckFlags {
+            acceptance: (bits & 0x01) != 0,
+            start: (bits & 0x02) != 0,
+            progress: (bits & 0x04) != 0,
+            completion: (bits & 0x08) != 0,
+        }
+    }
+
+    /// The 4 bit field of the secondary header for these flags.
+    pub fn bits(&self) -> u8 {
+        (self.acceptance as u8) |
+            ((self.start as u8) << 1) |
+            ((self.progress as u8) << 2) |
+            ((self.completion as u8) << 3)
+    }
+}
+
+/// A PusTcConfig gives the mission defined parts of the PUS-C telecommand secondary
+/// header: the width of the source ID and any spare bytes.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusTcConfig {
+    /// The number of bytes in the source ID, from 0 to 2. The standard uses 2 bytes.
+    pub source_id_bytes: usize,
+
+    /// The number of spare bytes following the source ID, used to pad the header to
+    /// a mission defined size. Spare bytes are written as 0.
+    pub spare_bytes: usize,
+}
+
+impl Default for PusTcConfig {
+    fn default() -> PusTcConfig {
+        PusTcConfig::new()
+    }
+}
+
+impl PusTcConfig {
+    /// Create a configuration with the standard source ID width and no spare bytes.
+    pub fn new() -> PusTcConfig {
+        PusTcConfig {
+            source_id_bytes: 2,
+            spare_bytes: 0,
+        }
+    }
+
+    /// Check that the field widths are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.source_id_bytes <= 2
+    }
+
+    /// The number of bytes in a secondary header with this configuration.
+    pub fn header_len(&self) -> usize {
+        PUS_TC_FIXED_HEADER_BYTES + self.source_id_bytes + self.spare_bytes
+    }
+}
+
+
+/// A PusTcHeader is the secondary header of a PUS-C telecommand packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusTcHeader {
+    /// The TC packet PUS version number, which is 2 for PUS-C.
+    pub version: u8,
+
+    /// The acknowledgements requested for the command.
+    pub ack_flags: AckFlags,
+
+    /// The service type ID of the request.
+    pub service_type: u8,
+
+    /// The message subtype ID of the request.
+    pub message_subtype: u8,
+
+    /// The ID of the application process that sent the request.
+    pub source_id: u16,
+}
+
+impl PusTcHeader {
+    /// Create a PUS-C telecommand header for a service type and subtype, requesting
+    /// every acknowledgement, with a source ID of 0.
+    pub fn new(service_type: u8, message_subtype: u8) -> PusTcHeader {
+        PusTcHeader {
+            version: PUS_C_VERSION,
+            ack_flags: AckFlags::all(),
+            service_type,
+            message_subtype,
+            source_id: 0,
+        }
+    }
+
+    /// Read a header from the start of a byte slice, which should start just after
+    /// the primary header.
+    pub fn decode(config: &PusTcConfig, bytes: &[u8]) -> Result<PusTcHeader, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if bytes.len() < config.header_len() {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        let version = bytes[0] >> 4;
+        if version != PUS_C_VERSION {
+            return Err(PusError::InvalidVersion(version));
+        }
+
+        Ok(PusTcHeader {
+            version,
+            ack_flags: AckFlags::from_bits(bytes[0] & 0x0F),
+            service_type: bytes[1],
+            message_subtype: bytes[2],
+            source_id: read_field(&bytes[PUS_TC_FIXED_HEADER_BYTES..], config.source_id_bytes),
+        })
+    }
+
+    /// Write the header to the start of a byte slice, returning the number of bytes written.
+    pub fn encode(&self, config: &PusTcConfig, bytes: &mut [u8]) -> Result<usize, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        let header_len = config.header_len();
+        if bytes.len() < header_len {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        if self.version > 0x0F {
+            return Err(PusError::FieldOutOfRange);
+        }
+
+        bytes[0] = (self.version << 4) | self.ack_flags.bits();
+        bytes[1] = self.service_type;
+        bytes[2] = self.message_subtype;
+
+        let index = PUS_TC_FIXED_HEADER_BYTES;
+        write_field(&mut bytes[index..], config.source_id_bytes, self.source_id)?;
+
+        for byte in bytes[index + config.source_id_bytes..header_len].iter_mut() {
+            *byte = 0;
+        }
+
+        Ok(header_len)
+    }
+
+    /// Read the header of a telecommand packet, starting with its primary header. The
+    /// packet must be a command packet with the secondary header flag set.
+    pub fn from_packet(config: &PusTcConfig, packet: &[u8]) -> Result<PusTcHeader, PusError> {
+        let pri_header = PrimaryHeader::from_slice(packet).ok_or(PusError::NotEnoughBytes)?;
+
+        if pri_header.control.packet_type() != PacketType::Command {
+            return Err(PusError::WrongPacketType);
+        }
+
+        if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return Err(PusError::NoSecondaryHeader);
+        }
+
+        PusTcHeader::decode(config, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])
+    }
+}
+
+impl SecondaryHeader for PusTcHeader {
+    type Layout = PusTcConfig;
+
+    fn size(config: &PusTcConfig) -> usize {
+        config.header_len()
+    }
+
+    fn parse(config: &PusTcConfig, bytes: &[u8]) -> Option<PusTcHeader> {
+        PusTcHeader::decode(config, bytes).ok()
+    }
+
+    fn write(&self, config: &PusTcConfig, bytes: &mut [u8]) -> Option<usize> {
+        self.encode(config, bytes).ok()
+    }
+}
+
+
+/// Build a complete PUS-C telecommand packet: a primary header for an unsegmented
+/// command with the given APID and sequence count, the secondary header, the
+/// application data, and the Packet Error Control field.
+pub fn build_pus_tc(config: &PusTcConfig,
+                    apid: u16,
+                    sequence_count: u16,
+                    header: &PusTcHeader,
+                    app_data: &[u8]) -> Result<Vec<u8>, PusError> {
+    if apid > 0x07FF || sequence_count > 0x3FFF {
+        return Err(PusError::FieldOutOfRange);
+    }
+
+    let data_length = config.header_len() + app_data.len() + PUS_PEC_BYTES;
+    if data_length > CCSDS_MAX_LENGTH as usize - CCSDS_PRI_HEADER_SIZE_BYTES as usize {
+        return Err(PusError::FieldOutOfRange);
+    }
+
+    let mut pri_header: PrimaryHeader = Default::default();
+    pri_header.control.set_packet_type(PacketType::Command);
+    pri_header.control.set_secondary_header_flag(SecondaryHeaderFlag::Present);
+    pri_header.control.set_apid(apid);
+    pri_header.sequence.set_sequence_type(SeqFlag::Unsegmented);
+    pri_header.sequence.set_sequence_count(sequence_count);
+    pri_header.length.set_length_field((data_length - CCSDS_MIN_DATA_LENGTH_BYTES as usize) as u16);
+
+    let mut packet = Vec::with_capacity(CCSDS_PRI_HEADER_SIZE_BYTES as usize + data_length);
+    packet.extend_from_slice(&pri_header.to_bytes());
+
+    let header_start = packet.len();
+    packet.resize(header_start + config.header_len(), 0);
+    header.encode(config, &mut packet[header_start..])?;
+
+    packet.extend_from_slice(app_data);
+    append_pec(&mut packet);
+
+    Ok(packet)
+}
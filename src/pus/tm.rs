@@ -0,0 +1,231 @@
+use primary_header::*;
+use secondary_header::*;
+use time::*;
+use pus::*;
+
+
+/// The size of the fixed part of a PUS-C telemetry secondary header: the version,
+/// time reference status, service type and message subtype.
+pub const PUS_TM_FIXED_HEADER_BYTES: usize = 3;
+
+/// A PusTmConfig gives the mission defined parts of the PUS-C telemetry secondary
+/// header: the widths of the message type counter and destination ID, the time
+/// code format, and any spare bytes after the time.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PusTmConfig<F: TimeCodeFormat> {
+    /// The number of bytes in the message type counter, from 0 to 2. The standard
+    /// uses 2 bytes, and 0 bytes is used by missions that do not count messages.
+    pub message_counter_bytes: usize,
+
+    /// The number of bytes in the destination ID, from 0 to 2. The standard uses 2 bytes.
+    pub destination_id_bytes: usize,
+
+    /// The format of the time field.
+    pub time_format: F,
+
+    /// The number of spare bytes following the time, used to pad the header to
+    /// a mission defined size. Spare bytes are written as 0.
+    pub spare_bytes: usize,
+}
+
+impl<F: TimeCodeFormat> PusTmConfig<F> {
+    /// Create a configuration with the standard field widths and no spare bytes.
+    pub fn new(time_format: F) -> PusTmConfig<F> {
+        PusTmConfig {
+            message_counter_bytes: 2,
+            destination_id_bytes: 2,
+            time_format,
+            spare_bytes: 0,
+        }
+    }
+
+    /// Check that the field widths are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.message_counter_bytes <= 2 && self.destination_id_bytes <= 2
+    }
+
+    /// The number of bytes in a secondary header with this configuration.
+    pub fn header_len(&self) -> usize {
+        PUS_TM_FIXED_HEADER_BYTES +
+            self.message_counter_bytes +
+            self.destination_id_bytes +
+            self.time_format.encoded_len() +
+            self.spare_bytes
+    }
+}
+
+
+/// A PusTmHeader is the secondary header of a PUS-C telemetry packet. Its time is
+/// kept in the time code format F given by the header's configuration.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PusTmHeader<F: TimeCodeFormat> {
+    /// The TM packet PUS version number, which is 2 for PUS-C.
+    pub version: u8,
+
+    /// The spacecraft time reference status, a 4 bit field giving the state
+    /// of the on board time reference when the time was taken.
+    pub time_reference_status: u8,
+
+    /// The service type ID of the report.
+    pub service_type: u8,
+
+    /// The message subtype ID of the report.
+    pub message_subtype: u8,
+
+    /// The message type counter, counting reports of this service type and
+    /// subtype sent to the destination.
+    pub message_type_counter: u16,
+
+    /// The ID of the application process the report is sent to.
+    pub destination_id: u16,
+
+    /// The time the report was generated.
+    pub time: F::Time,
+}
+
+impl<F: TimeCodeFormat> PusTmHeader<F> {
+    /// Create a PUS-C telemetry header for a service type and subtype, with the other
+    /// fields set to 0.
+    pub fn new(service_type: u8, message_subtype: u8, time: F::Time) -> PusTmHeader<F> {
+        PusTmHeader {
+            version: PUS_C_VERSION,
+            time_reference_status: 0,
+            service_type,
+            message_subtype,
+            message_type_counter: 0,
+            destination_id: 0,
+            time,
+        }
+    }
+
+    /// Read a header from the start of a byte slice, which should start just after
+    /// the primary header.
+    pub fn decode(config: &PusTmConfig<F>, bytes: &[u8]) -> Result<PusTmHeader<F>, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        if bytes.len() < config.header_len() {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        let version = bytes[0] >> 4;
+        if version != PUS_C_VERSION {
+            return Err(PusError::InvalidVersion(version));
+        }
+
+        let mut index = PUS_TM_FIXED_HEADER_BYTES;
+        let message_type_counter = read_field(&bytes[index..], config.message_counter_bytes);
+        index += config.message_counter_bytes;
+
+        let destination_id = read_field(&bytes[index..], config.destination_id_bytes);
+        index += config.destination_id_bytes;
+
+        let (time, _) = config.time_format.decode(&bytes[index..])?;
+
+        Ok(PusTmHeader {
+            version,
+            time_reference_status: bytes[0] & 0x0F,
+            service_type: bytes[1],
+            message_subtype: bytes[2],
+            message_type_counter,
+            destination_id,
+            time,
+        })
+    }
+
+    /// Write the header to the start of a byte slice, returning the number of bytes written.
+    pub fn encode(&self, config: &PusTmConfig<F>, bytes: &mut [u8]) -> Result<usize, PusError> {
+        if !config.is_valid() {
+            return Err(PusError::InvalidConfig);
+        }
+
+        let header_len = config.header_len();
+        if bytes.len() < header_len {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        if self.version > 0x0F || self.time_reference_status > 0x0F {
+            return Err(PusError::FieldOutOfRange);
+        }
+
+        bytes[0] = (self.version << 4) | self.time_reference_status;
+        bytes[1] = self.service_type;
+        bytes[2] = self.message_subtype;
+
+        let mut index = PUS_TM_FIXED_HEADER_BYTES;
+        write_field(&mut bytes[index..], config.message_counter_bytes, self.message_type_counter)?;
+        index += config.message_counter_bytes;
+
+        write_field(&mut bytes[index..], config.destination_id_bytes, self.destination_id)?;
+        index += config.destination_id_bytes;
+
+        index += config.time_format.encode(&self.time, &mut bytes[index..])?;
+
+        for byte in bytes[index..header_len].iter_mut() {
+            *byte = 0;
+        }
+
+        Ok(header_len)
+    }
+
+    /// Read the header of a telemetry packet, starting with its primary header. The
+    /// packet must be a telemetry packet with the secondary header flag set.
+    pub fn from_packet(config: &PusTmConfig<F>, packet: &[u8]) -> Result<PusTmHeader<F>, PusError> {
+        let pri_header = PrimaryHeader::from_slice(packet).ok_or(PusError::NotEnoughBytes)?;
+
+        if pri_header.control.packet_type() != PacketType::Data {
+            return Err(PusError::WrongPacketType);
+        }
+
+        if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return Err(PusError::NoSecondaryHeader);
+        }
+
+        PusTmHeader::decode(config, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..])
+    }
+}
+
+impl<F: TimeCodeFormat> SecondaryHeader for PusTmHeader<F> {
+    type Layout = PusTmConfig<F>;
+
+    fn size(config: &PusTmConfig<F>) -> usize {
+        config.header_len()
+    }
+
+    fn parse(config: &PusTmConfig<F>, bytes: &[u8]) -> Option<PusTmHeader<F>> {
+        PusTmHeader::decode(config, bytes).ok()
+    }
+
+    fn write(&self, config: &PusTmConfig<F>, bytes: &mut [u8]) -> Option<usize> {
+        self.encode(config, bytes).ok()
+    }
+}
+
+
+/// The user data of a telemetry packet: the bytes following the secondary header,
+/// up to the end of the packet given by its primary header. If the packet has a
+/// Packet Error Control field it is checked and not included in the user data.
+pub fn pus_tm_user_data<'a, F: TimeCodeFormat>(config: &PusTmConfig<F>,
+                                               packet: &'a [u8],
+                                               has_pec: bool) -> Result<&'a [u8], PusError> {
+    let pri_header = PrimaryHeader::from_slice(packet).ok_or(PusError::NotEnoughBytes)?;
+    let mut end = pri_header.packet_length() as usize;
+    if packet.len() < end {
+        return Err(PusError::NotEnoughBytes);
+    }
+
+    if has_pec {
+        if !has_valid_pec(packet) {
+            return Err(PusError::InvalidPec);
+        }
+        end -= PUS_PEC_BYTES;
+    }
+
+    let start = CCSDS_PRI_HEADER_SIZE_BYTES as usize + config.header_len();
+    if end < start {
+        return Err(PusError::NotEnoughBytes);
+    }
+
+    Ok(&packet[start..end])
+}
@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use time::*;
+use pus::*;
+
+
+/// The service type of PUS service 1, request verification.
+pub const PUS_SERVICE_VERIFICATION: u8 = 1;
+
+/// The size of the request ID at the start of each verification report.
+pub const PUS_REQUEST_ID_BYTES: usize = 4;
+
+/// A RequestId identifies a telecommand by its APID and sequence count. It is copied
+/// from the command's primary header into each verification report for the command.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct RequestId {
+    pub apid: u16,
+    pub sequence_count: u16,
+}
+
+impl RequestId {
+    /// The request ID of a command with the given primary header.
+    pub fn from_header(pri_header: &PrimaryHeader) -> RequestId {
+        RequestId {
+            apid: pri_header.control.apid(),
+            sequence_count: pri_header.sequence.sequence_count(),
+        }
+    }
+
+    /// Read a request ID from the start of a verification report. The request ID is
+    /// the first 4 bytes of the command's primary header.
+    pub fn decode(bytes: &[u8]) -> Result<RequestId, PusError> {
+        if bytes.len() < PUS_REQUEST_ID_BYTES {
+            return Err(PusError::NotEnoughBytes);
+        }
+
+        let control = ControlWord([bytes[0], bytes[1]]);
+        let sequence = SequenceWord([bytes[2], bytes[3]]);
+
+        Ok(RequestId {
+            apid: control.apid(),
+            sequence_count: sequence.sequence_count(),
+        })
+    }
+}
+
+/// The VerificationStage is the step of a command's execution that a verification
+/// report is about.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum VerificationStage {
+    Acceptance,
+    Start,
+    Progress,
+    Completion,
+}
+
+/// A VerificationFailure gives the failure notice of a failed verification report.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VerificationFailure {
+    /// The stage that failed.
+    pub stage: VerificationStage,
+
+    /// The mission defined failure code.
+    pub code: u16,
+
+    /// Any data following the failure code.
+    pub data: Vec<u8>,
+}
+
+/// A VerificationReport is the user data of a PUS service 1 telemetry packet.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VerificationReport {
+    /// The command the report is for.
+    pub request_id: RequestId,
+
+    /// The stage of execution the report is about.
+    pub stage: VerificationStage,
+
+    /// The step ID of a progress report.
+    pub step: Option<u16>,
+
+    /// The failure notice, if the report is a failure report.
+    pub failure: Option<VerificationFailure>,
+}
+
+impl VerificationReport {
+    /// Decode the user data of a service 1 report with the given message subtype.
+    /// Progress reports carry a step ID, and failure reports a failure code, with
+    /// the mission defined widths given in bytes.
+    pub fn decode(message_subtype: u8,
+                  user_data: &[u8],
+                  step_id_bytes: usize,
+                  failure_code_bytes: usize) -> Result<VerificationReport, PusError> {
+        let (stage, success) = match message_subtype {
+            1 => (VerificationStage::Acceptance, true),
+            2 => (VerificationStage::Acceptance, false),
+            3 => (VerificationStage::Start, true),
+            4 => (VerificationStage::Start, false),
+            5 => (VerificationStage::Progress, true),
+            6 => (VerificationStage::Progress, false),
+            7 => (VerificationStage::Completion, true),
+            8 => (VerificationStage::Completion, false),
+            _ => return Err(PusError::UnknownSubtype(PUS_SERVICE_VERIFICATION, message_subtype)),
+        };
+
+        if step_id_bytes > 2 || failure_code_bytes > 2 {
+            return Err(PusError::InvalidConfig);
+        }
+
+        let request_id = RequestId::decode(user_data)?;
+        let mut index = PUS_REQUEST_ID_BYTES;
+
+        let step = if stage == VerificationStage::Progress {
+            if user_data.len() < index + step_id_bytes {
+                return Err(PusError::NotEnoughBytes);
+            }
+            let step = read_field(&user_data[index..], step_id_bytes);
+            index += step_id_bytes;
+            Some(step)
+        } else {
+            None
+        };
+
+        let failure = if success {
+            None
+        } else {
+            if user_data.len() < index + failure_code_bytes {
+                return Err(PusError::NotEnoughBytes);
+            }
+            let code = read_field(&user_data[index..], failure_code_bytes);
+            index += failure_code_bytes;
+            Some(VerificationFailure { stage, code, data: user_data[index..].to_vec() })
+        };
+
+        Ok(VerificationReport { request_id, stage, step, failure })
+    }
+}
+
+
+/// The CommandState summarizes where a command is in its verification.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CommandState {
+    /// The command has been sent, and has not yet completed or failed.
+    Pending,
+
+    /// A successful report has been received for the last stage whose acknowledgement
+    /// was requested, or a successful completion report has been received.
+    Completed,
+
+    /// A failure report has been received.
+    Failed,
+
+    /// A report was not received for a stage before its timeout.
+    TimedOut(VerificationStage),
+}
+
+/// A CommandRecord is the verification history of a command sent through a
+/// CommandVerifier.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CommandRecord {
+    /// The request ID of the command.
+    pub request_id: RequestId,
+
+    /// The acknowledgements requested by the command.
+    pub ack_flags: AckFlags,
+
+    /// The time the command was sent.
+    pub sent_at: Duration,
+
+    /// The latest stage with a successful report, if any.
+    pub stage: Option<VerificationStage>,
+
+    /// The step ID of the latest successful progress report.
+    pub progress_step: Option<u16>,
+
+    /// The failure notice, if a failure report was received.
+    pub failure: Option<VerificationFailure>,
+
+    /// The stage that timed out, if a report was not received in time.
+    pub timed_out: Option<VerificationStage>,
+}
+
+impl CommandRecord {
+    /// The command's verification state.
+    pub fn state(&self) -> CommandState {
+        let last_stage = self.last_requested_stage().unwrap_or(VerificationStage::Completion);
+
+        if self.failure.is_some() {
+            CommandState::Failed
+        } else if self.stage.map_or(false, |stage| stage >= last_stage) {
+            CommandState::Completed
+        } else if let Some(stage) = self.timed_out {
+            CommandState::TimedOut(stage)
+        } else {
+            CommandState::Pending
+        }
+    }
+
+    /// The failure code of the command, if it failed.
+    pub fn failure_code(&self) -> Option<u16> {
+        self.failure.as_ref().map(|failure| failure.code)
+    }
+
+    /// Check whether the command has finished verification, either by completing,
+    /// failing, or timing out.
+    pub fn is_finished(&self) -> bool {
+        self.state() != CommandState::Pending
+    }
+
+    /// The last stage whose successful report was requested by the command, or None
+    /// if the command requested no acknowledgements.
+    pub fn last_requested_stage(&self) -> Option<VerificationStage> {
+        let flags = self.ack_flags;
+
+        if flags.completion {
+            Some(VerificationStage::Completion)
+        } else if flags.progress {
+            Some(VerificationStage::Progress)
+        } else if flags.start {
+            Some(VerificationStage::Start)
+        } else if flags.acceptance {
+            Some(VerificationStage::Acceptance)
+        } else {
+            None
+        }
+    }
+}
+
+
+/// A CommandVerifier tracks PUS telecommands through their service 1 verification
+/// reports. Commands are recorded as they are sent, and telemetry packets are given
+/// to the verifier as they are received. Reports are matched to commands by the
+/// APID and sequence count of the command.
+///
+/// Time is given by the caller as a Duration from any fixed point, such as the time
+/// since the verifier was created, so the verifier can be driven by a simulated clock.
+///
+/// A command is completed once its last requested stage has succeeded, as later
+/// successful reports are never sent, and timeouts likewise only apply to stages
+/// whose acknowledgement was requested. A command which requests no acknowledgements
+/// only ever reports failure, so it stays pending until it is removed.
+#[derive(Debug, Clone)]
+pub struct CommandVerifier<F: TimeCodeFormat> {
+    /// The configuration of the telemetry secondary header of verification reports.
+    pub tm_config: PusTmConfig<F>,
+
+    /// Whether telemetry packets end with a Packet Error Control field, which is
+    /// checked before a report is used.
+    pub tm_has_pec: bool,
+
+    /// The number of bytes in the step ID of progress reports.
+    pub step_id_bytes: usize,
+
+    /// The number of bytes in the failure code of failure reports.
+    pub failure_code_bytes: usize,
+
+    /// The time allowed from sending a command to its successful acceptance report.
+    pub acceptance_timeout: Option<Duration>,
+
+    /// The time allowed from sending a command to the successful report of its last
+    /// requested stage, when that is after acceptance.
+    pub completion_timeout: Option<Duration>,
+
+    /// The number of verification reports which did not match any recorded command.
+    pub unmatched_reports: usize,
+
+    /// The number of verification reports which could not be read.
+    pub invalid_reports: usize,
+
+    commands: BTreeMap<RequestId, CommandRecord>,
+}
+
+impl<F: TimeCodeFormat> CommandVerifier<F> {
+    /// Create a verifier for reports with the given telemetry secondary header. Reports
+    /// are expected to have a 1 byte step ID, a 2 byte failure code, and no PEC.
+    /// Commands must be accepted within 10 seconds and completed within 60 seconds.
+    pub fn new(tm_config: PusTmConfig<F>) -> CommandVerifier<F> {
+        CommandVerifier {
+            tm_config,
+            tm_has_pec: false,
+            step_id_bytes: 1,
+            failure_code_bytes: 2,
+            acceptance_timeout: Some(Duration::from_secs(10)),
+            completion_timeout: Some(Duration::from_secs(60)),
+            unmatched_reports: 0,
+            invalid_reports: 0,
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// Record a command packet as it is sent. The packet must start with a primary
+    /// header and a PUS-C telecommand secondary header with the given configuration.
+    /// A command with the same request ID as an earlier command replaces it.
+    pub fn send_command(&mut self, tc_config: &PusTcConfig, packet: &[u8], now: Duration) -> Result<RequestId, PusError> {
+        let tc_header = PusTcHeader::from_packet(tc_config, packet)?;
+        let request_id = RequestId::from_header(&PrimaryHeader::from_slice(packet).unwrap());
+
+        self.commands.insert(request_id, CommandRecord {
+            request_id,
+            ack_flags: tc_header.ack_flags,
+            sent_at: now,
+            stage: None,
+            progress_step: None,
+            failure: None,
+            timed_out: None,
+        });
+
+        Ok(request_id)
+    }
+
+    /// Process a received telemetry packet. Packets that are not service 1 reports are
+    /// ignored. If the packet is a report for a recorded command, the command is updated
+    /// and its request ID is returned.
+    pub fn recv_telemetry(&mut self, packet: &[u8]) -> Option<RequestId> {
+        let tm_header = match PusTmHeader::from_packet(&self.tm_config, packet) {
+            Ok(tm_header) => tm_header,
+            Err(_) => return None,
+        };
+
+        if tm_header.service_type != PUS_SERVICE_VERIFICATION {
+            return None;
+        }
+
+        let report = match self.decode_report(tm_header.message_subtype, packet) {
+            Some(report) => report,
+            None => {
+                self.invalid_reports += 1;
+                return None;
+            },
+        };
+
+        match self.commands.get_mut(&report.request_id) {
+            Some(record) => {
+                if report.failure.is_some() {
+                    record.failure = report.failure;
+                } else if record.stage.map_or(true, |stage| stage <= report.stage) {
+                    record.stage = Some(report.stage);
+                    if report.step.is_some() {
+                        record.progress_step = report.step;
+                    }
+                }
+
+                Some(report.request_id)
+            },
+
+            None => {
+                self.unmatched_reports += 1;
+                None
+            },
+        }
+    }
+
+    /// Check for commands whose reports have not arrived within their timeouts,
+    /// returning the request IDs of commands that timed out since the last check.
+    pub fn check_timeouts(&mut self, now: Duration) -> Vec<RequestId> {
+        let acceptance_timeout = self.acceptance_timeout;
+        let completion_timeout = self.completion_timeout;
+        let mut timed_out = Vec::new();
+
+        for record in self.commands.values_mut() {
+            if record.is_finished() {
+                continue;
+            }
+
+            let elapsed = now.checked_sub(record.sent_at).unwrap_or_default();
+
+            let acceptance_late =
+                record.ack_flags.acceptance && record.stage.is_none() &&
+                acceptance_timeout.map_or(false, |timeout| elapsed > timeout);
+
+            let last_stage = record.last_requested_stage().filter(|stage| *stage > VerificationStage::Acceptance);
+            let completion_late =
+                last_stage.is_some() &&
+                completion_timeout.map_or(false, |timeout| elapsed > timeout);
+
+            if acceptance_late {
+                record.timed_out = Some(VerificationStage::Acceptance);
+            } else if completion_late {
+                record.timed_out = last_stage;
+            } else {
+                continue;
+            }
+
+            timed_out.push(record.request_id);
+        }
+
+        timed_out
+    }
+
+    /// The record of a command.
+    pub fn command(&self, request_id: &RequestId) -> Option<&CommandRecord> {
+        self.commands.get(request_id)
+    }
+
+    /// The verification state of a command.
+    pub fn state(&self, request_id: &RequestId) -> Option<CommandState> {
+        self.commands.get(request_id).map(|record| record.state())
+    }
+
+    /// Iterate over the records of all commands, in request ID order.
+    pub fn commands(&self) -> impl Iterator<Item = &CommandRecord> {
+        self.commands.values()
+    }
+
+    /// Remove and return the record of a command, whether or not it has finished.
+    pub fn remove_command(&mut self, request_id: &RequestId) -> Option<CommandRecord> {
+        self.commands.remove(request_id)
+    }
+
+    /// Remove and return the records of all finished commands.
+    pub fn take_finished(&mut self) -> Vec<CommandRecord> {
+        let finished: Vec<RequestId> =
+            self.commands.values()
+                         .filter(|record| record.is_finished())
+                         .map(|record| record.request_id)
+                         .collect();
+
+        finished.iter().filter_map(|request_id| self.commands.remove(request_id)).collect()
+    }
+
+    fn decode_report(&self, message_subtype: u8, packet: &[u8]) -> Option<VerificationReport> {
+        let user_data = pus_tm_user_data(&self.tm_config, packet, self.tm_has_pec).ok()?;
+
+        VerificationReport::decode(message_subtype, user_data, self.step_id_bytes, self.failure_code_bytes).ok()
+    }
+}
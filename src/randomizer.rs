@@ -0,0 +1,56 @@
+/*!
+The randomizer module applies the pseudo-randomizers of CCSDS 131.0, TM Synchronization
+and Channel Coding. Frames are randomized before they are sent so that the transmitted
+bits have enough transitions for the receiver to keep its bit lock, whatever the frames
+hold.
+
+A frame is randomized by XORing it with a pseudo-random sequence which restarts at the
+start of every frame, after the Attached Sync Marker. Derandomizing is the same operation.
+*/
+
+/// A Randomizer is one of the pseudo-random sequences defined by CCSDS.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Randomizer {
+    /// The sequence with a period of 255 bits, generated by h(x) = x^8 + x^7 + x^5 + x^3 + 1.
+    /// This is the randomizer used by most missions. It starts with 0xFF, 0x48, 0x0E, 0xC0.
+    Pn255,
+
+    /// The sequence with a period of 131071 bits, generated by h(x) = x^17 + x^14 + 1,
+    /// for high rate links.
+    Pn131071,
+}
+
+impl Randomizer {
+    /// The first bytes of the pseudo-random sequence. Each bit is the XOR of earlier bits
+    /// of the sequence, given by the generator polynomial, starting from all ones.
+    pub fn sequence(&self, length: usize) -> Vec<u8> {
+        let (degree, taps): (usize, &[usize]) = match *self {
+            Randomizer::Pn255 => (8, &[1, 3, 5, 8]),
+            Randomizer::Pn131071 => (17, &[3, 17]),
+        };
+
+        let num_bits = length * 8;
+        let mut bits = vec![1u8; degree.min(num_bits)];
+        while bits.len() < num_bits {
+            let index = bits.len();
+            bits.push(taps.iter().fold(0, |bit, tap| bit ^ bits[index - tap]));
+        }
+
+        bits.chunks(8)
+            .map(|chunk| chunk.iter().fold(0, |byte, bit| (byte << 1) | bit))
+            .collect()
+    }
+
+    /// Randomize a frame, not including its Attached Sync Marker, in place.
+    pub fn randomize(&self, frame: &mut [u8]) {
+        let sequence = self.sequence(frame.len());
+        for (byte, random) in frame.iter_mut().zip(sequence) {
+            *byte ^= random;
+        }
+    }
+
+    /// Derandomize a received frame, not including its Attached Sync Marker, in place.
+    pub fn derandomize(&self, frame: &mut [u8]) {
+        self.randomize(frame);
+    }
+}
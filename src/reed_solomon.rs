@@ -0,0 +1,476 @@
+/*!
+The reed_solomon module encodes and decodes the Reed-Solomon codes of CCSDS 131.0, TM
+Synchronization and Channel Coding, which let a receiver correct symbol errors in TM
+and AOS frames.
+
+The RS(255,223) code adds 32 check symbols to every 223 data symbols, and can correct
+up to 16 symbol errors in each codeword. The RS(255,239) code adds 16 check symbols,
+correcting up to 8. Codewords are interleaved within a codeblock, so that a burst of
+errors is spread over several codewords, and may be shortened with virtual fill: data
+symbols of zero which are not sent. CCSDS symbols are given in Berlekamp's dual basis.
+*/
+
+/// The number of symbols in a full codeword.
+pub const RS_CODEWORD_LENGTH: usize = 255;
+
+/// The largest interleave depth allowed by the standard.
+pub const RS_MAX_INTERLEAVE_DEPTH: usize = 8;
+
+/// The field generator polynomial, x^8 + x^7 + x^2 + x + 1.
+const RS_FIELD_POLY: u16 = 0x187;
+
+/// The power of the primitive element between consecutive roots of the code generator.
+const RS_ROOT_STEP: usize = 11;
+
+/// The multiplicative inverse of RS_ROOT_STEP modulo 255, used to locate errors.
+const RS_ROOT_STEP_INVERSE: usize = 116;
+
+/// The log of zero, which has no log, in the index form of symbols.
+const RS_LOG_ZERO: usize = RS_CODEWORD_LENGTH;
+
+/// The rows of the matrix taking a symbol from the conventional basis to the dual basis.
+const DUAL_BASIS_ROWS: [u8; 8] = [0x8D, 0xEF, 0xEC, 0x86, 0xFA, 0x99, 0xAF, 0x7B];
+
+
+/// The RsCode is the Reed-Solomon code used on a channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RsCode {
+    /// RS(255,223), correcting up to 16 symbols per codeword.
+    Rs255_223,
+
+    /// RS(255,239), correcting up to 8 symbols per codeword.
+    Rs255_239,
+}
+
+impl RsCode {
+    /// The number of check symbols in each codeword.
+    pub fn parity_length(&self) -> usize {
+        match *self {
+            RsCode::Rs255_223 => 32,
+            RsCode::Rs255_239 => 16,
+        }
+    }
+
+    /// The number of data symbols in each full codeword.
+    pub fn data_length(&self) -> usize {
+        RS_CODEWORD_LENGTH - self.parity_length()
+    }
+
+    /// The largest number of symbol errors which can be corrected in each codeword.
+    pub fn max_corrections(&self) -> usize {
+        self.parity_length() / 2
+    }
+
+    /// The log of the first root of the code generator, in steps of RS_ROOT_STEP.
+    fn first_root(&self) -> usize {
+        128 - self.max_corrections()
+    }
+}
+
+
+/// An RsError describes why a codeblock could not be encoded or decoded.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RsError {
+    /// The interleave depth or virtual fill is not allowed.
+    InvalidConfig,
+
+    /// The frame or codeblock does not have the length given by the configuration.
+    WrongLength { expected: usize, actual: usize },
+}
+
+
+/// The RsConfig gives the Reed-Solomon coding of a channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RsConfig {
+    pub code: RsCode,
+
+    /// The number of codewords interleaved in each codeblock, from 1 to 8.
+    pub interleave_depth: usize,
+
+    /// The number of data symbols of each codeword which are virtual fill, and not sent.
+    pub virtual_fill: usize,
+
+    /// Whether symbols are in the dual basis, as CCSDS requires, rather than the
+    /// conventional basis.
+    pub dual_basis: bool,
+}
+
+impl RsConfig {
+    /// Create a configuration with full codewords in the dual basis.
+    pub fn new(code: RsCode, interleave_depth: usize) -> RsConfig {
+        RsConfig {
+            code,
+            interleave_depth,
+            virtual_fill: 0,
+            dual_basis: true,
+        }
+    }
+
+    /// Check that the interleave depth and virtual fill are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.interleave_depth >= 1 &&
+        self.interleave_depth <= RS_MAX_INTERLEAVE_DEPTH &&
+        self.virtual_fill < self.code.data_length()
+    }
+
+    /// The length of the frame carried in each codeblock.
+    pub fn frame_length(&self) -> usize {
+        self.interleave_depth * (self.code.data_length() - self.virtual_fill)
+    }
+
+    /// The length of each codeblock: the frame followed by the check symbols.
+    pub fn codeblock_length(&self) -> usize {
+        self.frame_length() + self.interleave_depth * self.code.parity_length()
+    }
+}
+
+
+/// The result of decoding a codeblock.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RsDecodeReport {
+    /// The number of symbols corrected in each codeword, or None for a codeword with
+    /// too many errors to correct, which is left as it was received.
+    pub corrected_symbols: Vec<Option<usize>>,
+}
+
+impl RsDecodeReport {
+    /// Whether every codeword could be corrected, so the frame can be used.
+    pub fn is_correctable(&self) -> bool {
+        self.corrected_symbols.iter().all(|corrected| corrected.is_some())
+    }
+
+    /// The total number of symbols corrected in the codeblock.
+    pub fn total_corrected(&self) -> usize {
+        self.corrected_symbols.iter().map(|corrected| corrected.unwrap_or(0)).sum()
+    }
+}
+
+
+/// A ReedSolomon encoder and decoder for a configuration, holding the tables used
+/// for arithmetic in the code's field.
+#[derive(Debug, Clone)]
+pub struct ReedSolomon {
+    config: RsConfig,
+
+    alpha_to: [u8; 256],
+
+    index_of: [usize; 256],
+
+    /// The code generator polynomial in index form, lowest power first.
+    generator: Vec<usize>,
+
+    to_dual: [u8; 256],
+
+    from_dual: [u8; 256],
+}
+
+impl ReedSolomon {
+    /// Create an encoder and decoder for a configuration.
+    pub fn new(config: RsConfig) -> Result<ReedSolomon, RsError> {
+        if !config.is_valid() {
+            return Err(RsError::InvalidConfig);
+        }
+
+        let mut alpha_to = [0u8; 256];
+        let mut index_of = [RS_LOG_ZERO; 256];
+        let mut symbol: u16 = 1;
+        for (power, alpha) in alpha_to.iter_mut().enumerate().take(RS_CODEWORD_LENGTH) {
+            index_of[symbol as usize] = power;
+            *alpha = symbol as u8;
+
+            symbol <<= 1;
+            if symbol & 0x100 != 0 {
+                symbol ^= RS_FIELD_POLY;
+            }
+        }
+
+        let mut to_dual = [0u8; 256];
+        let mut from_dual = [0u8; 256];
+        for (conventional, dual) in to_dual.iter_mut().enumerate() {
+            *dual = (0..8).filter(|bit| conventional & (1 << bit) != 0)
+                          .fold(0, |dual, bit| dual ^ DUAL_BASIS_ROWS[7 - bit]);
+            from_dual[*dual as usize] = conventional as u8;
+        }
+
+        let mut rs = ReedSolomon { config, alpha_to, index_of, generator: Vec::new(), to_dual, from_dual };
+
+        // the generator is the product of (x - a^(11 j)) over its roots
+        let parity_length = config.code.parity_length();
+        let mut generator = vec![0u8; parity_length + 1];
+        generator[0] = 1;
+        for index in 0..parity_length {
+            let root = (config.code.first_root() + index) * RS_ROOT_STEP;
+            generator[index + 1] = 1;
+
+            for power in (1..index + 1).rev() {
+                generator[power] = if generator[power] != 0 {
+                    generator[power - 1] ^ rs.alpha_to[rs.modnn(rs.index_of[generator[power] as usize] + root)]
+                } else {
+                    generator[power - 1]
+                };
+            }
+
+            generator[0] = rs.alpha_to[rs.modnn(rs.index_of[generator[0] as usize] + root)];
+        }
+
+        rs.generator = generator.iter().map(|coefficient| rs.index_of[*coefficient as usize]).collect();
+        Ok(rs)
+    }
+
+    /// The configuration this encoder and decoder was created for.
+    pub fn config(&self) -> RsConfig {
+        self.config
+    }
+
+    /// Encode a frame, returning the codeblock: the frame followed by the interleaved
+    /// check symbols of each codeword.
+    pub fn encode(&self, frame: &[u8]) -> Result<Vec<u8>, RsError> {
+        if frame.len() != self.config.frame_length() {
+            return Err(RsError::WrongLength { expected: self.config.frame_length(), actual: frame.len() });
+        }
+
+        let depth = self.config.interleave_depth;
+        let mut codeblock = frame.to_vec();
+        codeblock.resize(self.config.codeblock_length(), 0);
+
+        for codeword in 0..depth {
+            let data: Vec<u8> = frame.iter().skip(codeword).step_by(depth).map(|symbol| self.conventional_symbol(*symbol)).collect();
+
+            for (index, symbol) in self.parity(&data).iter().enumerate() {
+                codeblock[frame.len() + index * depth + codeword] = self.channel_symbol(*symbol);
+            }
+        }
+
+        Ok(codeblock)
+    }
+
+    /// Decode a codeblock in place, correcting the codewords which can be corrected. The
+    /// report gives the number of symbols corrected in each codeword, and whether the
+    /// frame at the start of the codeblock can be used.
+    pub fn decode(&self, codeblock: &mut [u8]) -> Result<RsDecodeReport, RsError> {
+        if codeblock.len() != self.config.codeblock_length() {
+            return Err(RsError::WrongLength { expected: self.config.codeblock_length(), actual: codeblock.len() });
+        }
+
+        let depth = self.config.interleave_depth;
+        let mut corrected_symbols = Vec::with_capacity(depth);
+
+        for codeword in 0..depth {
+            let mut symbols: Vec<u8> = codeblock.iter()
+                                                .skip(codeword)
+                                                .step_by(depth)
+                                                .map(|symbol| self.conventional_symbol(*symbol))
+                                                .collect();
+
+            let corrected = self.correct(&mut symbols);
+            if corrected.map_or(false, |corrected| corrected > 0) {
+                for (index, symbol) in symbols.iter().enumerate() {
+                    codeblock[index * depth + codeword] = self.channel_symbol(*symbol);
+                }
+            }
+
+            corrected_symbols.push(corrected);
+        }
+
+        Ok(RsDecodeReport { corrected_symbols })
+    }
+
+    /// The check symbols of a codeword's data, in the conventional basis.
+    fn parity(&self, data: &[u8]) -> Vec<u8> {
+        let parity_length = self.config.code.parity_length();
+        let mut parity = vec![0u8; parity_length];
+
+        for symbol in data.iter() {
+            let feedback = self.index_of[(symbol ^ parity[0]) as usize];
+
+            if feedback != RS_LOG_ZERO {
+                for (index, symbol) in parity.iter_mut().enumerate().skip(1) {
+                    *symbol ^= self.alpha_to[self.modnn(feedback + self.generator[parity_length - index])];
+                }
+            }
+
+            parity.rotate_left(1);
+            parity[parity_length - 1] = if feedback != RS_LOG_ZERO {
+                self.alpha_to[self.modnn(feedback + self.generator[0])]
+            } else {
+                0
+            };
+        }
+
+        parity
+    }
+
+    /// Correct a shortened codeword in the conventional basis in place, using the
+    /// Berlekamp-Massey algorithm. Returns the number of symbols corrected, or None
+    /// if there are too many errors to correct.
+    fn correct(&self, codeword: &mut [u8]) -> Option<usize> {
+        let parity_length = self.config.code.parity_length();
+        let first_root = self.config.code.first_root();
+        let pad = RS_CODEWORD_LENGTH - codeword.len();
+
+        // the syndromes are the codeword evaluated at each root of the generator
+        let mut syndromes = vec![0u8; parity_length];
+        for (index, syndrome) in syndromes.iter_mut().enumerate() {
+            let root = (first_root + index) * RS_ROOT_STEP;
+            *syndrome = codeword.iter().fold(0, |value, symbol| {
+                if value == 0 {
+                    *symbol
+                } else {
+                    symbol ^ self.alpha_to[self.modnn(self.index_of[value as usize] + root)]
+                }
+            });
+        }
+
+        if syndromes.iter().all(|syndrome| *syndrome == 0) {
+            return Some(0);
+        }
+
+        let syndromes: Vec<usize> = syndromes.iter().map(|syndrome| self.index_of[*syndrome as usize]).collect();
+
+        // find the error locator polynomial lambda
+        let mut lambda = vec![0u8; parity_length + 1];
+        lambda[0] = 1;
+        let mut b: Vec<usize> = lambda.iter().map(|coefficient| self.index_of[*coefficient as usize]).collect();
+        let mut degree = 0;
+
+        for step in 1..parity_length + 1 {
+            let mut discrepancy = 0;
+            for index in 0..step {
+                if lambda[index] != 0 && syndromes[step - index - 1] != RS_LOG_ZERO {
+                    discrepancy ^= self.alpha_to[self.modnn(self.index_of[lambda[index] as usize] +
+                                                            syndromes[step - index - 1])];
+                }
+            }
+            let discrepancy = self.index_of[discrepancy as usize];
+
+            if discrepancy == RS_LOG_ZERO {
+                b.rotate_right(1);
+                b[0] = RS_LOG_ZERO;
+                continue;
+            }
+
+            let mut next = lambda.clone();
+            for index in 0..parity_length {
+                if b[index] != RS_LOG_ZERO {
+                    next[index + 1] ^= self.alpha_to[self.modnn(discrepancy + b[index])];
+                }
+            }
+
+            if 2 * degree < step {
+                degree = step - degree;
+                for (b_coefficient, coefficient) in b.iter_mut().zip(lambda.iter()) {
+                    *b_coefficient = if *coefficient == 0 {
+                        RS_LOG_ZERO
+                    } else {
+                        self.modnn(self.index_of[*coefficient as usize] + RS_CODEWORD_LENGTH - discrepancy)
+                    };
+                }
+            } else {
+                b.rotate_right(1);
+                b[0] = RS_LOG_ZERO;
+            }
+
+            lambda = next;
+        }
+
+        let lambda: Vec<usize> = lambda.iter().map(|coefficient| self.index_of[*coefficient as usize]).collect();
+        let lambda_degree = lambda.iter().rposition(|coefficient| *coefficient != RS_LOG_ZERO).unwrap_or(0);
+
+        // find the roots of lambda by trying every location, which are the error locations
+        let mut registers = lambda.clone();
+        let mut roots = Vec::with_capacity(lambda_degree);
+        let mut locations = Vec::with_capacity(lambda_degree);
+        let mut location = RS_ROOT_STEP_INVERSE - 1;
+
+        for root in 1..RS_CODEWORD_LENGTH + 1 {
+            let mut value = 1;
+            for power in (1..lambda_degree + 1).rev() {
+                if registers[power] != RS_LOG_ZERO {
+                    registers[power] = self.modnn(registers[power] + power);
+                    value ^= self.alpha_to[registers[power]];
+                }
+            }
+
+            if value == 0 {
+                roots.push(root);
+                locations.push(location);
+                if roots.len() == lambda_degree {
+                    break;
+                }
+            }
+
+            location = self.modnn(location + RS_ROOT_STEP_INVERSE);
+        }
+
+        if roots.len() != lambda_degree {
+            return None;
+        }
+
+        // the error evaluator polynomial omega is the syndromes times lambda, mod x^parity_length
+        let omega_degree = lambda_degree - 1;
+        let omega: Vec<usize> = (0..omega_degree + 1).map(|index| {
+            let value = (0..index + 1).fold(0, |value, power| {
+                if syndromes[index - power] != RS_LOG_ZERO && lambda[power] != RS_LOG_ZERO {
+                    value ^ self.alpha_to[self.modnn(syndromes[index - power] + lambda[power])]
+                } else {
+                    value
+                }
+            });
+            self.index_of[value as usize]
+        }).collect();
+
+        // find the error values with Forney's algorithm
+        let mut corrections = Vec::with_capacity(roots.len());
+        for (root, location) in roots.iter().zip(locations.iter()) {
+            let numerator = omega.iter().enumerate().fold(0, |value, (power, coefficient)| {
+                if *coefficient != RS_LOG_ZERO {
+                    value ^ self.alpha_to[self.modnn(coefficient + power * root)]
+                } else {
+                    value
+                }
+            });
+
+            let scale = self.alpha_to[self.modnn(root * (first_root - 1))];
+
+            // the formal derivative of lambda has only its odd powers
+            let mut denominator = 0;
+            for power in (0..lambda_degree.min(parity_length - 1) + 1).step_by(2) {
+                if lambda[power + 1] != RS_LOG_ZERO {
+                    denominator ^= self.alpha_to[self.modnn(lambda[power + 1] + power * root)];
+                }
+            }
+
+            if denominator == 0 || *location < pad {
+                return None;
+            }
+
+            if numerator != 0 {
+                let value = self.alpha_to[self.modnn(self.index_of[numerator as usize] +
+                                                     self.index_of[scale as usize] +
+                                                     RS_CODEWORD_LENGTH -
+                                                     self.index_of[denominator as usize])];
+                corrections.push((location - pad, value));
+            }
+        }
+
+        for (index, value) in corrections.iter() {
+            codeword[*index] ^= value;
+        }
+
+        Some(corrections.len())
+    }
+
+    fn modnn(&self, value: usize) -> usize {
+        value % RS_CODEWORD_LENGTH
+    }
+
+    /// Take a symbol from the configured basis to the conventional basis.
+    fn conventional_symbol(&self, symbol: u8) -> u8 {
+        if self.config.dual_basis { self.from_dual[symbol as usize] } else { symbol }
+    }
+
+    /// Take a symbol from the conventional basis to the configured basis.
+    fn channel_symbol(&self, symbol: u8) -> u8 {
+        if self.config.dual_basis { self.to_dual[symbol as usize] } else { symbol }
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+
+use primary_header::*;
+use time::*;
+
+
+/// A SecondaryHeader is a mission specific header following the primary header
+/// in packets with the secondary header flag set.
+///
+/// Many secondary headers have fields whose sizes are chosen by the mission, so
+/// reading and writing a header is done with a Layout giving these choices. Headers
+/// with a single fixed layout use () as their Layout.
+pub trait SecondaryHeader: Sized {
+    /// The parameters that determine the header's layout, such as field sizes.
+    type Layout;
+
+    /// The number of bytes in the header with the given layout.
+    fn size(layout: &Self::Layout) -> usize;
+
+    /// Read a header from the start of a byte slice, returning None if the bytes
+    /// do not contain a valid header.
+    fn parse(layout: &Self::Layout, bytes: &[u8]) -> Option<Self>;
+
+    /// Write a header to the start of a byte slice, returning the number of bytes
+    /// written, or None if the slice is too small or the header can not be
+    /// represented in the layout.
+    fn write(&self, layout: &Self::Layout, bytes: &mut [u8]) -> Option<usize>;
+}
+
+/// A time code alone is a common secondary header, so CUC times can be used
+/// directly as a secondary header.
+impl SecondaryHeader for CucTime {
+    type Layout = CucFormat;
+
+    fn size(layout: &CucFormat) -> usize {
+        layout.encoded_len()
+    }
+
+    fn parse(layout: &CucFormat, bytes: &[u8]) -> Option<CucTime> {
+        layout.decode(bytes).ok().map(|(time, _)| time)
+    }
+
+    fn write(&self, layout: &CucFormat, bytes: &mut [u8]) -> Option<usize> {
+        layout.encode(self, bytes).ok()
+    }
+}
+
+/// A time code alone is a common secondary header, so CDS times can be used
+/// directly as a secondary header.
+impl SecondaryHeader for CdsTime {
+    type Layout = CdsFormat;
+
+    fn size(layout: &CdsFormat) -> usize {
+        layout.encoded_len()
+    }
+
+    fn parse(layout: &CdsFormat, bytes: &[u8]) -> Option<CdsTime> {
+        layout.decode(bytes).ok().map(|(time, _)| time)
+    }
+
+    fn write(&self, layout: &CdsFormat, bytes: &mut [u8]) -> Option<usize> {
+        layout.encode(self, bytes).ok()
+    }
+}
+
+
+/// The SecondaryHeaderSizes give the number of bytes of secondary header expected
+/// in packets that have the secondary header flag set, by APID or by packet type.
+/// This is used by a CcsdsParser to reject packets too short to hold their
+/// secondary header. A size for an APID takes priority over a size for a packet type.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SecondaryHeaderSizes {
+    /// Secondary header sizes for particular APIDs.
+    pub apids: BTreeMap<u16, u32>,
+
+    /// The secondary header size for telemetry packets without an APID specific size.
+    pub data: Option<u32>,
+
+    /// The secondary header size for command packets without an APID specific size.
+    pub command: Option<u32>,
+}
+
+impl SecondaryHeaderSizes {
+    /// Create an empty set of sizes, which does not check any packets.
+    pub fn new() -> SecondaryHeaderSizes {
+        Default::default()
+    }
+
+    /// Check whether any sizes have been given.
+    pub fn is_empty(&self) -> bool {
+        self.apids.is_empty() && self.data.is_none() && self.command.is_none()
+    }
+
+    /// The expected secondary header size for a packet, if the packet has a secondary
+    /// header and a size is known for its APID or packet type.
+    pub fn size_for(&self, pri_header: &PrimaryHeader) -> Option<u32> {
+        if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return None;
+        }
+
+        match self.apids.get(&pri_header.control.apid()) {
+            Some(size) => Some(*size),
+            None => {
+                match pri_header.control.packet_type() {
+                    PacketType::Command => self.command,
+                    _ => self.data,
+                }
+            },
+        }
+    }
+}
+
+
+/// A SecondaryHeaderRegistry maps APIDs and packet types to the layout of their
+/// secondary header. All layouts in a registry are for the same header type H;
+/// missions with several kinds of secondary header can use an enum for H.
+///
+/// A layout registered for an APID takes priority over a layout registered for
+/// the packet's type.
+pub struct SecondaryHeaderRegistry<H: SecondaryHeader> {
+    apids: BTreeMap<u16, H::Layout>,
+    data: Option<H::Layout>,
+    command: Option<H::Layout>,
+}
+
+impl<H: SecondaryHeader> Default for SecondaryHeaderRegistry<H> {
+    fn default() -> SecondaryHeaderRegistry<H> {
+        SecondaryHeaderRegistry::new()
+    }
+}
+
+impl<H: SecondaryHeader> SecondaryHeaderRegistry<H> {
+    /// Create an empty registry.
+    pub fn new() -> SecondaryHeaderRegistry<H> {
+        SecondaryHeaderRegistry {
+            apids: BTreeMap::new(),
+            data: None,
+            command: None,
+        }
+    }
+
+    /// Register the secondary header layout for an APID.
+    pub fn register_apid(&mut self, apid: u16, layout: H::Layout) {
+        self.apids.insert(apid, layout);
+    }
+
+    /// Register the secondary header layout for all packets of a type which do
+    /// not have an APID specific layout.
+    pub fn register_packet_type(&mut self, packet_type: PacketType, layout: H::Layout) {
+        match packet_type {
+            PacketType::Command => self.command = Some(layout),
+            _ => self.data = Some(layout),
+        }
+    }
+
+    /// The layout of a packet's secondary header, if the packet has a secondary
+    /// header and a layout has been registered for it.
+    pub fn layout_for(&self, pri_header: &PrimaryHeader) -> Option<&H::Layout> {
+        if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return None;
+        }
+
+        match self.apids.get(&pri_header.control.apid()) {
+            Some(layout) => Some(layout),
+            None => {
+                match pri_header.control.packet_type() {
+                    PacketType::Command => self.command.as_ref(),
+                    _ => self.data.as_ref(),
+                }
+            },
+        }
+    }
+
+    /// Read the secondary header of a packet, starting with its primary header.
+    /// Bytes past the packet length given in the primary header are not part of the
+    /// secondary header.
+    pub fn parse(&self, packet: &[u8]) -> Option<H> {
+        let pri_header = PrimaryHeader::from_slice(packet)?;
+        let layout = self.layout_for(&pri_header)?;
+        let packet_length = packet.len().min(pri_header.packet_length() as usize);
+        H::parse(layout, &packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..packet_length])
+    }
+
+    /// The sizes of the registered secondary headers, for use in a CcsdsParserConfig.
+    pub fn sizes(&self) -> SecondaryHeaderSizes {
+        SecondaryHeaderSizes {
+            apids: self.apids.iter().map(|(apid, layout)| (*apid, H::size(layout) as u32)).collect(),
+            data: self.data.as_ref().map(|layout| H::size(layout) as u32),
+            command: self.command.as_ref().map(|layout| H::size(layout) as u32),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use primary_header::*;
+use secondary_header::*;
+
+
+/// A SpacePacket is a view of a complete CCSDS packet in a byte slice. It gives
+/// access to the primary header, a typed secondary header, and the data following
+/// the headers, without copying the packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SpacePacket<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SpacePacket<'a> {
+    /// Create a SpacePacket from the start of a byte slice. If the slice is too short
+    /// for the packet length given in the primary header then None is returned.
+    /// Any bytes following the packet are not part of the SpacePacket.
+    pub fn new(bytes: &'a [u8]) -> Option<SpacePacket<'a>> {
+        let pri_header = PrimaryHeader::from_slice(bytes)?;
+        let packet_length = pri_header.packet_length() as usize;
+
+        if bytes.len() < packet_length {
+            return None;
+        }
+
+        Some(SpacePacket { bytes: &bytes[0..packet_length] })
+    }
+
+    /// The packet's primary header.
+    pub fn primary_header(&self) -> PrimaryHeader {
+        PrimaryHeader::from_slice(self.bytes).unwrap()
+    }
+
+    /// The bytes of the whole packet, including the primary header.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The packet's data field, which is everything after the primary header.
+    /// This includes the secondary header, if there is one.
+    pub fn data_field(&self) -> &'a [u8] {
+        &self.bytes[CCSDS_PRI_HEADER_SIZE_BYTES as usize..]
+    }
+
+    /// Read the packet's secondary header with the given layout. If the packet
+    /// does not have its secondary header flag set, or the header can not be read,
+    /// then None is returned.
+    pub fn secondary_header<H: SecondaryHeader>(&self, layout: &H::Layout) -> Option<H> {
+        if self.primary_header().control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return None;
+        }
+
+        H::parse(layout, self.data_field())
+    }
+
+    /// Read the packet's secondary header using the layout registered for its APID
+    /// or packet type.
+    pub fn secondary_header_from<H: SecondaryHeader>(&self, registry: &SecondaryHeaderRegistry<H>) -> Option<H> {
+        registry.parse(self.bytes)
+    }
+
+    /// The user data following the secondary header, given the secondary header's layout.
+    /// If the packet has no secondary header, this is the whole data field.
+    pub fn user_data<H: SecondaryHeader>(&self, layout: &H::Layout) -> Option<&'a [u8]> {
+        if self.primary_header().control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return Some(self.data_field());
+        }
+
+        let data_field = self.data_field();
+        let size = H::size(layout);
+        if data_field.len() < size {
+            return None;
+        }
+
+        Some(&data_field[size..])
+    }
+}
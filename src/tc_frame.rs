@@ -0,0 +1,542 @@
+/*!
+The tc_frame module reads and writes TC Transfer Frames, as defined in CCSDS 232.0,
+TC Space Data Link Protocol, which carry command packets to a spacecraft.
+
+TC frames have a variable length of up to 1024 bytes. A frame's data field holds
+whole packets, or when the virtual channel uses segment headers, a segment of a packet
+too long for a single frame.
+*/
+use std::collections::{BTreeMap, VecDeque};
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+
+use frame::*;
+
+
+/// The size of the TC Transfer Frame primary header.
+pub const TC_PRI_HEADER_SIZE_BYTES: usize = 5;
+
+/// The size of the segment header, when used.
+pub const TC_SEGMENT_HEADER_SIZE_BYTES: usize = 1;
+
+/// The largest TC frame allowed by the standard.
+pub const TC_MAX_FRAME_LENGTH: usize = 1024;
+
+/// The transfer frame version number of TC frames.
+pub const TC_FRAME_VERSION: u8 = 0;
+
+/// The largest virtual channel ID.
+pub const TC_MAX_VCID: u8 = 0x3F;
+
+/// The largest MAP ID in a segment header.
+pub const TC_MAX_MAP_ID: u8 = 0x3F;
+
+
+/// The TcFrameType is given by the bypass and control command flags of a frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TcFrameType {
+    /// A sequence controlled data frame (Type-AD), delivered in order by COP-1.
+    AD,
+
+    /// An expedited data frame (Type-BD), which bypasses the COP-1 acceptance checks.
+    BD,
+
+    /// An expedited control command frame (Type-BC), used to control the FARM-1 on board.
+    BC,
+
+    /// A sequence controlled control command frame, which the standard does not allow.
+    Invalid,
+}
+
+impl TcFrameType {
+    /// The bypass flag of frames of this type.
+    pub fn bypass_flag(&self) -> bool {
+        *self != TcFrameType::AD
+    }
+
+    /// The control command flag of frames of this type.
+    pub fn control_command_flag(&self) -> bool {
+        *self == TcFrameType::BC || *self == TcFrameType::Invalid
+    }
+}
+
+/// The sequence flags of a segment header, giving where a segment falls in a packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SegmentFlag {
+    Continuation,
+    First,
+    Last,
+    Unsegmented,
+}
+
+impl From<u8> for SegmentFlag {
+    fn from(byte: u8) -> SegmentFlag {
+        match byte & 0x03 {
+            0 => SegmentFlag::Continuation,
+            1 => SegmentFlag::First,
+            2 => SegmentFlag::Last,
+            _ => SegmentFlag::Unsegmented,
+        }
+    }
+}
+
+impl From<SegmentFlag> for u8 {
+    fn from(flag: SegmentFlag) -> u8 {
+        match flag {
+            SegmentFlag::Continuation => 0,
+            SegmentFlag::First => 1,
+            SegmentFlag::Last => 2,
+            SegmentFlag::Unsegmented => 3,
+        }
+    }
+}
+
+
+/// The primary header of a TC Transfer Frame, kept as its raw bytes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TcFrameHeader(pub [u8; 5]);
+
+impl TcFrameHeader {
+    /// Create a TcFrameHeader from a slice. If the slice is not long enough then
+    /// None is returned.
+    pub fn from_slice(bytes: &[u8]) -> Option<TcFrameHeader> {
+        if bytes.len() < TC_PRI_HEADER_SIZE_BYTES {
+            return None;
+        }
+
+        let mut header = TcFrameHeader::default();
+        header.0.copy_from_slice(&bytes[0..TC_PRI_HEADER_SIZE_BYTES]);
+        Some(header)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[0] >> 6
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.0[0] = (self.0[0] & 0x3F) | ((version & 0x03) << 6);
+    }
+
+    pub fn bypass_flag(&self) -> bool {
+        (self.0[0] & 0x20) != 0
+    }
+
+    pub fn set_bypass_flag(&mut self, flag: bool) {
+        self.0[0] = (self.0[0] & 0xDF) | ((flag as u8) << 5);
+    }
+
+    pub fn control_command_flag(&self) -> bool {
+        (self.0[0] & 0x10) != 0
+    }
+
+    pub fn set_control_command_flag(&mut self, flag: bool) {
+        self.0[0] = (self.0[0] & 0xEF) | ((flag as u8) << 4);
+    }
+
+    /// The frame type given by the bypass and control command flags.
+    pub fn frame_type(&self) -> TcFrameType {
+        match (self.bypass_flag(), self.control_command_flag()) {
+            (false, false) => TcFrameType::AD,
+            (true, false) => TcFrameType::BD,
+            (true, true) => TcFrameType::BC,
+            (false, true) => TcFrameType::Invalid,
+        }
+    }
+
+    pub fn set_frame_type(&mut self, frame_type: TcFrameType) {
+        self.set_bypass_flag(frame_type.bypass_flag());
+        self.set_control_command_flag(frame_type.control_command_flag());
+    }
+
+    pub fn spacecraft_id(&self) -> u16 {
+        BigEndian::read_u16(&self.0[0..2]) & 0x03FF
+    }
+
+    pub fn set_spacecraft_id(&mut self, spacecraft_id: u16) {
+        let word = (BigEndian::read_u16(&self.0[0..2]) & 0xFC00) | (spacecraft_id & 0x03FF);
+        BigEndian::write_u16(&mut self.0[0..2], word);
+    }
+
+    pub fn vcid(&self) -> u8 {
+        self.0[2] >> 2
+    }
+
+    pub fn set_vcid(&mut self, vcid: u8) {
+        self.0[2] = (self.0[2] & 0x03) | ((vcid & TC_MAX_VCID) << 2);
+    }
+
+    /// The raw frame length field, which is the frame length less one.
+    pub fn frame_length_field(&self) -> u16 {
+        BigEndian::read_u16(&self.0[2..4]) & 0x03FF
+    }
+
+    pub fn set_frame_length_field(&mut self, length: u16) {
+        let word = (BigEndian::read_u16(&self.0[2..4]) & 0xFC00) | (length & 0x03FF);
+        BigEndian::write_u16(&mut self.0[2..4], word);
+    }
+
+    /// The length of the frame in bytes, including the header and any FECF.
+    pub fn frame_length(&self) -> usize {
+        self.frame_length_field() as usize + 1
+    }
+
+    /// Set the length of the frame in bytes, including the header and any FECF.
+    pub fn set_frame_length(&mut self, frame_length: usize) {
+        self.set_frame_length_field(frame_length.saturating_sub(1) as u16);
+    }
+
+    pub fn sequence_number(&self) -> u8 {
+        self.0[4]
+    }
+
+    pub fn set_sequence_number(&mut self, sequence_number: u8) {
+        self.0[4] = sequence_number;
+    }
+}
+
+
+/// A TcFrameConfig gives the managed parameters of a TC channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TcFrameConfig {
+    /// The spacecraft the frames are for. Frames for other spacecraft are rejected
+    /// when decoding.
+    pub spacecraft_id: u16,
+
+    /// Whether frames end with a Frame Error Control Field.
+    pub has_fecf: bool,
+
+    /// Whether the data field of each frame starts with a segment header.
+    pub has_segment_header: bool,
+
+    /// The largest frame that may be sent, up to 1024 bytes.
+    pub max_frame_length: usize,
+}
+
+impl TcFrameConfig {
+    /// Create a configuration for a spacecraft, with a FECF, no segment headers,
+    /// and the largest frame length allowed by the standard.
+    pub fn new(spacecraft_id: u16) -> TcFrameConfig {
+        TcFrameConfig {
+            spacecraft_id,
+            has_fecf: true,
+            has_segment_header: false,
+            max_frame_length: TC_MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// The number of bytes of each frame which are not data: the primary header,
+    /// any segment header and any FECF.
+    pub fn overhead(&self) -> usize {
+        let mut overhead = TC_PRI_HEADER_SIZE_BYTES;
+        if self.has_segment_header {
+            overhead += TC_SEGMENT_HEADER_SIZE_BYTES;
+        }
+        if self.has_fecf {
+            overhead += FECF_SIZE_BYTES;
+        }
+        overhead
+    }
+
+    /// The largest data field of a frame with this configuration.
+    pub fn max_data_length(&self) -> usize {
+        self.max_frame_length.min(TC_MAX_FRAME_LENGTH).saturating_sub(self.overhead())
+    }
+}
+
+
+/// A TcFrame is a view of a TC Transfer Frame in a byte slice, checked against
+/// the channel's configuration.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TcFrame<'a> {
+    bytes: &'a [u8],
+    header: TcFrameHeader,
+    config: TcFrameConfig,
+}
+
+impl<'a> TcFrame<'a> {
+    /// Read a frame, checking its version, spacecraft ID, length and FECF. Control
+    /// command frames never have a segment header. Any bytes after the frame length
+    /// given in the header are ignored.
+    pub fn parse(config: &TcFrameConfig, bytes: &'a [u8]) -> Result<TcFrame<'a>, FrameError> {
+        let header = TcFrameHeader::from_slice(bytes).ok_or(FrameError::NotEnoughBytes)?;
+
+        if header.version() != TC_FRAME_VERSION {
+            return Err(FrameError::InvalidVersion(header.version()));
+        }
+
+        if header.spacecraft_id() != config.spacecraft_id {
+            return Err(FrameError::WrongSpacecraft(header.spacecraft_id()));
+        }
+
+        let frame_length = header.frame_length();
+        if frame_length < TC_PRI_HEADER_SIZE_BYTES {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        if bytes.len() < frame_length {
+            return Err(FrameError::WrongLength { expected: frame_length, actual: bytes.len() });
+        }
+
+        let frame = TcFrame { bytes: &bytes[0..frame_length], header, config: *config };
+        if frame.data_start() > frame.data_end() || header.frame_type() == TcFrameType::Invalid {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        if config.has_fecf && !fecf_is_valid(frame.bytes) {
+            return Err(FrameError::InvalidFecf);
+        }
+
+        Ok(frame)
+    }
+
+    /// The frame's primary header.
+    pub fn header(&self) -> TcFrameHeader {
+        self.header
+    }
+
+    /// The bytes of the whole frame.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The segment flag and MAP ID of the frame's segment header, if it has one.
+    pub fn segment_header(&self) -> Option<(SegmentFlag, u8)> {
+        if !self.has_segment_header() {
+            return None;
+        }
+
+        let byte = self.bytes[TC_PRI_HEADER_SIZE_BYTES];
+        Some((SegmentFlag::from(byte >> 6), byte & TC_MAX_MAP_ID))
+    }
+
+    /// The frame's data field, not including any segment header.
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[self.data_start()..self.data_end()]
+    }
+
+    /// The Frame Error Control Field, if the channel uses one.
+    pub fn fecf(&self) -> Option<u16> {
+        if !self.config.has_fecf {
+            return None;
+        }
+
+        Some(BigEndian::read_u16(&self.bytes[self.bytes.len() - FECF_SIZE_BYTES..]))
+    }
+
+    fn has_segment_header(&self) -> bool {
+        self.config.has_segment_header && !self.header().control_command_flag()
+    }
+
+    fn data_start(&self) -> usize {
+        if self.has_segment_header() {
+            TC_PRI_HEADER_SIZE_BYTES + TC_SEGMENT_HEADER_SIZE_BYTES
+        } else {
+            TC_PRI_HEADER_SIZE_BYTES
+        }
+    }
+
+    fn data_end(&self) -> usize {
+        if self.config.has_fecf {
+            self.bytes.len().saturating_sub(FECF_SIZE_BYTES)
+        } else {
+            self.bytes.len()
+        }
+    }
+}
+
+
+/// A TcFrameEncoder wraps command packets in TC frames for a spacecraft. It keeps
+/// the frame sequence number of each virtual channel, which counts Type-AD frames.
+/// Type-BD and Type-BC frames have a sequence number of 0.
+#[derive(Debug, Clone)]
+pub struct TcFrameEncoder {
+    /// The configuration of the TC channel.
+    pub config: TcFrameConfig,
+
+    sequence_numbers: BTreeMap<u8, u8>,
+}
+
+impl TcFrameEncoder {
+    /// Create an encoder with all sequence numbers starting at 0.
+    pub fn new(config: TcFrameConfig) -> TcFrameEncoder {
+        TcFrameEncoder {
+            config,
+            sequence_numbers: BTreeMap::new(),
+        }
+    }
+
+    /// The sequence number of the next Type-AD frame on a virtual channel, V(S) in COP-1.
+    pub fn next_sequence_number(&self, vcid: u8) -> u8 {
+        self.sequence_numbers.get(&vcid).cloned().unwrap_or(0)
+    }
+
+    /// Set the sequence number of the next Type-AD frame on a virtual channel.
+    pub fn set_next_sequence_number(&mut self, vcid: u8, sequence_number: u8) {
+        self.sequence_numbers.insert(vcid, sequence_number);
+    }
+
+    /// Build a single frame around the given data, such as one or more packets.
+    /// If the channel uses segment headers, the data is marked as unsegmented with the
+    /// given MAP ID. Control command frames never have a segment header.
+    pub fn encode(&mut self, frame_type: TcFrameType, vcid: u8, map_id: u8, data: &[u8]) -> Result<Vec<u8>, FrameError> {
+        self.encode_segment(frame_type, vcid, map_id, SegmentFlag::Unsegmented, data)
+    }
+
+    /// Build the frames carrying a packet. If the packet does not fit in a single frame,
+    /// and the channel uses segment headers, it is split into segments across several
+    /// frames. Otherwise a packet too long for a frame is an error.
+    pub fn encode_packet(&mut self, frame_type: TcFrameType, vcid: u8, map_id: u8, packet: &[u8]) -> Result<Vec<Vec<u8>>, FrameError> {
+        let max_data_length = self.config.max_data_length();
+
+        if packet.len() <= max_data_length || !self.config.has_segment_header || frame_type == TcFrameType::BC {
+            return Ok(vec![self.encode(frame_type, vcid, map_id, packet)?]);
+        }
+
+        if max_data_length == 0 {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        let num_segments = (packet.len() + max_data_length - 1) / max_data_length;
+        let mut frames = Vec::with_capacity(num_segments);
+
+        for (index, segment) in packet.chunks(max_data_length).enumerate() {
+            let flag = if index == 0 {
+                SegmentFlag::First
+            } else if index == num_segments - 1 {
+                SegmentFlag::Last
+            } else {
+                SegmentFlag::Continuation
+            };
+
+            frames.push(self.encode_segment(frame_type, vcid, map_id, flag, segment)?);
+        }
+
+        Ok(frames)
+    }
+
+    fn encode_segment(&mut self,
+                      frame_type: TcFrameType,
+                      vcid: u8,
+                      map_id: u8,
+                      flag: SegmentFlag,
+                      data: &[u8]) -> Result<Vec<u8>, FrameError> {
+        if frame_type == TcFrameType::Invalid || vcid > TC_MAX_VCID || map_id > TC_MAX_MAP_ID {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        let segment_header = self.config.has_segment_header && frame_type != TcFrameType::BC;
+
+        let mut frame_length = TC_PRI_HEADER_SIZE_BYTES + data.len();
+        if segment_header {
+            frame_length += TC_SEGMENT_HEADER_SIZE_BYTES;
+        }
+        if self.config.has_fecf {
+            frame_length += FECF_SIZE_BYTES;
+        }
+
+        if frame_length > self.config.max_frame_length.min(TC_MAX_FRAME_LENGTH) {
+            return Err(FrameError::FrameTooLong(frame_length));
+        }
+
+        let sequence_number = if frame_type == TcFrameType::AD {
+            let sequence_number = self.next_sequence_number(vcid);
+            self.sequence_numbers.insert(vcid, sequence_number.wrapping_add(1));
+            sequence_number
+        } else {
+            0
+        };
+
+        let mut header = TcFrameHeader::default();
+        header.set_version(TC_FRAME_VERSION);
+        header.set_frame_type(frame_type);
+        header.set_spacecraft_id(self.config.spacecraft_id);
+        header.set_vcid(vcid);
+        header.set_frame_length(frame_length);
+        header.set_sequence_number(sequence_number);
+
+        let mut frame = Vec::with_capacity(frame_length);
+        frame.extend_from_slice(&header.0);
+        if segment_header {
+            frame.push((u8::from(flag) << 6) | map_id);
+        }
+        frame.extend_from_slice(data);
+
+        if self.config.has_fecf {
+            append_fecf(&mut frame);
+        }
+
+        Ok(frame)
+    }
+}
+
+
+/// A TcFrameDecoder unwraps TC frames back into the packets they carry. Segmented
+/// packets are reassembled separately for each virtual channel and MAP ID. Control
+/// command frames carry no packets, and are only checked.
+#[derive(Debug, Clone)]
+pub struct TcFrameDecoder {
+    /// The configuration of the TC channel.
+    pub config: TcFrameConfig,
+
+    /// The number of frames which could not be read.
+    pub invalid_frames: usize,
+
+    extractors: BTreeMap<(u8, u8), PacketExtractor>,
+
+    packets: VecDeque<(u8, BytesMut)>,
+}
+
+impl TcFrameDecoder {
+    /// Create a decoder for a TC channel.
+    pub fn new(config: TcFrameConfig) -> TcFrameDecoder {
+        TcFrameDecoder {
+            config,
+            invalid_frames: 0,
+            extractors: BTreeMap::new(),
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Process a frame, extracting any packets it completes. Frames that can not be
+    /// read are counted and their error returned.
+    pub fn recv_frame(&mut self, bytes: &[u8]) -> Result<TcFrameHeader, FrameError> {
+        let frame = match TcFrame::parse(&self.config, bytes) {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.invalid_frames += 1;
+                return Err(err);
+            },
+        };
+
+        let header = frame.header();
+        if header.control_command_flag() {
+            return Ok(header);
+        }
+
+        let (flag, map_id) = frame.segment_header().unwrap_or((SegmentFlag::Unsegmented, 0));
+        let vcid = header.vcid();
+        let extractor = self.extractors.entry((vcid, map_id)).or_default();
+
+        match flag {
+            SegmentFlag::First | SegmentFlag::Unsegmented => extractor.recv_data(frame.data(), Some(0)),
+            SegmentFlag::Continuation | SegmentFlag::Last => extractor.recv_data(frame.data(), None),
+        }
+
+        while let Some(packet) = extractor.pull_packet() {
+            self.packets.push_back((vcid, packet));
+        }
+
+        Ok(header)
+    }
+
+    /// Take the next packet unwrapped from the frames.
+    pub fn pull_packet(&mut self) -> Option<BytesMut> {
+        self.packets.pop_front().map(|(_, packet)| packet)
+    }
+
+    /// Take the next packet, along with the virtual channel it was carried on.
+    pub fn pull_packet_with_vcid(&mut self) -> Option<(u8, BytesMut)> {
+        self.packets.pop_front()
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::str::FromStr;
+
+use time::*;
+
+
+/// The CCSDS ASCII time code formats, from CCSDS 301.0. Both formats are UTC,
+/// and may be followed by a 'Z'.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AsciiTimeFormat {
+    /// Calendar format, YYYY-MM-DDThh:mm:ss.d->dZ
+    A,
+
+    /// Day of year format, YYYY-DDDThh:mm:ss.d->dZ
+    B,
+}
+
+impl Default for AsciiTimeFormat {
+    fn default() -> AsciiTimeFormat {
+        AsciiTimeFormat::A
+    }
+}
+
+/// The largest number of fractional second digits kept when parsing, as times
+/// are kept to a resolution of nanoseconds.
+pub const ASCII_MAX_FRACTION_DIGITS: usize = 9;
+
+
+/// Parse an ASCII time code in either format A or B. The format is determined from
+/// the date. As allowed by the standard, the time may be truncated from the right,
+/// so "2017-001" and "2017-01-01T12:30" are both valid times.
+pub fn parse_ascii_time(text: &str) -> Result<UtcDateTime, TimeError> {
+    let text = text.strip_suffix('Z').unwrap_or(text);
+
+    let (date, time_of_day) = match text.find('T') {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+
+    let date_fields: Vec<&str> = date.split('-').collect();
+    let mut utc = match date_fields.as_slice() {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            UtcDateTime::new(parse_digits(year)? as i64, parse_digits(month)?, parse_digits(day)?, 0, 0, 0)
+        },
+
+        [year, day_of_year] if year.len() == 4 && day_of_year.len() == 3 => {
+            let year = parse_digits(year)? as i64;
+            let day_of_year = parse_digits(day_of_year)?;
+            if day_of_year < 1 || day_of_year > days_in_year(year) {
+                return Err(TimeError::InvalidDate);
+            }
+            UtcDateTime::from_day_of_year(year, day_of_year, 0, 0, 0)
+        },
+
+        _ => return Err(TimeError::InvalidAsciiTime),
+    };
+
+    if let Some(time_of_day) = time_of_day {
+        let (time_of_day, fraction) = match time_of_day.find('.') {
+            Some(index) => (&time_of_day[..index], Some(&time_of_day[index + 1..])),
+            None => (time_of_day, None),
+        };
+
+        let fields: Vec<&str> = time_of_day.split(':').collect();
+        if fields.is_empty() || fields.len() > 3 || fields.iter().any(|field| field.len() != 2) {
+            return Err(TimeError::InvalidAsciiTime);
+        }
+
+        // a fraction is only allowed on the seconds field
+        if fraction.is_some() && fields.len() != 3 {
+            return Err(TimeError::InvalidAsciiTime);
+        }
+
+        let values = fields.iter().map(|field| parse_digits(field)).collect::<Result<Vec<u32>, TimeError>>()?;
+        utc.hour = values[0];
+        utc.minute = values.get(1).cloned().unwrap_or(0);
+        utc.second = values.get(2).cloned().unwrap_or(0);
+
+        if let Some(fraction) = fraction {
+            if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+                return Err(TimeError::InvalidAsciiTime);
+            }
+
+            // digits beyond nanoseconds are dropped
+            let kept = &fraction[..fraction.len().min(ASCII_MAX_FRACTION_DIGITS)];
+            utc.nanosecond = parse_digits(kept)? * 10u32.pow((ASCII_MAX_FRACTION_DIGITS - kept.len()) as u32);
+        }
+    }
+
+    if !utc.is_valid() {
+        return Err(TimeError::InvalidDate);
+    }
+
+    Ok(utc)
+}
+
+/// Format a UTC time as an ASCII time code, with the given number of fractional
+/// second digits (up to 9) and a trailing 'Z'. Fractional seconds are truncated.
+pub fn format_ascii_time(utc: &UtcDateTime, format: AsciiTimeFormat, fraction_digits: usize) -> String {
+    let date = match format {
+        AsciiTimeFormat::A => format!("{:04}-{:02}-{:02}", utc.year, utc.month, utc.day),
+        AsciiTimeFormat::B => format!("{:04}-{:03}", utc.year, utc.day_of_year()),
+    };
+
+    let mut text = format!("{}T{:02}:{:02}:{:02}", date, utc.hour, utc.minute, utc.second);
+
+    let fraction_digits = fraction_digits.min(ASCII_MAX_FRACTION_DIGITS);
+    if fraction_digits > 0 {
+        let fraction = utc.nanosecond / 10u32.pow((ASCII_MAX_FRACTION_DIGITS - fraction_digits) as u32);
+        text.push_str(&format!(".{:0width$}", fraction, width = fraction_digits));
+    }
+
+    text.push('Z');
+    text
+}
+
+/// Read a binary time code and format it as an ASCII time code.
+pub fn time_to_ascii<F: TimeCodeFormat>(format: &F,
+                                        time: &F::Time,
+                                        ascii_format: AsciiTimeFormat,
+                                        fraction_digits: usize) -> String {
+    format_ascii_time(&format.to_utc(time), ascii_format, fraction_digits)
+}
+
+/// Parse an ASCII time code and convert it to a binary time code.
+pub fn time_from_ascii<F: TimeCodeFormat>(format: &F, text: &str) -> Result<F::Time, TimeError> {
+    format.time_from_utc(&parse_ascii_time(text)?)
+}
+
+impl fmt::Display for UtcDateTime {
+    /// Display a time as an ASCII time code in format A, with millisecond resolution.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_ascii_time(self, AsciiTimeFormat::A, 3))
+    }
+}
+
+impl FromStr for UtcDateTime {
+    type Err = TimeError;
+
+    fn from_str(text: &str) -> Result<UtcDateTime, TimeError> {
+        parse_ascii_time(text)
+    }
+}
+
+fn parse_digits(text: &str) -> Result<u32, TimeError> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return Err(TimeError::InvalidAsciiTime);
+    }
+
+    text.parse::<u32>().map_err(|_| TimeError::InvalidAsciiTime)
+}
+
+fn days_in_year(year: i64) -> u32 {
+    (days_from_civil(year + 1, 1, 1) - days_from_civil(year, 1, 1)) as u32
+}
@@ -0,0 +1,410 @@
+use std::time::Duration;
+
+use time::*;
+
+
+/// The time code ID of a CDS time code, as given in its P-field.
+pub const CDS_TIME_CODE_ID: u8 = 0x4;
+
+/// The number of milliseconds in a day, not including leap seconds.
+pub const MILLISECONDS_PER_DAY: u32 = 86_400_000;
+
+
+/// The size of the day segment of a CDS time code.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CdsDaySegment {
+    /// The number of days is a 16 bit field.
+    Days16,
+
+    /// The number of days is a 24 bit field.
+    Days24,
+}
+
+impl Default for CdsDaySegment {
+    fn default() -> CdsDaySegment {
+        CdsDaySegment::Days16
+    }
+}
+
+impl CdsDaySegment {
+    /// The number of octets in the day segment.
+    pub fn octets(&self) -> usize {
+        match *self {
+            CdsDaySegment::Days16 => 2,
+            CdsDaySegment::Days24 => 3,
+        }
+    }
+}
+
+/// The resolution of the optional submillisecond segment of a CDS time code.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CdsSubMillis {
+    /// There is no submillisecond segment.
+    None,
+
+    /// A 16 bit count of microseconds within the millisecond.
+    Microseconds,
+
+    /// A 32 bit count of picoseconds within the millisecond.
+    Picoseconds,
+}
+
+impl Default for CdsSubMillis {
+    fn default() -> CdsSubMillis {
+        CdsSubMillis::None
+    }
+}
+
+impl CdsSubMillis {
+    /// The number of octets in the submillisecond segment.
+    pub fn octets(&self) -> usize {
+        match *self {
+            CdsSubMillis::None => 0,
+            CdsSubMillis::Microseconds => 2,
+            CdsSubMillis::Picoseconds => 4,
+        }
+    }
+
+    /// The number of submillisecond units in a millisecond.
+    fn units_per_milli(&self) -> u64 {
+        match *self {
+            CdsSubMillis::None => 1,
+            CdsSubMillis::Microseconds => 1_000,
+            CdsSubMillis::Picoseconds => 1_000_000_000,
+        }
+    }
+}
+
+/// A CDS time is a CCSDS Day Segmented Time Code value. It is a count of days since an
+/// epoch, the milliseconds into the day, and optionally a count of microseconds or
+/// picoseconds within that millisecond.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Hash)]
+pub struct CdsTime {
+    /// The number of days since the epoch.
+    pub days: u32,
+
+    /// The number of milliseconds into the day. This may be 86400000 or more during
+    /// a leap second, for time codes on the UTC time scale.
+    pub ms_of_day: u32,
+
+    /// The microseconds or picoseconds into the millisecond, depending on the format.
+    /// This is 0 for formats without a submillisecond segment.
+    pub submillis: u32,
+}
+
+/// A CdsFormat describes a CCSDS Day Segmented Time Code (CDS), as defined in CCSDS 301.0.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CdsFormat {
+    /// The size of the day segment.
+    pub day_segment: CdsDaySegment,
+
+    /// The resolution of the submillisecond segment, if there is one.
+    pub submillis: CdsSubMillis,
+
+    /// Whether the P-field is sent with the time code.
+    pub pfield: PField,
+
+    /// The epoch of the time code, either the CCSDS epoch or an agency defined epoch.
+    pub epoch: Epoch,
+
+    /// The time scale of the time code. The CCSDS epoch is defined in TAI, but CDS
+    /// times are often kept in UTC, which includes leap seconds in its day segment.
+    pub time_scale: TimeScale,
+}
+
+impl Default for CdsFormat {
+    fn default() -> CdsFormat {
+        CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None)
+    }
+}
+
+impl CdsFormat {
+    /// Create a CDS format with the given segment sizes, an implicit P-field,
+    /// the CCSDS epoch and the TAI time scale.
+    pub fn new(day_segment: CdsDaySegment, submillis: CdsSubMillis) -> CdsFormat {
+        CdsFormat {
+            day_segment,
+            submillis,
+            pfield: PField::Implicit,
+            epoch: CCSDS_EPOCH,
+            time_scale: TimeScale::Tai,
+        }
+    }
+
+    /// Whether the epoch is the CCSDS epoch, as opposed to an agency defined epoch.
+    pub fn is_ccsds_epoch(&self) -> bool {
+        self.epoch == CCSDS_EPOCH
+    }
+
+    /// The P-field byte describing this format.
+    pub fn pfield_byte(&self) -> u8 {
+        let epoch_id = if self.is_ccsds_epoch() { 0 } else { 1 };
+        let day_length = match self.day_segment {
+            CdsDaySegment::Days16 => 0,
+            CdsDaySegment::Days24 => 1,
+        };
+        let resolution = match self.submillis {
+            CdsSubMillis::None => 0,
+            CdsSubMillis::Microseconds => 1,
+            CdsSubMillis::Picoseconds => 2,
+        };
+
+        (CDS_TIME_CODE_ID << 4) | (epoch_id << 3) | (day_length << 2) | resolution
+    }
+
+    /// Create a format from a P-field byte. The epoch ID bit selects the CCSDS epoch or
+    /// this format's agency defined epoch, so a P-field asking for an agency epoch is
+    /// rejected if this format uses the CCSDS epoch. The time scale is not part of the
+    /// P-field, so it is taken from this format.
+    pub fn with_pfield_byte(&self, pfield: u8) -> Result<CdsFormat, TimeError> {
+        let extension = (pfield & 0x80) != 0;
+        let time_code_id = (pfield >> 4) & 0x07;
+
+        if extension || time_code_id != CDS_TIME_CODE_ID {
+            return Err(TimeError::InvalidPField(pfield));
+        }
+
+        let epoch =
+            if (pfield & 0x08) == 0 {
+                CCSDS_EPOCH
+            } else if !self.is_ccsds_epoch() {
+                self.epoch
+            } else {
+                return Err(TimeError::InvalidPField(pfield));
+            };
+
+        let day_segment =
+            if (pfield & 0x04) == 0 { CdsDaySegment::Days16 } else { CdsDaySegment::Days24 };
+
+        let submillis =
+            match pfield & 0x03 {
+                0 => CdsSubMillis::None,
+                1 => CdsSubMillis::Microseconds,
+                2 => CdsSubMillis::Picoseconds,
+                _ => return Err(TimeError::InvalidPField(pfield)),
+            };
+
+        Ok(CdsFormat {
+            day_segment,
+            submillis,
+            pfield: PField::Explicit,
+            epoch,
+            .. *self
+        })
+    }
+
+    /// The number of bytes in the time code fields (the T-field), not including the P-field.
+    pub fn tfield_len(&self) -> usize {
+        self.day_segment.octets() + 4 + self.submillis.octets()
+    }
+
+    /// Check that the fields of a time are in range for this format.
+    pub fn is_valid_time(&self, time: &CdsTime) -> bool {
+        let max_days = match self.day_segment {
+            CdsDaySegment::Days16 => 0xFFFF,
+            CdsDaySegment::Days24 => 0xFF_FFFF,
+        };
+
+        // a UTC day may have one extra second
+        let ms_per_day = match self.time_scale {
+            TimeScale::Tai => MILLISECONDS_PER_DAY,
+            TimeScale::Utc => MILLISECONDS_PER_DAY + 1000,
+        };
+
+        time.days <= max_days &&
+        time.ms_of_day < ms_per_day &&
+        (time.submillis as u64) < self.submillis.units_per_milli()
+    }
+
+    /// The submillisecond segment for a time with the given fractional nanoseconds.
+    fn submillis_from_nanos(&self, nanos: u32) -> u32 {
+        let nanos_of_milli = nanos % 1_000_000;
+        match self.submillis {
+            CdsSubMillis::None => 0,
+            CdsSubMillis::Microseconds => nanos_of_milli / 1000,
+            CdsSubMillis::Picoseconds => nanos_of_milli * 1000,
+        }
+    }
+
+    fn decode_tfield(&self, bytes: &[u8]) -> Result<CdsTime, TimeError> {
+        if bytes.len() < self.tfield_len() {
+            return Err(TimeError::NotEnoughBytes);
+        }
+
+        let read = |bytes: &[u8]| bytes.iter().fold(0u32, |acc, byte| (acc << 8) | *byte as u32);
+
+        let day_octets = self.day_segment.octets();
+        let time = CdsTime {
+            days: read(&bytes[0..day_octets]),
+            ms_of_day: read(&bytes[day_octets..day_octets + 4]),
+            submillis: read(&bytes[day_octets + 4..self.tfield_len()]),
+        };
+
+        if !self.is_valid_time(&time) {
+            return Err(TimeError::OutOfRange);
+        }
+
+        Ok(time)
+    }
+}
+
+impl TimeCodeFormat for CdsFormat {
+    type Time = CdsTime;
+
+    fn encoded_len(&self) -> usize {
+        match self.pfield {
+            PField::Implicit => self.tfield_len(),
+            PField::Explicit => self.tfield_len() + 1,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<(CdsTime, usize), TimeError> {
+        match self.pfield {
+            PField::Implicit => {
+                let time = self.decode_tfield(bytes)?;
+                Ok((time, self.tfield_len()))
+            },
+
+            PField::Explicit => {
+                if bytes.is_empty() {
+                    return Err(TimeError::NotEnoughBytes);
+                }
+
+                let format = self.with_pfield_byte(bytes[0])?;
+                let time = format.decode_tfield(&bytes[1..])?;
+                Ok((time, format.tfield_len() + 1))
+            },
+        }
+    }
+
+    fn encode(&self, time: &CdsTime, bytes: &mut [u8]) -> Result<usize, TimeError> {
+        if bytes.len() < self.encoded_len() {
+            return Err(TimeError::NotEnoughBytes);
+        }
+
+        if !self.is_valid_time(time) {
+            return Err(TimeError::OutOfRange);
+        }
+
+        let mut index = 0;
+        if self.pfield == PField::Explicit {
+            bytes[0] = self.pfield_byte();
+            index += 1;
+        }
+
+        let mut write = |value: u32, octets: usize| {
+            for octet in (0..octets).rev() {
+                bytes[index] = (value >> (8 * octet)) as u8;
+                index += 1;
+            }
+        };
+
+        write(time.days, self.day_segment.octets());
+        write(time.ms_of_day, 4);
+        write(time.submillis, self.submillis.octets());
+
+        Ok(self.encoded_len())
+    }
+
+    fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    fn time_scale(&self) -> TimeScale {
+        self.time_scale
+    }
+
+    fn to_duration(&self, time: &CdsTime) -> Duration {
+        let submillis_nanos = match self.submillis {
+            CdsSubMillis::None => 0,
+            CdsSubMillis::Microseconds => time.submillis as u64 * 1000,
+            CdsSubMillis::Picoseconds => time.submillis as u64 / 1000,
+        };
+
+        Duration::from_secs(time.days as u64 * SECONDS_PER_DAY as u64) +
+            Duration::from_millis(time.ms_of_day as u64) +
+            Duration::from_nanos(submillis_nanos)
+    }
+
+    fn time_from_duration(&self, duration: Duration) -> Result<CdsTime, TimeError> {
+        let days = duration.as_secs() / SECONDS_PER_DAY as u64;
+        let ms_of_day = (duration.as_secs() % SECONDS_PER_DAY as u64) * 1000 +
+                        duration.subsec_millis() as u64;
+        let submillis = self.submillis_from_nanos(duration.subsec_nanos());
+
+        if days > u32::MAX as u64 {
+            return Err(TimeError::OutOfRange);
+        }
+
+        let time = CdsTime {
+            days: days as u32,
+            ms_of_day: ms_of_day as u32,
+            submillis,
+        };
+
+        if !self.is_valid_time(&time) {
+            return Err(TimeError::OutOfRange);
+        }
+
+        Ok(time)
+    }
+
+    fn to_utc(&self, time: &CdsTime) -> UtcDateTime {
+        let duration = self.to_duration(time);
+
+        match self.time_scale {
+            TimeScale::Tai => {
+                utc_from_tai(self.epoch.0 + duration.as_secs() as i64, duration.subsec_nanos())
+            },
+
+            TimeScale::Utc => {
+                // the day segment already accounts for leap seconds, so the time of day
+                // is read directly, including a leap second at the end of the day
+                let day_start = self.epoch.0 + time.days as i64 * SECONDS_PER_DAY;
+                let mut utc = UtcDateTime::from_unix_seconds(day_start, duration.subsec_nanos());
+
+                let second_of_day = time.ms_of_day / 1000;
+                utc.hour = (second_of_day / 3600).min(23);
+                utc.minute = if second_of_day >= 86400 { 59 } else { (second_of_day / 60) % 60 };
+                utc.second = if second_of_day >= 86400 { 60 } else { second_of_day % 60 };
+                utc
+            },
+        }
+    }
+
+    fn time_from_utc(&self, utc: &UtcDateTime) -> Result<CdsTime, TimeError> {
+        if !utc.is_valid() {
+            return Err(TimeError::InvalidDate);
+        }
+
+        match self.time_scale {
+            TimeScale::Tai => {
+                let since_epoch = tai_from_utc(utc) - self.epoch.0;
+                if since_epoch < 0 {
+                    return Err(TimeError::OutOfRange);
+                }
+                self.time_from_duration(Duration::new(since_epoch as u64, utc.nanosecond))
+            },
+
+            TimeScale::Utc => {
+                let days = utc.days_since_unix_epoch() - self.epoch.0.div_euclid(SECONDS_PER_DAY);
+                if days < 0 {
+                    return Err(TimeError::OutOfRange);
+                }
+
+                let second_of_day = utc.hour * 3600 + utc.minute * 60 + utc.second;
+                let time = CdsTime {
+                    days: days as u32,
+                    ms_of_day: second_of_day * 1000 + utc.nanosecond / 1_000_000,
+                    submillis: self.submillis_from_nanos(utc.nanosecond),
+                };
+
+                if !self.is_valid_time(&time) {
+                    return Err(TimeError::OutOfRange);
+                }
+
+                Ok(time)
+            },
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use time::*;
+
+
+/// The time code ID of a level 1 CUC time code, using the CCSDS epoch.
+pub const CUC_TIME_CODE_ID_CCSDS_EPOCH: u8 = 0x1;
+
+/// The time code ID of a level 2 CUC time code, using an agency defined epoch.
+pub const CUC_TIME_CODE_ID_AGENCY_EPOCH: u8 = 0x2;
+
+/// The largest number of coarse time octets in a CUC time code without a P-field extension.
+pub const CUC_MAX_COARSE_OCTETS: u8 = 4;
+
+/// The largest number of fine time octets in a CUC time code without a P-field extension.
+pub const CUC_MAX_FINE_OCTETS: u8 = 3;
+
+
+/// The P-field describes the layout of a time code. It is either implicit, meaning
+/// it is known by configuration and not sent with the time, or explicit, meaning
+/// it is sent as a single byte in front of the time code.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PField {
+    /// The P-field is not sent, and the format's configuration is used.
+    Implicit,
+
+    /// The P-field is sent in front of the time code.
+    Explicit,
+}
+
+impl Default for PField {
+    fn default() -> PField {
+        PField::Implicit
+    }
+}
+
+/// A CUC time is a CCSDS Unsegmented Time Code value. It is a count of seconds
+/// since an epoch, plus a fraction of a second.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Hash)]
+pub struct CucTime {
+    /// The number of whole seconds since the epoch.
+    pub coarse: u32,
+
+    /// The fraction of a second in units of 2^-32 seconds. This is independent
+    /// of the number of fine octets, so a one octet fine time of 0x80 is stored
+    /// as 0x80000000.
+    pub fine: u32,
+}
+
+/// A CucFormat describes a CCSDS Unsegmented Time Code (CUC), as defined
+/// in CCSDS 301.0. The number of coarse octets (seconds) and fine octets
+/// (fractions of a second) are configured, along with the epoch.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CucFormat {
+    /// The number of octets of whole seconds, from 1 to 4.
+    pub coarse_octets: u8,
+
+    /// The number of octets of fractional seconds, from 0 to 3.
+    pub fine_octets: u8,
+
+    /// Whether the P-field is sent with the time code.
+    pub pfield: PField,
+
+    /// The epoch of the time code. This is the CCSDS epoch for level 1 time codes, and
+    /// an agency defined epoch for level 2 time codes.
+    pub epoch: Epoch,
+}
+
+impl Default for CucFormat {
+    fn default() -> CucFormat {
+        CucFormat::new(4, 2)
+    }
+}
+
+impl CucFormat {
+    /// Create a CUC format with the given field sizes, an implicit P-field,
+    /// and the CCSDS epoch.
+    pub fn new(coarse_octets: u8, fine_octets: u8) -> CucFormat {
+        CucFormat {
+            coarse_octets,
+            fine_octets,
+            pfield: PField::Implicit,
+            epoch: CCSDS_EPOCH,
+        }
+    }
+
+    /// Create a CUC format with the given field sizes, an implicit P-field,
+    /// and an agency defined epoch.
+    pub fn with_epoch(coarse_octets: u8, fine_octets: u8, epoch: Epoch) -> CucFormat {
+        CucFormat {
+            epoch,
+            .. CucFormat::new(coarse_octets, fine_octets)
+        }
+    }
+
+    /// Check that the field sizes are allowed by the standard.
+    pub fn is_valid(&self) -> bool {
+        self.coarse_octets >= 1 && self.coarse_octets <= CUC_MAX_COARSE_OCTETS &&
+        self.fine_octets <= CUC_MAX_FINE_OCTETS
+    }
+
+    /// The time code ID for this format. Formats using the CCSDS epoch are level 1
+    /// time codes, and any other epoch is a level 2 (agency defined) time code.
+    pub fn time_code_id(&self) -> u8 {
+        if self.epoch == CCSDS_EPOCH {
+            CUC_TIME_CODE_ID_CCSDS_EPOCH
+        } else {
+            CUC_TIME_CODE_ID_AGENCY_EPOCH
+        }
+    }
+
+    /// The P-field byte describing this format. The field sizes are masked to their
+    /// 2 bit fields, so the byte only describes formats for which is_valid is true.
+    pub fn pfield_byte(&self) -> u8 {
+        (self.time_code_id() << 4) |
+        ((self.coarse_octets.wrapping_sub(1) & 0x03) << 2) |
+        (self.fine_octets & 0x03)
+    }
+
+    /// Create a format from a P-field byte. A time code ID of 001 means the CCSDS epoch,
+    /// while an ID of 010 means an agency defined epoch, which is not part of the P-field,
+    /// so the given epoch is used.
+    pub fn from_pfield_byte(pfield: u8, agency_epoch: Epoch) -> Result<CucFormat, TimeError> {
+        let extension = (pfield & 0x80) != 0;
+        let time_code_id = (pfield >> 4) & 0x07;
+
+        let epoch = match time_code_id {
+            CUC_TIME_CODE_ID_CCSDS_EPOCH if !extension => CCSDS_EPOCH,
+            CUC_TIME_CODE_ID_AGENCY_EPOCH if !extension => agency_epoch,
+            _ => return Err(TimeError::InvalidPField(pfield)),
+        };
+
+        Ok(CucFormat {
+            coarse_octets: ((pfield >> 2) & 0x03) + 1,
+            fine_octets: pfield & 0x03,
+            pfield: PField::Explicit,
+            epoch,
+        })
+    }
+
+    /// The number of bytes in the time code fields (the T-field), not including the P-field.
+    pub fn tfield_len(&self) -> usize {
+        (self.coarse_octets + self.fine_octets) as usize
+    }
+
+    /// Read the T-field of a time code with this format's field sizes.
+    fn decode_tfield(&self, bytes: &[u8]) -> Result<CucTime, TimeError> {
+        if !self.is_valid() {
+            return Err(TimeError::InvalidFormat);
+        }
+
+        if bytes.len() < self.tfield_len() {
+            return Err(TimeError::NotEnoughBytes);
+        }
+
+        let coarse_octets = self.coarse_octets as usize;
+
+        let coarse = bytes[0..coarse_octets].iter()
+                                            .fold(0u32, |acc, byte| (acc << 8) | *byte as u32);
+
+        let fine = bytes[coarse_octets..self.tfield_len()].iter()
+                                                          .enumerate()
+                                                          .fold(0u32, |acc, (index, byte)| acc | ((*byte as u32) << (24 - 8 * index)));
+
+        Ok(CucTime { coarse, fine })
+    }
+}
+
+impl TimeCodeFormat for CucFormat {
+    type Time = CucTime;
+
+    fn encoded_len(&self) -> usize {
+        match self.pfield {
+            PField::Implicit => self.tfield_len(),
+            PField::Explicit => self.tfield_len() + 1,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<(CucTime, usize), TimeError> {
+        match self.pfield {
+            PField::Implicit => {
+                let time = self.decode_tfield(bytes)?;
+                Ok((time, self.tfield_len()))
+            },
+
+            PField::Explicit => {
+                if bytes.is_empty() {
+                    return Err(TimeError::NotEnoughBytes);
+                }
+
+                // an explicit P-field describes the time code that follows it
+                let format = CucFormat::from_pfield_byte(bytes[0], self.epoch)?;
+                let time = format.decode_tfield(&bytes[1..])?;
+                Ok((time, format.tfield_len() + 1))
+            },
+        }
+    }
+
+    fn encode(&self, time: &CucTime, bytes: &mut [u8]) -> Result<usize, TimeError> {
+        if !self.is_valid() {
+            return Err(TimeError::InvalidFormat);
+        }
+
+        if bytes.len() < self.encoded_len() {
+            return Err(TimeError::NotEnoughBytes);
+        }
+
+        let coarse_bits = 8 * self.coarse_octets as u32;
+        if coarse_bits < 32 && (time.coarse >> coarse_bits) != 0 {
+            return Err(TimeError::OutOfRange);
+        }
+
+        let mut index = 0;
+        if self.pfield == PField::Explicit {
+            bytes[0] = self.pfield_byte();
+            index += 1;
+        }
+
+        for octet in (0..self.coarse_octets as u32).rev() {
+            bytes[index] = (time.coarse >> (8 * octet)) as u8;
+            index += 1;
+        }
+
+        for octet in 0..self.fine_octets as u32 {
+            bytes[index] = (time.fine >> (24 - 8 * octet)) as u8;
+            index += 1;
+        }
+
+        Ok(index)
+    }
+
+    fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    fn to_duration(&self, time: &CucTime) -> Duration {
+        let nanos = ((time.fine as u64) * 1_000_000_000) >> 32;
+        Duration::new(time.coarse as u64, nanos as u32)
+    }
+
+    fn time_from_duration(&self, duration: Duration) -> Result<CucTime, TimeError> {
+        if !self.is_valid() {
+            return Err(TimeError::InvalidFormat);
+        }
+
+        let coarse_bits = 8 * self.coarse_octets as u32;
+        if coarse_bits < 64 && (duration.as_secs() >> coarse_bits) != 0 {
+            return Err(TimeError::OutOfRange);
+        }
+
+        // round the fraction up so that converting back to a duration gives the
+        // same number of nanoseconds, then drop bits the format does not keep
+        let fine = (((duration.subsec_nanos() as u64) << 32) + 999_999_999) / 1_000_000_000;
+        let fine_mask = if self.fine_octets == 0 { 0 } else { !0u32 << (32 - 8 * self.fine_octets as u32) };
+
+        Ok(CucTime {
+            coarse: duration.as_secs() as u32,
+            fine: (fine as u32) & fine_mask,
+        })
+    }
+}
@@ -0,0 +1,198 @@
+/*!
+The time module provides the CCSDS time codes defined in CCSDS 301.0, Time Code Formats.
+These are commonly carried in the secondary header of a CCSDS packet.
+
+Each time code has a format, which holds the configuration for that time code such
+as its field sizes and epoch, and a time value. Formats implement TimeCodeFormat,
+so code that reads times from packets can be generic over the time code in use.
+*/
+use std::time::Duration;
+
+use primary_header::*;
+
+pub mod cuc;
+pub mod cds;
+pub mod utc;
+pub mod ascii;
+
+pub use self::cuc::*;
+pub use self::cds::*;
+pub use self::utc::*;
+pub use self::ascii::*;
+
+
+/// The number of seconds in a day, not including leap seconds.
+pub const SECONDS_PER_DAY: i64 = 86400;
+
+/// A TimeError describes why a time code could not be read or written.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TimeError {
+    /// There were not enough bytes to read or write the time code.
+    NotEnoughBytes,
+
+    /// The P-field did not describe a time code supported by the format.
+    InvalidPField(u8),
+
+    /// The time could not be represented with the format's field sizes.
+    OutOfRange,
+
+    /// The format's configuration is not valid, such as a field size that
+    /// the standard does not allow.
+    InvalidFormat,
+
+    /// A time was read from a packet which does not have a secondary header.
+    NoSecondaryHeader,
+
+    /// A calendar date or time of day was not valid, such as February 30th
+    /// or a leap second at the end of a day without one.
+    InvalidDate,
+
+    /// An ASCII time code was not in format A or B.
+    InvalidAsciiTime,
+}
+
+/// An Epoch is the start of a time code's time scale. It is stored as a number of
+/// seconds relative to 1970-01-01T00:00:00 on the time code's own time scale,
+/// ignoring leap seconds.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct Epoch(pub i64);
+
+/// The CCSDS epoch, 1958-01-01T00:00:00 TAI. This is the epoch of level 1 time codes.
+pub const CCSDS_EPOCH: Epoch = Epoch(-378_691_200);
+
+/// The Unix epoch, 1970-01-01T00:00:00.
+pub const UNIX_EPOCH: Epoch = Epoch(0);
+
+/// The GPS epoch, 1980-01-06T00:00:00 UTC. GPS time does not include leap seconds,
+/// so like the CCSDS epoch this is on the TAI based scale, where it is 19 seconds
+/// ahead of UTC: 1980-01-06T00:00:19 TAI, not midnight.
+pub const GPS_EPOCH: Epoch = Epoch(315_964_819);
+
+impl Epoch {
+    /// Create an epoch at midnight on the given date, such as a mission's agency
+    /// defined epoch.
+    pub fn from_date(year: i64, month: u32, day: u32) -> Epoch {
+        Epoch(days_from_civil(year, month, day) * SECONDS_PER_DAY)
+    }
+}
+
+impl Default for Epoch {
+    fn default() -> Epoch {
+        CCSDS_EPOCH
+    }
+}
+
+/// A TimeCodeFormat describes how to read and write a particular kind of time code.
+/// The format holds any configuration, such as field sizes and the epoch, and the
+/// Time type holds the fields of a particular time.
+pub trait TimeCodeFormat {
+    /// The time value read and written by this format.
+    type Time: Copy + PartialEq + ::std::fmt::Debug;
+
+    /// The number of bytes written by encode, including the P-field if it is explicit.
+    fn encoded_len(&self) -> usize;
+
+    /// Read a time from the start of a byte slice. The number of bytes consumed
+    /// is returned along with the time, as an explicit P-field may change the
+    /// size of the time code.
+    fn decode(&self, bytes: &[u8]) -> Result<(Self::Time, usize), TimeError>;
+
+    /// Write a time to the start of a byte slice, returning the number of bytes written.
+    fn encode(&self, time: &Self::Time, bytes: &mut [u8]) -> Result<usize, TimeError>;
+
+    /// The epoch that times in this format are measured from.
+    fn epoch(&self) -> Epoch;
+
+    /// Convert a time to the duration since the format's epoch.
+    fn to_duration(&self, time: &Self::Time) -> Duration;
+
+    /// Convert a duration since the format's epoch to a time. Precision beyond the
+    /// resolution of the format is truncated.
+    fn time_from_duration(&self, duration: Duration) -> Result<Self::Time, TimeError>;
+
+    /// The time scale that times in this format are kept in. Most time codes count
+    /// elapsed seconds, so this is TAI unless the format says otherwise.
+    fn time_scale(&self) -> TimeScale {
+        TimeScale::Tai
+    }
+
+    /// Convert a time to a UTC date and time, accounting for leap seconds.
+    fn to_utc(&self, time: &Self::Time) -> UtcDateTime {
+        let duration = self.to_duration(time);
+        let seconds = self.epoch().0 + duration.as_secs() as i64;
+
+        match self.time_scale() {
+            TimeScale::Tai => utc_from_tai(seconds, duration.subsec_nanos()),
+            TimeScale::Utc => UtcDateTime::from_unix_seconds(seconds, duration.subsec_nanos()),
+        }
+    }
+
+    /// Convert a UTC date and time to a time, accounting for leap seconds.
+    fn time_from_utc(&self, utc: &UtcDateTime) -> Result<Self::Time, TimeError> {
+        if !utc.is_valid() {
+            return Err(TimeError::InvalidDate);
+        }
+
+        let seconds = match self.time_scale() {
+            TimeScale::Tai => tai_from_utc(utc),
+            TimeScale::Utc => utc.unix_seconds(),
+        };
+
+        let since_epoch = seconds - self.epoch().0;
+        if since_epoch < 0 {
+            return Err(TimeError::OutOfRange);
+        }
+
+        self.time_from_duration(Duration::new(since_epoch as u64, utc.nanosecond))
+    }
+
+    /// Write a time to a new Vec.
+    fn encode_to_vec(&self, time: &Self::Time) -> Result<Vec<u8>, TimeError> {
+        let mut bytes = vec![0; self.encoded_len()];
+        let len = self.encode(time, &mut bytes)?;
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+
+    /// Read a time from the start of a packet's secondary header. The packet must
+    /// start with a primary header which has the secondary header flag set.
+    fn decode_from_packet(&self, packet: &[u8]) -> Result<Self::Time, TimeError> {
+        let pri_header = PrimaryHeader::from_slice(packet).ok_or(TimeError::NotEnoughBytes)?;
+
+        if pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            return Err(TimeError::NoSecondaryHeader);
+        }
+
+        self.decode(&packet[CCSDS_PRI_HEADER_SIZE_BYTES as usize..]).map(|(time, _)| time)
+    }
+}
+
+
+/// The number of days from 1970-01-01 to the given date in the proleptic
+/// Gregorian calendar.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date, as a year, month and day, that is the given number of days from 1970-01-01
+/// in the proleptic Gregorian calendar.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 { month_index + 3 } else { month_index - 9 }) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
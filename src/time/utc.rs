@@ -0,0 +1,174 @@
+use time::*;
+
+
+/// The dates that each TAI-UTC offset took effect, as a year, a month (UTC leap
+/// seconds are added at the end of June or December), and the offset in seconds.
+///
+/// This table must be extended when a new leap second is announced by the IERS.
+const LEAP_SECONDS: [(i64, u32, i64); 28] = [
+    (1972, 1, 10), (1972, 7, 11), (1973, 1, 12), (1974, 1, 13),
+    (1975, 1, 14), (1976, 1, 15), (1977, 1, 16), (1978, 1, 17),
+    (1979, 1, 18), (1980, 1, 19), (1981, 7, 20), (1982, 7, 21),
+    (1983, 7, 22), (1985, 7, 23), (1988, 1, 24), (1990, 1, 25),
+    (1991, 1, 26), (1992, 7, 27), (1993, 7, 28), (1994, 7, 29),
+    (1996, 1, 30), (1997, 7, 31), (1999, 1, 32), (2006, 1, 33),
+    (2009, 1, 34), (2012, 7, 35), (2015, 7, 36), (2017, 1, 37),
+];
+
+/// The TAI-UTC offset before the first entry in the leap second table. Before 1972,
+/// UTC was not offset from TAI by whole seconds, so this is an approximation.
+const INITIAL_TAI_MINUS_UTC: i64 = 10;
+
+
+/// The TimeScale of a time code determines whether its seconds include leap seconds.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TimeScale {
+    /// International Atomic Time. Times count every elapsed second, so converting
+    /// to UTC requires the leap second table. The CCSDS epoch is defined in TAI.
+    Tai,
+
+    /// Coordinated Universal Time. Times count days of 86400 seconds, except that
+    /// a day ending in a leap second may have a time of day of 86400 seconds or more.
+    Utc,
+}
+
+impl Default for TimeScale {
+    fn default() -> TimeScale {
+        TimeScale::Tai
+    }
+}
+
+/// A UtcDateTime is a calendar date and time of day in UTC. The second field
+/// is 60 during a leap second.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Hash)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+}
+
+impl UtcDateTime {
+    /// Create a UtcDateTime from a date and time of day, with no fractional seconds.
+    pub fn new(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> UtcDateTime {
+        UtcDateTime { year, month, day, hour, minute, second, nanosecond: 0 }
+    }
+
+    /// Create a UtcDateTime from a number of seconds since the Unix epoch, not
+    /// counting leap seconds. This can never produce a leap second.
+    pub fn from_unix_seconds(seconds: i64, nanosecond: u32) -> UtcDateTime {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(days);
+
+        UtcDateTime {
+            year,
+            month,
+            day,
+            hour: second_of_day / 3600,
+            minute: (second_of_day / 60) % 60,
+            second: second_of_day % 60,
+            nanosecond,
+        }
+    }
+
+    /// The number of days since the Unix epoch to the date of this time.
+    pub fn days_since_unix_epoch(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    /// The number of seconds since the Unix epoch, not counting leap seconds.
+    /// A leap second has the same value as the first second of the next day.
+    pub fn unix_seconds(&self) -> i64 {
+        self.days_since_unix_epoch() * SECONDS_PER_DAY +
+            (self.hour * 3600 + self.minute * 60 + self.second) as i64
+    }
+
+    /// The day of the year, starting at 1 for January 1st.
+    pub fn day_of_year(&self) -> u32 {
+        (self.days_since_unix_epoch() - days_from_civil(self.year, 1, 1)) as u32 + 1
+    }
+
+    /// Create a UtcDateTime from a year, a day of the year starting at 1, and a time of day.
+    pub fn from_day_of_year(year: i64, day_of_year: u32, hour: u32, minute: u32, second: u32) -> UtcDateTime {
+        let days = days_from_civil(year, 1, 1) + day_of_year as i64 - 1;
+        let (year, month, day) = civil_from_days(days);
+        UtcDateTime::new(year, month, day, hour, minute, second)
+    }
+
+    /// Check that the fields of this time are in range. A second of 60 is only
+    /// valid at the end of a day with a leap second.
+    pub fn is_valid(&self) -> bool {
+        let valid_date =
+            self.month >= 1 && self.month <= 12 && self.day >= 1 &&
+            civil_from_days(self.days_since_unix_epoch()) == (self.year, self.month, self.day);
+
+        let valid_time =
+            self.hour < 24 && self.minute < 60 && self.nanosecond < 1_000_000_000 &&
+            (self.second < 60 ||
+             (self.second == 60 && self.hour == 23 && self.minute == 59 &&
+              is_leap_second_day(self.days_since_unix_epoch())));
+
+        valid_date && valid_time
+    }
+}
+
+/// The TAI-UTC offset in seconds at a time given in seconds since the Unix epoch,
+/// not counting leap seconds.
+pub fn tai_minus_utc(unix_seconds: i64) -> i64 {
+    LEAP_SECONDS.iter()
+                .rev()
+                .find(|&&(year, month, _)| unix_seconds >= leap_second_start(year, month))
+                .map(|&(_, _, offset)| offset)
+                .unwrap_or(INITIAL_TAI_MINUS_UTC)
+}
+
+/// Check whether the day, given in days since the Unix epoch, ends in a leap second.
+pub fn is_leap_second_day(days: i64) -> bool {
+    let end_of_day = (days + 1) * SECONDS_PER_DAY;
+    LEAP_SECONDS.iter()
+                .skip(1)
+                .any(|&(year, month, _)| leap_second_start(year, month) == end_of_day)
+}
+
+/// Convert a TAI time, given as seconds since the Unix epoch on the TAI time scale,
+/// to UTC. A TAI time during a leap second gives a UTC second of 60.
+pub fn utc_from_tai(tai_seconds: i64, nanosecond: u32) -> UtcDateTime {
+    for (index, &(year, month, offset)) in LEAP_SECONDS.iter().enumerate().rev() {
+        let start = leap_second_start(year, month);
+
+        if tai_seconds - offset >= start {
+            return UtcDateTime::from_unix_seconds(tai_seconds - offset, nanosecond);
+        }
+
+        // a TAI time that is after this offset's start using the previous offset
+        // falls in the leap second inserted just before the start
+        let previous_offset = if index == 0 { INITIAL_TAI_MINUS_UTC } else { LEAP_SECONDS[index - 1].2 };
+        if tai_seconds - previous_offset >= start {
+            let mut utc = UtcDateTime::from_unix_seconds(start - 1, nanosecond);
+            utc.second = 60;
+            return utc;
+        }
+    }
+
+    UtcDateTime::from_unix_seconds(tai_seconds - INITIAL_TAI_MINUS_UTC, nanosecond)
+}
+
+/// Convert a UTC time to seconds since the Unix epoch on the TAI time scale.
+pub fn tai_from_utc(utc: &UtcDateTime) -> i64 {
+    let unix_seconds = utc.unix_seconds();
+
+    if utc.second == 60 {
+        // the leap second takes the offset in effect before it was inserted
+        unix_seconds + tai_minus_utc(unix_seconds - 1)
+    } else {
+        unix_seconds + tai_minus_utc(unix_seconds)
+    }
+}
+
+fn leap_second_start(year: i64, month: u32) -> i64 {
+    days_from_civil(year, month, 1) * SECONDS_PER_DAY
+}
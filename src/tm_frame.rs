@@ -0,0 +1,425 @@
+/*!
+The tm_frame module reads TM Transfer Frames, as defined in CCSDS 132.0, TM Space Data
+Link Protocol, and extracts the space packets they carry.
+
+TM frames have a fixed length for a mission, and are sent on a physical channel
+shared by up to 8 virtual channels. Packets are carried in the data fields of the
+frames of a virtual channel, and may span several frames.
+*/
+use std::collections::{BTreeMap, VecDeque};
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+
+use clcw::*;
+use frame::*;
+
+
+/// The size of the TM Transfer Frame primary header.
+pub const TM_PRI_HEADER_SIZE_BYTES: usize = 6;
+
+/// The transfer frame version number of TM frames.
+pub const TM_FRAME_VERSION: u8 = 0;
+
+/// The size of the Operational Control Field.
+pub const TM_OCF_SIZE_BYTES: usize = 4;
+
+/// The first header pointer of a frame in which no packet starts.
+pub const TM_FHP_NO_PACKET_START: u16 = 0x07FF;
+
+/// The first header pointer of a frame carrying only idle data.
+pub const TM_FHP_IDLE_DATA: u16 = 0x07FE;
+
+/// The number of values of the master and virtual channel frame counts.
+const TM_FRAME_COUNT_MODULUS: u16 = 0x100;
+
+
+/// The primary header of a TM Transfer Frame. Like the PrimaryHeader of a packet,
+/// it is kept as its raw bytes, with accessors for each field.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TmFrameHeader(pub [u8; 6]);
+
+impl TmFrameHeader {
+    /// Create a TmFrameHeader from a slice. If the slice is not long enough then
+    /// None is returned.
+    pub fn from_slice(bytes: &[u8]) -> Option<TmFrameHeader> {
+        if bytes.len() < TM_PRI_HEADER_SIZE_BYTES {
+            return None;
+        }
+
+        let mut header = TmFrameHeader::default();
+        header.0.copy_from_slice(&bytes[0..TM_PRI_HEADER_SIZE_BYTES]);
+        Some(header)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[0] >> 6
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.0[0] = (self.0[0] & 0x3F) | ((version & 0x03) << 6);
+    }
+
+    pub fn spacecraft_id(&self) -> u16 {
+        (BigEndian::read_u16(&self.0[0..2]) >> 4) & 0x03FF
+    }
+
+    pub fn set_spacecraft_id(&mut self, spacecraft_id: u16) {
+        let word = (BigEndian::read_u16(&self.0[0..2]) & 0xC00F) | ((spacecraft_id & 0x03FF) << 4);
+        BigEndian::write_u16(&mut self.0[0..2], word);
+    }
+
+    pub fn vcid(&self) -> u8 {
+        (self.0[1] >> 1) & 0x07
+    }
+
+    pub fn set_vcid(&mut self, vcid: u8) {
+        self.0[1] = (self.0[1] & 0xF1) | ((vcid & 0x07) << 1);
+    }
+
+    /// Whether the frame ends with an Operational Control Field.
+    pub fn ocf_flag(&self) -> bool {
+        (self.0[1] & 0x01) != 0
+    }
+
+    pub fn set_ocf_flag(&mut self, ocf_flag: bool) {
+        self.0[1] = (self.0[1] & 0xFE) | (ocf_flag as u8);
+    }
+
+    pub fn master_channel_frame_count(&self) -> u8 {
+        self.0[2]
+    }
+
+    pub fn set_master_channel_frame_count(&mut self, count: u8) {
+        self.0[2] = count;
+    }
+
+    pub fn virtual_channel_frame_count(&self) -> u8 {
+        self.0[3]
+    }
+
+    pub fn set_virtual_channel_frame_count(&mut self, count: u8) {
+        self.0[3] = count;
+    }
+
+    /// Whether the frame has a secondary header.
+    pub fn secondary_header_flag(&self) -> bool {
+        (self.0[4] & 0x80) != 0
+    }
+
+    pub fn set_secondary_header_flag(&mut self, flag: bool) {
+        self.0[4] = (self.0[4] & 0x7F) | ((flag as u8) << 7);
+    }
+
+    /// Whether the data field is not synchronized with packets. When set, the data
+    /// field holds a virtual channel access service data unit rather than packets,
+    /// and the first header pointer is undefined.
+    pub fn sync_flag(&self) -> bool {
+        (self.0[4] & 0x40) != 0
+    }
+
+    pub fn set_sync_flag(&mut self, flag: bool) {
+        self.0[4] = (self.0[4] & 0xBF) | ((flag as u8) << 6);
+    }
+
+    pub fn packet_order_flag(&self) -> bool {
+        (self.0[4] & 0x20) != 0
+    }
+
+    pub fn segment_length_id(&self) -> u8 {
+        (self.0[4] >> 3) & 0x03
+    }
+
+    pub fn first_header_pointer(&self) -> u16 {
+        BigEndian::read_u16(&self.0[4..6]) & 0x07FF
+    }
+
+    pub fn set_first_header_pointer(&mut self, pointer: u16) {
+        let word = (BigEndian::read_u16(&self.0[4..6]) & 0xF800) | (pointer & 0x07FF);
+        BigEndian::write_u16(&mut self.0[4..6], word);
+    }
+}
+
+
+/// A TmFrameConfig gives the managed parameters of a TM physical channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TmFrameConfig {
+    /// The length of every frame on the channel, in bytes.
+    pub frame_length: usize,
+
+    /// Whether frames end with a Frame Error Control Field.
+    pub has_fecf: bool,
+
+    /// The spacecraft whose frames are accepted, or None to accept any spacecraft.
+    pub spacecraft_id: Option<u16>,
+}
+
+impl TmFrameConfig {
+    /// Create a configuration for frames of the given length with a Frame Error
+    /// Control Field, accepting frames from any spacecraft.
+    pub fn new(frame_length: usize) -> TmFrameConfig {
+        TmFrameConfig {
+            frame_length,
+            has_fecf: true,
+            spacecraft_id: None,
+        }
+    }
+}
+
+
+/// A TmFrame is a view of a TM Transfer Frame in a byte slice, checked against
+/// the channel's configuration.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TmFrame<'a> {
+    bytes: &'a [u8],
+    has_fecf: bool,
+}
+
+impl<'a> TmFrame<'a> {
+    /// Read a frame, checking its length, version, spacecraft ID and FECF.
+    pub fn parse(config: &TmFrameConfig, bytes: &'a [u8]) -> Result<TmFrame<'a>, FrameError> {
+        if bytes.len() != config.frame_length {
+            return Err(FrameError::WrongLength { expected: config.frame_length, actual: bytes.len() });
+        }
+
+        let header = TmFrameHeader::from_slice(bytes).ok_or(FrameError::NotEnoughBytes)?;
+
+        if header.version() != TM_FRAME_VERSION {
+            return Err(FrameError::InvalidVersion(header.version()));
+        }
+
+        if let Some(spacecraft_id) = config.spacecraft_id {
+            if header.spacecraft_id() != spacecraft_id {
+                return Err(FrameError::WrongSpacecraft(header.spacecraft_id()));
+            }
+        }
+
+        if config.has_fecf && !fecf_is_valid(bytes) {
+            return Err(FrameError::InvalidFecf);
+        }
+
+        let frame = TmFrame { bytes, has_fecf: config.has_fecf };
+
+        if frame.data_field_start() > frame.data_field_end() {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        if !header.sync_flag() {
+            let pointer = header.first_header_pointer();
+            if pointer != TM_FHP_NO_PACKET_START && pointer != TM_FHP_IDLE_DATA &&
+               pointer as usize >= frame.data_field().len() {
+                return Err(FrameError::InvalidHeader);
+            }
+        }
+
+        Ok(frame)
+    }
+
+    /// The frame's primary header.
+    pub fn header(&self) -> TmFrameHeader {
+        TmFrameHeader::from_slice(self.bytes).unwrap()
+    }
+
+    /// The bytes of the whole frame.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The data of the frame's secondary header, not including its identification byte,
+    /// if the frame has a secondary header.
+    pub fn secondary_header(&self) -> Option<&'a [u8]> {
+        if !self.header().secondary_header_flag() {
+            return None;
+        }
+
+        Some(&self.bytes[TM_PRI_HEADER_SIZE_BYTES + 1..self.data_field_start()])
+    }
+
+    /// The version number of the frame's secondary header, if it has one.
+    pub fn secondary_header_version(&self) -> Option<u8> {
+        if !self.header().secondary_header_flag() {
+            return None;
+        }
+
+        Some(self.bytes[TM_PRI_HEADER_SIZE_BYTES] >> 6)
+    }
+
+    /// The frame's data field.
+    pub fn data_field(&self) -> &'a [u8] {
+        &self.bytes[self.data_field_start()..self.data_field_end()]
+    }
+
+    /// The Operational Control Field, if the frame has one. This usually holds a
+    /// Communications Link Control Word reporting the state of the command link.
+    pub fn ocf(&self) -> Option<u32> {
+        if !self.header().ocf_flag() {
+            return None;
+        }
+
+        let start = self.data_field_end();
+        Some(BigEndian::read_u32(&self.bytes[start..start + TM_OCF_SIZE_BYTES]))
+    }
+
+    /// The CLCW in the frame's Operational Control Field, if it holds one.
+    pub fn clcw(&self) -> Option<Clcw> {
+        self.ocf().map(Clcw::from).filter(|clcw| clcw.control_word_type() == 0)
+    }
+
+    /// The Frame Error Control Field, if the channel uses one.
+    pub fn fecf(&self) -> Option<u16> {
+        if !self.has_fecf {
+            return None;
+        }
+
+        Some(BigEndian::read_u16(&self.bytes[self.bytes.len() - FECF_SIZE_BYTES..]))
+    }
+
+    /// The offset in the data field of the first packet header, or None if no packet
+    /// starts in the frame, or the frame does not carry packets.
+    pub fn first_packet_offset(&self) -> Option<usize> {
+        let header = self.header();
+        let pointer = header.first_header_pointer();
+
+        if header.sync_flag() || pointer == TM_FHP_NO_PACKET_START || pointer == TM_FHP_IDLE_DATA {
+            None
+        } else {
+            Some(pointer as usize)
+        }
+    }
+
+    /// Whether the frame's data field holds only idle data.
+    pub fn is_idle(&self) -> bool {
+        let header = self.header();
+        !header.sync_flag() && header.first_header_pointer() == TM_FHP_IDLE_DATA
+    }
+
+    fn data_field_start(&self) -> usize {
+        if self.header().secondary_header_flag() {
+            let length = (self.bytes.get(TM_PRI_HEADER_SIZE_BYTES).cloned().unwrap_or(0) & 0x3F) as usize;
+            TM_PRI_HEADER_SIZE_BYTES + length + 1
+        } else {
+            TM_PRI_HEADER_SIZE_BYTES
+        }
+    }
+
+    fn data_field_end(&self) -> usize {
+        let mut end = self.bytes.len();
+        if self.has_fecf {
+            end -= FECF_SIZE_BYTES;
+        }
+
+        if self.header().ocf_flag() {
+            end -= TM_OCF_SIZE_BYTES;
+        }
+
+        end
+    }
+}
+
+
+/// The frame counts and packet extractor of a virtual channel.
+#[derive(Debug, Clone, Default)]
+struct VirtualChannel {
+    next_frame_count: Option<u8>,
+    extractor: PacketExtractor,
+}
+
+/// A TmFrameDecoder reads the TM frames of a physical channel and extracts the
+/// packets carried on each virtual channel. A gap in a virtual channel's frame count
+/// means frames were lost, so any partial packet on that channel is dropped.
+#[derive(Debug, Clone)]
+pub struct TmFrameDecoder {
+    /// The configuration of the physical channel.
+    pub config: TmFrameConfig,
+
+    /// The number of frames which could not be read, such as frames with a bad FECF.
+    pub invalid_frames: usize,
+
+    /// The number of frames missing from the master channel frame count.
+    pub lost_frames: usize,
+
+    /// The number of gaps in virtual channel frame counts.
+    pub virtual_channel_gaps: usize,
+
+    next_master_frame_count: Option<u8>,
+
+    channels: BTreeMap<u8, VirtualChannel>,
+
+    packets: VecDeque<(u8, BytesMut)>,
+}
+
+impl TmFrameDecoder {
+    /// Create a decoder for a physical channel.
+    pub fn new(config: TmFrameConfig) -> TmFrameDecoder {
+        TmFrameDecoder {
+            config,
+            invalid_frames: 0,
+            lost_frames: 0,
+            virtual_channel_gaps: 0,
+            next_master_frame_count: None,
+            channels: BTreeMap::new(),
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Process a received frame, extracting any packets it completes. Frames that
+    /// can not be read are counted and their error returned.
+    pub fn recv_frame(&mut self, bytes: &[u8]) -> Result<TmFrameHeader, FrameError> {
+        let frame = match TmFrame::parse(&self.config, bytes) {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.invalid_frames += 1;
+                return Err(err);
+            },
+        };
+
+        let header = frame.header();
+
+        let master_count = header.master_channel_frame_count();
+        if let Some(expected) = self.next_master_frame_count {
+            self.lost_frames += count_gap(expected, master_count);
+        }
+        self.next_master_frame_count = Some(master_count.wrapping_add(1));
+
+        let vcid = header.vcid();
+        let channel = self.channels.entry(vcid).or_default();
+
+        let vc_count = header.virtual_channel_frame_count();
+        if let Some(expected) = channel.next_frame_count {
+            if expected != vc_count {
+                self.virtual_channel_gaps += 1;
+                channel.extractor.reset();
+            }
+        }
+        channel.next_frame_count = Some(vc_count.wrapping_add(1));
+
+        if !header.sync_flag() && !frame.is_idle() {
+            channel.extractor.recv_data(frame.data_field(), frame.first_packet_offset());
+            while let Some(packet) = channel.extractor.pull_packet() {
+                self.packets.push_back((vcid, packet));
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Take the next extracted packet.
+    pub fn pull_packet(&mut self) -> Option<BytesMut> {
+        self.packets.pop_front().map(|(_, packet)| packet)
+    }
+
+    /// Take the next extracted packet, along with the virtual channel it was carried on.
+    pub fn pull_packet_with_vcid(&mut self) -> Option<(u8, BytesMut)> {
+        self.packets.pop_front()
+    }
+
+    /// The packet extractor of a virtual channel, if any frames have been received on it.
+    pub fn extractor(&self, vcid: u8) -> Option<&PacketExtractor> {
+        self.channels.get(&vcid).map(|channel| &channel.extractor)
+    }
+}
+
+/// The number of frame counts skipped between the expected and received counts.
+fn count_gap(expected: u8, received: u8) -> usize {
+    ((received as u16 + TM_FRAME_COUNT_MODULUS - expected as u16) % TM_FRAME_COUNT_MODULUS) as usize
+}
@@ -0,0 +1,842 @@
+/*!
+The uslp_frame module reads and writes USLP Transfer Frames, as defined in CCSDS 732.1,
+Unified Space Data Link Protocol, and extracts the space packets they carry.
+
+USLP frames may have a fixed length, or a variable length of up to 65536 bytes given
+in the frame's primary header. Frames on a channel with fixed length frames may use a
+truncated primary header of 4 bytes, leaving out the frame length and frame count.
+
+Each frame's data field starts with a Transfer Frame Data Field (TFDF) header, whose
+construction rule tells how the data zone following it is organized. Packets may
+span fixed length frames, located by the First Header Pointer, or be carried whole or
+in segments in variable length frames. Packets are reassembled separately for each
+virtual channel and MAP ID.
+*/
+use std::collections::{BTreeMap, VecDeque};
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+
+use clcw::*;
+use frame::*;
+
+
+/// The transfer frame version number of USLP frames.
+pub const USLP_FRAME_VERSION: u8 = 0x0C;
+
+/// The size of a truncated primary header.
+pub const USLP_TRUNCATED_HEADER_SIZE_BYTES: usize = 4;
+
+/// The size of a primary header with no virtual channel frame count.
+pub const USLP_MIN_PRI_HEADER_SIZE_BYTES: usize = 7;
+
+/// The largest size of the virtual channel frame count.
+pub const USLP_MAX_FRAME_COUNT_BYTES: u8 = 7;
+
+/// The largest USLP frame allowed by the standard.
+pub const USLP_MAX_FRAME_LENGTH: usize = 65536;
+
+/// The largest virtual channel ID.
+pub const USLP_MAX_VCID: u8 = 0x3F;
+
+/// The virtual channel ID of idle frames, carrying only idle data.
+pub const USLP_IDLE_VCID: u8 = 0x3F;
+
+/// The largest MAP ID.
+pub const USLP_MAX_MAP_ID: u8 = 0x0F;
+
+/// The size of the Operational Control Field.
+pub const USLP_OCF_SIZE_BYTES: usize = 4;
+
+/// The USLP Protocol ID of a data zone carrying space packets.
+pub const USLP_UPID_SPACE_PACKETS: u8 = 0x00;
+
+/// The USLP Protocol ID of a data zone carrying only idle data.
+pub const USLP_UPID_IDLE: u8 = 0x1F;
+
+/// The first header pointer of a data zone in which no packet starts. This is also
+/// the last valid octet pointer of a data zone which is completely valid.
+pub const USLP_FHP_NO_PACKET_START: u16 = 0xFFFF;
+
+
+/// The construction rule of a TFDF, telling how the data zone is organized.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ConstructionRule {
+    /// Packets spanning fixed length data zones, located by the first header pointer.
+    PacketSpanning,
+
+    /// The start of a MAP or VC access service data unit in a fixed length data zone,
+    /// with a last valid octet pointer.
+    MapaStart,
+
+    /// The continuation of a MAP or VC access service data unit in a fixed length data
+    /// zone, with a last valid octet pointer.
+    MapaContinuation,
+
+    /// An octet stream in a variable length data zone.
+    OctetStream,
+
+    /// The first segment of a packet in a variable length data zone.
+    StartingSegment,
+
+    /// A middle segment of a packet in a variable length data zone.
+    ContinuingSegment,
+
+    /// The last segment of a packet in a variable length data zone.
+    LastSegment,
+
+    /// Complete packets in a variable length data zone.
+    NoSegmentation,
+}
+
+impl ConstructionRule {
+    /// Whether the TFDF header holds a first header or last valid octet pointer.
+    /// These rules are used with fixed length data zones.
+    pub fn has_pointer(&self) -> bool {
+        u8::from(*self) <= 2
+    }
+}
+
+impl From<u8> for ConstructionRule {
+    fn from(byte: u8) -> ConstructionRule {
+        match byte & 0x07 {
+            0 => ConstructionRule::PacketSpanning,
+            1 => ConstructionRule::MapaStart,
+            2 => ConstructionRule::MapaContinuation,
+            3 => ConstructionRule::OctetStream,
+            4 => ConstructionRule::StartingSegment,
+            5 => ConstructionRule::ContinuingSegment,
+            6 => ConstructionRule::LastSegment,
+            _ => ConstructionRule::NoSegmentation,
+        }
+    }
+}
+
+impl From<ConstructionRule> for u8 {
+    fn from(rule: ConstructionRule) -> u8 {
+        match rule {
+            ConstructionRule::PacketSpanning => 0,
+            ConstructionRule::MapaStart => 1,
+            ConstructionRule::MapaContinuation => 2,
+            ConstructionRule::OctetStream => 3,
+            ConstructionRule::StartingSegment => 4,
+            ConstructionRule::ContinuingSegment => 5,
+            ConstructionRule::LastSegment => 6,
+            ConstructionRule::NoSegmentation => 7,
+        }
+    }
+}
+
+
+/// The primary header of a USLP Transfer Frame. Unlike the headers of other frames,
+/// its length depends on its contents, so it is decoded into its fields.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UslpFrameHeader {
+    pub version: u8,
+
+    pub spacecraft_id: u16,
+
+    /// Whether the spacecraft ID is the destination of the frame rather than its source.
+    pub destination: bool,
+
+    pub vcid: u8,
+
+    pub map_id: u8,
+
+    /// Whether the header is truncated, ending after the MAP ID.
+    pub truncated: bool,
+
+    /// The length of the whole frame in bytes. A truncated header has no frame length
+    /// field, so when one is decoded this is the length of the bytes it was decoded from.
+    pub frame_length: usize,
+
+    /// Whether the frame is expedited, rather than sequence controlled.
+    pub bypass: bool,
+
+    /// Whether the data field holds protocol control commands rather than user data.
+    pub protocol_control_command: bool,
+
+    /// Whether the frame has an Operational Control Field.
+    pub ocf_flag: bool,
+
+    /// The size of the virtual channel frame count, from 0 to 7 bytes.
+    pub frame_count_length: u8,
+
+    pub frame_count: u64,
+}
+
+impl UslpFrameHeader {
+    /// Create a header for a sequence controlled frame of a virtual channel and MAP,
+    /// with no frame count. The frame length is filled in when the frame is encoded.
+    pub fn new(spacecraft_id: u16, vcid: u8, map_id: u8) -> UslpFrameHeader {
+        UslpFrameHeader {
+            version: USLP_FRAME_VERSION,
+            spacecraft_id,
+            destination: false,
+            vcid,
+            map_id,
+            truncated: false,
+            frame_length: 0,
+            bypass: false,
+            protocol_control_command: false,
+            ocf_flag: false,
+            frame_count_length: 0,
+            frame_count: 0,
+        }
+    }
+
+    /// The size of the encoded header.
+    pub fn size(&self) -> usize {
+        if self.truncated {
+            USLP_TRUNCATED_HEADER_SIZE_BYTES
+        } else {
+            USLP_MIN_PRI_HEADER_SIZE_BYTES + self.frame_count_length as usize
+        }
+    }
+
+    /// Decode a header from the start of a frame.
+    pub fn decode(bytes: &[u8]) -> Result<UslpFrameHeader, FrameError> {
+        if bytes.len() < USLP_TRUNCATED_HEADER_SIZE_BYTES {
+            return Err(FrameError::NotEnoughBytes);
+        }
+
+        let word = BigEndian::read_u32(bytes);
+        let mut header = UslpFrameHeader {
+            version: (word >> 28) as u8,
+            spacecraft_id: (word >> 12) as u16,
+            destination: (word & 0x0800) != 0,
+            vcid: ((word >> 5) & 0x3F) as u8,
+            map_id: ((word >> 1) & 0x0F) as u8,
+            truncated: (word & 0x01) != 0,
+            frame_length: bytes.len(),
+            bypass: false,
+            protocol_control_command: false,
+            ocf_flag: false,
+            frame_count_length: 0,
+            frame_count: 0,
+        };
+
+        if header.truncated {
+            return Ok(header);
+        }
+
+        if bytes.len() < USLP_MIN_PRI_HEADER_SIZE_BYTES {
+            return Err(FrameError::NotEnoughBytes);
+        }
+
+        header.frame_length = BigEndian::read_u16(&bytes[4..6]) as usize + 1;
+        header.bypass = (bytes[6] & 0x80) != 0;
+        header.protocol_control_command = (bytes[6] & 0x40) != 0;
+        header.ocf_flag = (bytes[6] & 0x08) != 0;
+        header.frame_count_length = bytes[6] & 0x07;
+
+        if bytes.len() < header.size() {
+            return Err(FrameError::NotEnoughBytes);
+        }
+
+        if header.frame_count_length > 0 {
+            header.frame_count = BigEndian::read_uint(&bytes[USLP_MIN_PRI_HEADER_SIZE_BYTES..header.size()],
+                                                      header.frame_count_length as usize);
+        }
+
+        Ok(header)
+    }
+
+    /// Encode the header. Fields which do not fit in the header are an InvalidHeader
+    /// error, as is a frame length outside of the lengths allowed.
+    pub fn encode(&self) -> Result<Vec<u8>, FrameError> {
+        if self.version > 0x0F || self.vcid > USLP_MAX_VCID || self.map_id > USLP_MAX_MAP_ID ||
+           self.frame_count_length > USLP_MAX_FRAME_COUNT_BYTES ||
+           self.frame_count > max_frame_count(self.frame_count_length) {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        let word = ((self.version as u32) << 28) |
+                   ((self.spacecraft_id as u32) << 12) |
+                   ((self.destination as u32) << 11) |
+                   ((self.vcid as u32) << 5) |
+                   ((self.map_id as u32) << 1) |
+                   (self.truncated as u32);
+
+        let mut bytes = vec![0; self.size()];
+        BigEndian::write_u32(&mut bytes, word);
+
+        if self.truncated {
+            return Ok(bytes);
+        }
+
+        if self.frame_length == 0 || self.frame_length > USLP_MAX_FRAME_LENGTH {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        BigEndian::write_u16(&mut bytes[4..6], (self.frame_length - 1) as u16);
+        bytes[6] = ((self.bypass as u8) << 7) |
+                   ((self.protocol_control_command as u8) << 6) |
+                   ((self.ocf_flag as u8) << 3) |
+                   self.frame_count_length;
+
+        if self.frame_count_length > 0 {
+            BigEndian::write_uint(&mut bytes[USLP_MIN_PRI_HEADER_SIZE_BYTES..],
+                                  self.frame_count,
+                                  self.frame_count_length as usize);
+        }
+
+        Ok(bytes)
+    }
+}
+
+
+/// The Transfer Frame Data Field header, at the start of the data field of every frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TfdfHeader {
+    pub construction_rule: ConstructionRule,
+
+    /// The USLP Protocol ID, giving what the data zone carries.
+    pub upid: u8,
+
+    /// The first header pointer or last valid octet pointer, for construction rules
+    /// which have one.
+    pub pointer: Option<u16>,
+}
+
+impl TfdfHeader {
+    /// Create a header for complete space packets in a variable length data zone.
+    pub fn new() -> TfdfHeader {
+        TfdfHeader {
+            construction_rule: ConstructionRule::NoSegmentation,
+            upid: USLP_UPID_SPACE_PACKETS,
+            pointer: None,
+        }
+    }
+
+    /// Create a header for space packets spanning fixed length data zones, with the
+    /// offset of the first packet header in the data zone, if any.
+    pub fn packet_spanning(first_header_pointer: Option<u16>) -> TfdfHeader {
+        TfdfHeader {
+            construction_rule: ConstructionRule::PacketSpanning,
+            upid: USLP_UPID_SPACE_PACKETS,
+            pointer: Some(first_header_pointer.unwrap_or(USLP_FHP_NO_PACKET_START)),
+        }
+    }
+
+    /// The size of the encoded header.
+    pub fn size(&self) -> usize {
+        if self.construction_rule.has_pointer() { 3 } else { 1 }
+    }
+
+    /// Decode a header from the start of a frame's data field.
+    pub fn decode(bytes: &[u8]) -> Result<TfdfHeader, FrameError> {
+        let first = *bytes.first().ok_or(FrameError::NotEnoughBytes)?;
+
+        let mut header = TfdfHeader {
+            construction_rule: ConstructionRule::from(first >> 5),
+            upid: first & 0x1F,
+            pointer: None,
+        };
+
+        if header.construction_rule.has_pointer() {
+            if bytes.len() < 3 {
+                return Err(FrameError::NotEnoughBytes);
+            }
+
+            header.pointer = Some(BigEndian::read_u16(&bytes[1..3]));
+        }
+
+        Ok(header)
+    }
+
+    /// Encode the header. A construction rule which needs a pointer defaults to
+    /// USLP_FHP_NO_PACKET_START if none is given.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![(u8::from(self.construction_rule) << 5) | (self.upid & 0x1F)];
+
+        if self.construction_rule.has_pointer() {
+            let pointer = self.pointer.unwrap_or(USLP_FHP_NO_PACKET_START);
+            bytes.push((pointer >> 8) as u8);
+            bytes.push(pointer as u8);
+        }
+
+        bytes
+    }
+}
+
+impl Default for TfdfHeader {
+    fn default() -> TfdfHeader {
+        TfdfHeader::new()
+    }
+}
+
+
+/// A UslpFrameConfig gives the managed parameters of a USLP physical channel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UslpFrameConfig {
+    /// The length of every frame on the channel, or None if frames have a variable length.
+    pub frame_length: Option<usize>,
+
+    /// The length of frames with a truncated header, or None if they are not used.
+    pub truncated_frame_length: Option<usize>,
+
+    /// The length of the insert zone following the primary header of frames which are
+    /// not truncated, or 0 if there is none.
+    pub insert_zone_length: usize,
+
+    /// Whether frames end with a Frame Error Control Field.
+    pub has_fecf: bool,
+
+    /// The spacecraft whose frames are accepted, or None to accept any spacecraft.
+    pub spacecraft_id: Option<u16>,
+
+    /// The largest frame to build when splitting a packet into segments.
+    pub max_frame_length: usize,
+}
+
+impl UslpFrameConfig {
+    /// Create a configuration for variable length frames with a Frame Error Control Field,
+    /// accepting frames from any spacecraft.
+    pub fn new() -> UslpFrameConfig {
+        UslpFrameConfig {
+            frame_length: None,
+            truncated_frame_length: None,
+            insert_zone_length: 0,
+            has_fecf: true,
+            spacecraft_id: None,
+            max_frame_length: USLP_MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Create a configuration for frames of a fixed length.
+    pub fn fixed(frame_length: usize) -> UslpFrameConfig {
+        UslpFrameConfig {
+            frame_length: Some(frame_length),
+            max_frame_length: frame_length,
+            ..UslpFrameConfig::new()
+        }
+    }
+}
+
+impl Default for UslpFrameConfig {
+    fn default() -> UslpFrameConfig {
+        UslpFrameConfig::new()
+    }
+}
+
+
+/// A UslpFrame is a view of a USLP Transfer Frame in a byte slice, checked against
+/// the channel's configuration.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UslpFrame<'a> {
+    bytes: &'a [u8],
+    header: UslpFrameHeader,
+    tfdf_header: TfdfHeader,
+    has_fecf: bool,
+    insert_zone_length: usize,
+    data_field_start: usize,
+    data_field_end: usize,
+}
+
+impl<'a> UslpFrame<'a> {
+    /// Read a frame, checking its length, version, spacecraft ID, FECF and TFDF header.
+    pub fn parse(config: &UslpFrameConfig, bytes: &'a [u8]) -> Result<UslpFrame<'a>, FrameError> {
+        let header = UslpFrameHeader::decode(bytes)?;
+
+        if header.version != USLP_FRAME_VERSION {
+            return Err(FrameError::InvalidVersion(header.version));
+        }
+
+        if let Some(spacecraft_id) = config.spacecraft_id {
+            if header.spacecraft_id != spacecraft_id {
+                return Err(FrameError::WrongSpacecraft(header.spacecraft_id));
+            }
+        }
+
+        let expected_length = if header.truncated {
+            config.truncated_frame_length.ok_or(FrameError::InvalidHeader)?
+        } else {
+            config.frame_length.unwrap_or(header.frame_length)
+        };
+
+        if bytes.len() != expected_length || bytes.len() != header.frame_length {
+            return Err(FrameError::WrongLength { expected: expected_length, actual: bytes.len() });
+        }
+
+        if config.has_fecf && !fecf_is_valid(bytes) {
+            return Err(FrameError::InvalidFecf);
+        }
+
+        let insert_zone_length = if header.truncated { 0 } else { config.insert_zone_length };
+        let data_field_start = header.size() + insert_zone_length;
+
+        let mut data_field_end = bytes.len();
+        if config.has_fecf {
+            data_field_end = data_field_end.saturating_sub(FECF_SIZE_BYTES);
+        }
+        if header.ocf_flag {
+            data_field_end = data_field_end.saturating_sub(USLP_OCF_SIZE_BYTES);
+        }
+
+        if data_field_start > data_field_end {
+            return Err(FrameError::NotEnoughBytes);
+        }
+
+        let tfdf_header = TfdfHeader::decode(&bytes[data_field_start..data_field_end])?;
+
+        let data_zone_length = data_field_end - data_field_start - tfdf_header.size();
+        if let Some(pointer) = tfdf_header.pointer {
+            if pointer != USLP_FHP_NO_PACKET_START && pointer as usize >= data_zone_length {
+                return Err(FrameError::InvalidHeader);
+            }
+        }
+
+        Ok(UslpFrame {
+            bytes,
+            header,
+            tfdf_header,
+            has_fecf: config.has_fecf,
+            insert_zone_length,
+            data_field_start,
+            data_field_end,
+        })
+    }
+
+    /// The frame's primary header.
+    pub fn header(&self) -> UslpFrameHeader {
+        self.header
+    }
+
+    /// The header at the start of the frame's data field.
+    pub fn tfdf_header(&self) -> TfdfHeader {
+        self.tfdf_header
+    }
+
+    /// The bytes of the whole frame.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The frame's insert zone, if the channel has one and the header is not truncated.
+    pub fn insert_zone(&self) -> Option<&'a [u8]> {
+        if self.insert_zone_length == 0 {
+            return None;
+        }
+
+        Some(&self.bytes[self.data_field_start - self.insert_zone_length..self.data_field_start])
+    }
+
+    /// The data zone of the frame, following the TFDF header.
+    pub fn data_zone(&self) -> &'a [u8] {
+        &self.bytes[self.data_field_start + self.tfdf_header.size()..self.data_field_end]
+    }
+
+    /// The Operational Control Field, if the frame has one. This usually holds a
+    /// Communications Link Control Word reporting the state of the command link.
+    pub fn ocf(&self) -> Option<u32> {
+        if !self.header.ocf_flag {
+            return None;
+        }
+
+        Some(BigEndian::read_u32(&self.bytes[self.data_field_end..]))
+    }
+
+    /// The CLCW in the frame's Operational Control Field, if it holds one.
+    pub fn clcw(&self) -> Option<Clcw> {
+        self.ocf().map(Clcw::from).filter(|clcw| clcw.control_word_type() == 0)
+    }
+
+    /// The Frame Error Control Field, if the channel uses one.
+    pub fn fecf(&self) -> Option<u16> {
+        if !self.has_fecf {
+            return None;
+        }
+
+        Some(BigEndian::read_u16(&self.bytes[self.bytes.len() - FECF_SIZE_BYTES..]))
+    }
+
+    /// Whether the frame carries only idle data, either as an idle frame or with
+    /// an idle data zone.
+    pub fn is_idle(&self) -> bool {
+        self.header.vcid == USLP_IDLE_VCID || self.tfdf_header.upid == USLP_UPID_IDLE
+    }
+
+    /// The offset in the data zone of the first packet header, or None if no packet
+    /// starts in the frame, or the data zone does not hold spanning packets.
+    pub fn first_packet_offset(&self) -> Option<usize> {
+        if self.tfdf_header.construction_rule != ConstructionRule::PacketSpanning {
+            return None;
+        }
+
+        match self.tfdf_header.pointer {
+            Some(USLP_FHP_NO_PACKET_START) | None => None,
+            Some(pointer) => Some(pointer as usize),
+        }
+    }
+}
+
+
+/// A UslpFrameEncoder builds the frames of a USLP physical channel, keeping the next
+/// frame count of each virtual channel. Sequence controlled and expedited frames of a
+/// virtual channel are counted separately.
+#[derive(Debug, Clone)]
+pub struct UslpFrameEncoder {
+    /// The configuration of the physical channel.
+    pub config: UslpFrameConfig,
+
+    /// The insert zone placed in frames which are not truncated. It must have the
+    /// configured insert zone length, and is all zeros when the encoder is created.
+    pub insert_zone: Vec<u8>,
+
+    frame_counts: BTreeMap<(u8, bool), u64>,
+}
+
+impl UslpFrameEncoder {
+    /// Create an encoder for a physical channel.
+    pub fn new(config: UslpFrameConfig) -> UslpFrameEncoder {
+        UslpFrameEncoder {
+            config,
+            insert_zone: vec![0; config.insert_zone_length],
+            frame_counts: BTreeMap::new(),
+        }
+    }
+
+    /// The frame count to be used for the next frame of a virtual channel.
+    pub fn next_frame_count(&self, vcid: u8, bypass: bool) -> u64 {
+        self.frame_counts.get(&(vcid, bypass)).cloned().unwrap_or(0)
+    }
+
+    /// Set the frame count to be used for the next frame of a virtual channel.
+    pub fn set_next_frame_count(&mut self, vcid: u8, bypass: bool, frame_count: u64) {
+        self.frame_counts.insert((vcid, bypass), frame_count);
+    }
+
+    /// Build a frame with the given data zone. The header gives the frame's virtual
+    /// channel, MAP ID, flags and the size of its frame count; the frame length, OCF
+    /// flag and frame count are filled in. Fixed length frames must be filled exactly.
+    pub fn encode(&mut self,
+                  header: &UslpFrameHeader,
+                  tfdf_header: &TfdfHeader,
+                  data: &[u8],
+                  ocf: Option<u32>) -> Result<Vec<u8>, FrameError> {
+        let mut header = *header;
+        header.ocf_flag = ocf.is_some();
+
+        if header.truncated && ocf.is_some() {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        if !header.truncated && self.insert_zone.len() != self.config.insert_zone_length {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        let mut frame_length = header.size() + tfdf_header.size() + data.len();
+        if !header.truncated {
+            frame_length += self.insert_zone.len();
+        }
+        if ocf.is_some() {
+            frame_length += USLP_OCF_SIZE_BYTES;
+        }
+        if self.config.has_fecf {
+            frame_length += FECF_SIZE_BYTES;
+        }
+
+        let expected_length = if header.truncated {
+            Some(self.config.truncated_frame_length.ok_or(FrameError::InvalidHeader)?)
+        } else {
+            self.config.frame_length
+        };
+
+        if let Some(expected) = expected_length {
+            if frame_length != expected {
+                return Err(FrameError::WrongLength { expected, actual: frame_length });
+            }
+        } else if frame_length > self.config.max_frame_length.min(USLP_MAX_FRAME_LENGTH) {
+            return Err(FrameError::FrameTooLong(frame_length));
+        }
+
+        header.frame_length = frame_length;
+        if !header.truncated && header.frame_count_length > 0 {
+            header.frame_count = self.next_frame_count(header.vcid, header.bypass) &
+                                 max_frame_count(header.frame_count_length);
+        }
+
+        let mut frame = header.encode()?;
+        if !header.truncated {
+            frame.extend_from_slice(&self.insert_zone);
+        }
+        frame.extend_from_slice(&tfdf_header.encode());
+        frame.extend_from_slice(data);
+
+        if let Some(ocf) = ocf {
+            let mut bytes = [0; USLP_OCF_SIZE_BYTES];
+            BigEndian::write_u32(&mut bytes, ocf);
+            frame.extend_from_slice(&bytes);
+        }
+
+        if self.config.has_fecf {
+            append_fecf(&mut frame);
+        }
+
+        if !header.truncated && header.frame_count_length > 0 {
+            let next = (header.frame_count + 1) & max_frame_count(header.frame_count_length);
+            self.frame_counts.insert((header.vcid, header.bypass), next);
+        }
+
+        Ok(frame)
+    }
+
+    /// Build variable length frames carrying a packet. A packet which fits in a single
+    /// frame is sent whole, and a longer packet is split into segments.
+    pub fn encode_packet(&mut self, header: &UslpFrameHeader, packet: &[u8]) -> Result<Vec<Vec<u8>>, FrameError> {
+        let mut overhead = header.size() + self.insert_zone.len() + TfdfHeader::new().size();
+        if self.config.has_fecf {
+            overhead += FECF_SIZE_BYTES;
+        }
+
+        let max_frame_length = self.config.max_frame_length.min(USLP_MAX_FRAME_LENGTH);
+        let max_data_length = max_frame_length.saturating_sub(overhead);
+
+        if packet.len() <= max_data_length {
+            return Ok(vec![self.encode(header, &TfdfHeader::new(), packet, None)?]);
+        }
+
+        if max_data_length == 0 {
+            return Err(FrameError::FrameTooLong(overhead + 1));
+        }
+
+        let num_segments = (packet.len() + max_data_length - 1) / max_data_length;
+        let mut frames = Vec::with_capacity(num_segments);
+
+        for (index, segment) in packet.chunks(max_data_length).enumerate() {
+            let construction_rule = if index == 0 {
+                ConstructionRule::StartingSegment
+            } else if index == num_segments - 1 {
+                ConstructionRule::LastSegment
+            } else {
+                ConstructionRule::ContinuingSegment
+            };
+
+            let tfdf_header = TfdfHeader { construction_rule, ..TfdfHeader::new() };
+            frames.push(self.encode(header, &tfdf_header, segment, None)?);
+        }
+
+        Ok(frames)
+    }
+}
+
+
+/// A UslpFrameDecoder reads the USLP frames of a physical channel and extracts the
+/// space packets carried on each virtual channel and MAP. A gap in the frame count of
+/// a virtual channel means frames were lost, so any partial packets on that channel's
+/// MAPs are dropped. Frames carrying data other than space packets are only checked.
+#[derive(Debug, Clone)]
+pub struct UslpFrameDecoder {
+    /// The configuration of the physical channel.
+    pub config: UslpFrameConfig,
+
+    /// The number of frames which could not be read, such as frames with a bad FECF.
+    pub invalid_frames: usize,
+
+    /// The number of gaps in virtual channel frame counts.
+    pub virtual_channel_gaps: usize,
+
+    next_frame_counts: BTreeMap<(u8, bool), u64>,
+
+    extractors: BTreeMap<(u8, u8), PacketExtractor>,
+
+    packets: VecDeque<(u8, u8, BytesMut)>,
+}
+
+impl UslpFrameDecoder {
+    /// Create a decoder for a physical channel.
+    pub fn new(config: UslpFrameConfig) -> UslpFrameDecoder {
+        UslpFrameDecoder {
+            config,
+            invalid_frames: 0,
+            virtual_channel_gaps: 0,
+            next_frame_counts: BTreeMap::new(),
+            extractors: BTreeMap::new(),
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Process a received frame, extracting any packets it completes. Frames that
+    /// can not be read are counted and their error returned.
+    pub fn recv_frame(&mut self, bytes: &[u8]) -> Result<UslpFrameHeader, FrameError> {
+        let frame = match UslpFrame::parse(&self.config, bytes) {
+            Ok(frame) => frame,
+            Err(err) => {
+                self.invalid_frames += 1;
+                return Err(err);
+            },
+        };
+
+        let header = frame.header();
+        if frame.is_idle() {
+            return Ok(header);
+        }
+
+        if !header.truncated && header.frame_count_length > 0 {
+            let key = (header.vcid, header.bypass);
+            if let Some(expected) = self.next_frame_counts.get(&key).cloned() {
+                if expected != header.frame_count {
+                    self.virtual_channel_gaps += 1;
+                    for (_, extractor) in self.extractors.range_mut((header.vcid, 0)..=(header.vcid, USLP_MAX_MAP_ID)) {
+                        extractor.reset();
+                    }
+                }
+            }
+
+            let next = (header.frame_count + 1) & max_frame_count(header.frame_count_length);
+            self.next_frame_counts.insert(key, next);
+        }
+
+        let tfdf_header = frame.tfdf_header();
+        if header.protocol_control_command || tfdf_header.upid != USLP_UPID_SPACE_PACKETS {
+            return Ok(header);
+        }
+
+        let extractor = self.extractors.entry((header.vcid, header.map_id)).or_default();
+        let data_zone = frame.data_zone();
+
+        match tfdf_header.construction_rule {
+            ConstructionRule::PacketSpanning => extractor.recv_data(data_zone, frame.first_packet_offset()),
+
+            ConstructionRule::StartingSegment | ConstructionRule::NoSegmentation =>
+                extractor.recv_data(data_zone, Some(0)),
+
+            ConstructionRule::ContinuingSegment | ConstructionRule::LastSegment =>
+                extractor.recv_data(data_zone, None),
+
+            _ => (),
+        }
+
+        while let Some(packet) = extractor.pull_packet() {
+            self.packets.push_back((header.vcid, header.map_id, packet));
+        }
+
+        Ok(header)
+    }
+
+    /// Take the next extracted packet.
+    pub fn pull_packet(&mut self) -> Option<BytesMut> {
+        self.packets.pop_front().map(|(_, _, packet)| packet)
+    }
+
+    /// Take the next extracted packet, along with the virtual channel and MAP it was carried on.
+    pub fn pull_packet_with_ids(&mut self) -> Option<(u8, u8, BytesMut)> {
+        self.packets.pop_front()
+    }
+
+    /// The packet extractor of a MAP, if any packet frames have been received on it.
+    pub fn extractor(&self, vcid: u8, map_id: u8) -> Option<&PacketExtractor> {
+        self.extractors.get(&(vcid, map_id))
+    }
+}
+
+
+/// The largest frame count which fits in the given number of bytes.
+fn max_frame_count(frame_count_length: u8) -> u64 {
+    if frame_count_length == 0 {
+        0
+    } else {
+        u64::MAX >> (64 - 8 * frame_count_length.min(8) as u32)
+    }
+}
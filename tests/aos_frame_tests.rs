@@ -0,0 +1,207 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::parser::*;
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::frame::*;
+use ccsds_primary_header::aos_frame::*;
+use common::make_packet;
+
+
+const FRAME_LENGTH: usize = 48;
+
+/// A channel with an FHEC, a 4 byte insert zone, an OCF and a FECF, leaving a 28 byte packet zone.
+fn make_config() -> AosFrameConfig {
+    let mut config = AosFrameConfig::new(FRAME_LENGTH);
+    config.has_fhec = true;
+    config.insert_zone_length = 4;
+    config.has_ocf = true;
+    config
+}
+
+fn make_frame(vcid: u8, count: u32, first_header_pointer: u16, data: &[u8]) -> Vec<u8> {
+    let mut header = AosFrameHeader::default();
+    header.set_version(AOS_FRAME_VERSION);
+    header.set_spacecraft_id(0xA5);
+    header.set_vcid(vcid);
+    header.set_virtual_channel_frame_count(count);
+
+    let mut frame = header.0.to_vec();
+    let fhec = header.fhec();
+    frame.push((fhec >> 8) as u8);
+    frame.push(fhec as u8);
+    frame.extend_from_slice(&[0xE0, 0xE1, 0xE2, 0xE3]);
+    frame.push((first_header_pointer >> 8) as u8);
+    frame.push(first_header_pointer as u8);
+    frame.extend_from_slice(data);
+    frame.resize(FRAME_LENGTH - 6, 0x55);
+    frame.extend_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+    let fecf = crc16_ccitt(&frame);
+    frame.push((fecf >> 8) as u8);
+    frame.push(fecf as u8);
+    frame
+}
+
+#[test]
+fn test_aos_frame_header() {
+    let mut header = AosFrameHeader::default();
+    header.set_version(AOS_FRAME_VERSION);
+    header.set_spacecraft_id(0xFF);
+    header.set_vcid(0x2A);
+    header.set_virtual_channel_frame_count(0x123456);
+    header.set_replay_flag(true);
+    header.set_frame_count_usage_flag(true);
+    header.set_frame_count_cycle(0x9);
+
+    assert_eq!(header.0, [0x7F, 0xEA, 0x12, 0x34, 0x56, 0xC9]);
+    assert_eq!(header.version(), AOS_FRAME_VERSION);
+    assert_eq!(header.spacecraft_id(), 0xFF);
+    assert_eq!(header.vcid(), 0x2A);
+    assert_eq!(header.virtual_channel_frame_count(), 0x123456);
+    assert!(header.replay_flag());
+    assert!(header.frame_count_usage_flag());
+    assert_eq!(header.frame_count_cycle(), 0x9);
+    assert_eq!(header.extended_frame_count(), 0x9123456);
+
+    header.set_frame_count_usage_flag(false);
+    assert_eq!(header.extended_frame_count(), 0x123456);
+}
+
+#[test]
+fn test_aos_fhec_correction() {
+    let mut header = AosFrameHeader([0x45, 0x67, 0x00, 0x00, 0x01, 0x80]);
+    let fhec = header.fhec();
+    assert_eq!(header.correct(fhec), Some(0));
+
+    // every single and double symbol error in the protected bytes and FHEC is corrected
+    let positions = [(0, 4), (0, 0), (1, 4), (1, 0), (5, 4), (5, 0), (6, 12), (6, 8), (6, 4), (6, 0)];
+    for first in 0..positions.len() {
+        for second in first..positions.len() {
+            for error in 1..16u8 {
+                let mut errors = vec![(positions[first], error)];
+                if second != first {
+                    errors.push((positions[second], 16 - error));
+                }
+
+                let mut received = header;
+                let mut received_fhec = fhec;
+                for &((position, shift), error) in errors.iter() {
+                    if position == 6 {
+                        received_fhec ^= (error as u16) << shift;
+                    } else {
+                        received.0[position] ^= error << shift;
+                    }
+                }
+
+                assert_eq!(received.correct(received_fhec), Some(errors.len()));
+                assert_eq!(received, header);
+            }
+        }
+    }
+
+    // the frame counts are not protected
+    let mut counted = header;
+    counted.set_virtual_channel_frame_count(100);
+    assert_eq!(counted.fhec(), fhec);
+}
+
+#[test]
+fn test_aos_frame_parse() {
+    let config = make_config();
+    let frame_bytes = make_frame(3, 7, 0, &[0xAA; 28]);
+
+    let frame = AosFrame::parse(&config, &frame_bytes).unwrap();
+    assert_eq!(frame.header().spacecraft_id(), 0xA5);
+    assert_eq!(frame.header().vcid(), 3);
+    assert_eq!(frame.header().virtual_channel_frame_count(), 7);
+    assert_eq!(frame.corrected_symbols(), 0);
+    assert_eq!(frame.insert_zone(), Some(&[0xE0, 0xE1, 0xE2, 0xE3][..]));
+    assert_eq!(frame.data_field().len(), 30);
+    assert_eq!(frame.mpdu_first_header_pointer(), Some(0));
+    assert_eq!(frame.mpdu_packet_zone(), Some(&[0xAA; 28][..]));
+    assert_eq!(frame.first_packet_offset(), Some(0));
+    assert_eq!(frame.ocf(), Some(0x01020304));
+    assert_eq!(frame.clcw().map(|clcw| clcw.report_value()), Some(0x04));
+    assert_eq!(frame.fecf(), Some(crc16_ccitt(&frame_bytes[0..FRAME_LENGTH - 2])));
+    assert!(!frame.is_idle());
+
+    let mut corrupted = frame_bytes.clone();
+    corrupted[20] ^= 0x01;
+    assert_eq!(AosFrame::parse(&config, &corrupted), Err(FrameError::InvalidFecf));
+
+    // without a FECF, header errors are corrected using the FHEC
+    let mut no_fecf = config;
+    no_fecf.has_fecf = false;
+    let mut corrupted = frame_bytes[0..FRAME_LENGTH - 2].to_vec();
+    no_fecf.frame_length = corrupted.len();
+    corrupted[1] ^= 0x21;
+    let frame = AosFrame::parse(&no_fecf, &corrupted).unwrap();
+    assert_eq!(frame.corrected_symbols(), 2);
+    assert_eq!(frame.header().vcid(), 3);
+
+    let mut wrong_spacecraft = config;
+    wrong_spacecraft.spacecraft_id = Some(0x12);
+    assert_eq!(AosFrame::parse(&wrong_spacecraft, &frame_bytes), Err(FrameError::WrongSpacecraft(0xA5)));
+
+    assert_eq!(AosFrame::parse(&config, &frame_bytes[0..30]),
+               Err(FrameError::WrongLength { expected: FRAME_LENGTH, actual: 30 }));
+}
+
+#[test]
+fn test_aos_frame_decoder_spanning_packets() {
+    let mut decoder = AosFrameDecoder::new(make_config());
+
+    // a 36 byte packet spans two frames, followed by an 8 byte packet and an idle packet
+    let first = make_packet(0x10, 30);
+    let second = make_packet(0x11, 2);
+    let mut stream = first.clone();
+    stream.extend_from_slice(&second);
+    stream.extend_from_slice(&make_packet(CCSDS_IDLE_APID, 6));
+
+    decoder.recv_frame(&make_frame(1, 0, 0, &stream[0..28])).unwrap();
+    assert_eq!(decoder.pull_packet(), None);
+
+    decoder.recv_frame(&make_frame(1, 1, 8, &stream[28..56])).unwrap();
+    assert_eq!(decoder.pull_packet_with_vcid(), Some((1, first.as_slice().into())));
+    assert_eq!(decoder.pull_packet(), Some(second.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+
+    // idle frames and idle M_PDUs do not affect extraction
+    decoder.recv_frame(&make_frame(AOS_IDLE_VCID, 0, AOS_FHP_IDLE_DATA, &[])).unwrap();
+    decoder.recv_frame(&make_frame(1, 2, AOS_FHP_IDLE_DATA, &[])).unwrap();
+    assert_eq!(decoder.virtual_channel_gaps, 0);
+
+    // a lost frame drops the partial packet
+    decoder.recv_frame(&make_frame(1, 3, 0, &first[0..28])).unwrap();
+    decoder.recv_frame(&make_frame(1, 5, AOS_FHP_NO_PACKET_START, &first[28..])).unwrap();
+    assert_eq!(decoder.virtual_channel_gaps, 1);
+    assert_eq!(decoder.pull_packet(), None);
+    assert_eq!(decoder.extractor(1).unwrap().dropped_partial_packets, 1);
+}
+
+#[test]
+fn test_aos_frame_decoder_parser_checks() {
+    let mut parser = CcsdsParser::new();
+    parser.config.max_packet_length = Some(10);
+    parser.add_validator(|pri_header, _| {
+        if pri_header.control.apid() == 0x20 { Err(7) } else { Ok(()) }
+    });
+
+    assert_eq!(parser.packet_status(&make_packet(0x10, 4)), CcsdsParserStatus::ValidPacket);
+    assert_eq!(parser.packet_status(&make_packet(0x10, 8)), CcsdsParserStatus::ExceedsMaxPacketLength);
+    assert_eq!(parser.packet_status(&make_packet(0x20, 4)), CcsdsParserStatus::RejectedByValidator(7));
+    assert_eq!(parser.packet_status(&make_packet(0x10, 4)[0..8]), CcsdsParserStatus::NotEnoughBytesPacketLength);
+    assert_eq!(parser.packet_status(&[0x00, 0x10]), CcsdsParserStatus::NotEnoughBytesForHeader);
+
+    let mut decoder = AosFrameDecoder::with_parser(make_config(), parser);
+
+    let mut stream = make_packet(0x10, 4);
+    stream.extend_from_slice(&make_packet(0x10, 5));
+    stream.extend_from_slice(&make_packet(0x20, 1));
+    decoder.recv_frame(&make_frame(2, 0, 0, &stream)).unwrap();
+
+    assert_eq!(decoder.pull_packet(), Some(make_packet(0x10, 4).as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+    assert_eq!(decoder.rejected_packets, 2);
+}
@@ -0,0 +1,100 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::apid_filter::*;
+use ccsds_primary_header::parser::*;
+
+
+#[test]
+fn test_apid_filter_empty() {
+    let filter = ApidFilter::new();
+
+    assert!(!filter.is_allowed(PacketType::Data, 0));
+    assert!(!filter.is_allowed(PacketType::Command, 0x7FF));
+}
+
+#[test]
+fn test_apid_filter_allow_all() {
+    let filter = ApidFilter::allow_all();
+
+    for apid in 0..0x800 {
+        assert!(filter.is_allowed(PacketType::Data, apid));
+        assert!(filter.is_allowed(PacketType::Command, apid));
+    }
+}
+
+#[test]
+fn test_apid_filter_range() {
+    let mut filter = ApidFilter::new();
+    filter.allow(ApidRule::Range(0x100, 0x1FF));
+
+    assert!(!filter.is_allowed(PacketType::Data, 0x0FF));
+    assert!(filter.is_allowed(PacketType::Data, 0x100));
+    assert!(filter.is_allowed(PacketType::Data, 0x150));
+    assert!(filter.is_allowed(PacketType::Data, 0x1FF));
+    assert!(!filter.is_allowed(PacketType::Data, 0x200));
+}
+
+#[test]
+fn test_apid_filter_mask() {
+    let mut filter = ApidFilter::new();
+    // subsystem 3 in the upper 3 bits of the APID
+    filter.allow(ApidRule::Mask { mask: 0x700, value: 0x300 });
+
+    assert!(filter.is_allowed(PacketType::Data, 0x300));
+    assert!(filter.is_allowed(PacketType::Data, 0x3FF));
+    assert!(!filter.is_allowed(PacketType::Data, 0x2FF));
+    assert!(!filter.is_allowed(PacketType::Data, 0x400));
+}
+
+#[test]
+fn test_apid_filter_deny_overrides_allow() {
+    let mut filter = ApidFilter::new();
+    filter.deny(ApidRule::Apid(0x105));
+    filter.allow(ApidRule::Range(0x100, 0x10F));
+
+    assert!(filter.is_allowed(PacketType::Data, 0x104));
+    assert!(!filter.is_allowed(PacketType::Data, 0x105));
+    assert!(filter.is_allowed(PacketType::Data, 0x106));
+}
+
+#[test]
+fn test_apid_filter_packet_type() {
+    let mut filter = ApidFilter::new();
+    filter.allow_for(PacketType::Command, ApidRule::Range(0x10, 0x1F));
+    filter.allow(ApidRule::Apid(0x20));
+    filter.deny_for(PacketType::Data, ApidRule::Apid(0x20));
+
+    assert!(filter.is_allowed(PacketType::Command, 0x10));
+    assert!(!filter.is_allowed(PacketType::Data, 0x10));
+    assert!(filter.is_allowed(PacketType::Command, 0x20));
+    assert!(!filter.is_allowed(PacketType::Data, 0x20));
+}
+
+#[test]
+fn test_ccsds_parser_apid_filter() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    parser.allow_apid(0x04);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ApidNotAllowed);
+
+    parser.allow_apids(ApidRule::Range(0x00, 0x03));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    parser.deny_apids(ApidRule::Apid(0x03));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ApidNotAllowed);
+}
+
+#[test]
+fn test_ccsds_parser_deny_apids() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+
+    parser.deny_apids(ApidRule::Apid(0x04));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    parser.deny_apids(ApidRule::Mask { mask: 0x0F, value: 0x03 });
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ApidNotAllowed);
+}
@@ -0,0 +1,93 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::time::*;
+
+
+#[test]
+fn test_ascii_parse_format_a() {
+    let mut expected = UtcDateTime::new(2017, 3, 4, 5, 6, 7);
+    expected.nanosecond = 890_000_000;
+
+    assert_eq!(parse_ascii_time("2017-03-04T05:06:07.89"), Ok(expected));
+    assert_eq!(parse_ascii_time("2017-03-04T05:06:07.89Z"), Ok(expected));
+}
+
+#[test]
+fn test_ascii_parse_format_b() {
+    let mut expected = UtcDateTime::new(2016, 12, 31, 23, 59, 60);
+    expected.nanosecond = 123_456_789;
+
+    assert_eq!(parse_ascii_time("2016-366T23:59:60.123456789Z"), Ok(expected));
+    assert_eq!(parse_ascii_time("2017-032T00:00:00"), Ok(UtcDateTime::new(2017, 2, 1, 0, 0, 0)));
+}
+
+#[test]
+fn test_ascii_parse_truncated() {
+    assert_eq!(parse_ascii_time("2017-001"), Ok(UtcDateTime::new(2017, 1, 1, 0, 0, 0)));
+    assert_eq!(parse_ascii_time("2017-01-01T12"), Ok(UtcDateTime::new(2017, 1, 1, 12, 0, 0)));
+    assert_eq!(parse_ascii_time("2017-01-01T12:30Z"), Ok(UtcDateTime::new(2017, 1, 1, 12, 30, 0)));
+
+    // extra precision is truncated to nanoseconds
+    let mut expected = UtcDateTime::new(2017, 1, 1, 12, 30, 1);
+    expected.nanosecond = 123_456_789;
+    assert_eq!(parse_ascii_time("2017-01-01T12:30:01.1234567899"), Ok(expected));
+}
+
+#[test]
+fn test_ascii_parse_invalid() {
+    assert_eq!(parse_ascii_time("2017/01/01"), Err(TimeError::InvalidAsciiTime));
+    assert_eq!(parse_ascii_time("17-01-01"), Err(TimeError::InvalidAsciiTime));
+    assert_eq!(parse_ascii_time("2017-01-01T1:00:00"), Err(TimeError::InvalidAsciiTime));
+    assert_eq!(parse_ascii_time("2017-01-01T10:00.5"), Err(TimeError::InvalidAsciiTime));
+    assert_eq!(parse_ascii_time("2017-01-01T10:00:00."), Err(TimeError::InvalidAsciiTime));
+    assert_eq!(parse_ascii_time("2017-01-01T10:00:0a"), Err(TimeError::InvalidAsciiTime));
+
+    assert_eq!(parse_ascii_time("2017-02-29"), Err(TimeError::InvalidDate));
+    assert_eq!(parse_ascii_time("2017-366"), Err(TimeError::InvalidDate));
+    assert_eq!(parse_ascii_time("2017-000"), Err(TimeError::InvalidDate));
+    assert_eq!(parse_ascii_time("2017-01-01T24:00:00"), Err(TimeError::InvalidDate));
+    assert_eq!(parse_ascii_time("2017-06-30T23:59:60"), Err(TimeError::InvalidDate));
+}
+
+#[test]
+fn test_ascii_format() {
+    let mut utc = UtcDateTime::new(2016, 12, 31, 23, 59, 60);
+    utc.nanosecond = 123_456_789;
+
+    assert_eq!(format_ascii_time(&utc, AsciiTimeFormat::A, 0), "2016-12-31T23:59:60Z");
+    assert_eq!(format_ascii_time(&utc, AsciiTimeFormat::A, 3), "2016-12-31T23:59:60.123Z");
+    assert_eq!(format_ascii_time(&utc, AsciiTimeFormat::B, 9), "2016-366T23:59:60.123456789Z");
+    assert_eq!(utc.to_string(), "2016-12-31T23:59:60.123Z");
+}
+
+#[test]
+fn test_ascii_round_trip() {
+    let times = [
+        "1958-01-01T00:00:00.000000000Z",
+        "1999-12-31T23:59:59.999999999Z",
+        "2000-02-29T12:00:00.000000001Z",
+        "2016-12-31T23:59:60.500000000Z",
+        "2024-07-04T01:02:03.040506070Z",
+    ];
+
+    for text in times.iter() {
+        let utc: UtcDateTime = text.parse().unwrap();
+        assert_eq!(&format_ascii_time(&utc, AsciiTimeFormat::A, 9), text);
+
+        let day_of_year = format_ascii_time(&utc, AsciiTimeFormat::B, 9);
+        assert_eq!(parse_ascii_time(&day_of_year), Ok(utc));
+    }
+}
+
+#[test]
+fn test_ascii_binary_conversion() {
+    let cds = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::Microseconds);
+    let time = time_from_ascii(&cds, "2017-001T00:00:00.001002Z").unwrap();
+    assert_eq!(time, CdsTime { days: 21550, ms_of_day: 37_001, submillis: 2 });
+    assert_eq!(time_to_ascii(&cds, &time, AsciiTimeFormat::A, 6), "2017-01-01T00:00:00.001002Z");
+
+    let cuc = CucFormat::new(4, 0);
+    let time = time_from_ascii(&cuc, "2016-12-31T23:59:60Z").unwrap();
+    assert_eq!(time, CucTime { coarse: 21550 * 86400 + 36, fine: 0 });
+    assert_eq!(time_to_ascii(&cuc, &time, AsciiTimeFormat::B, 0), "2016-366T23:59:60Z");
+}
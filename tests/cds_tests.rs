@@ -0,0 +1,166 @@
+extern crate ccsds_primary_header;
+
+use std::time::Duration;
+
+use ccsds_primary_header::time::*;
+
+
+#[test]
+fn test_cds_decode_implicit() {
+    let format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None);
+    let bytes = [0x00, 0x01, 0x00, 0x00, 0x03, 0xE8];
+
+    let (time, len) = format.decode(&bytes).unwrap();
+    assert_eq!(len, 6);
+    assert_eq!(time, CdsTime { days: 1, ms_of_day: 1000, submillis: 0 });
+    assert_eq!(format.to_duration(&time), Duration::new(86401, 0));
+}
+
+#[test]
+fn test_cds_decode_explicit() {
+    let format = CdsFormat { pfield: PField::Explicit, .. CdsFormat::default() };
+
+    // 24 bit days, microseconds
+    let bytes = [0x45, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xE7];
+    let (time, len) = format.decode(&bytes).unwrap();
+    assert_eq!(len, 10);
+    assert_eq!(time, CdsTime { days: 0x010000, ms_of_day: 2, submillis: 999 });
+
+    assert_eq!(format.decode(&[0x43, 0, 0, 0, 0, 0, 0]), Err(TimeError::InvalidPField(0x43)));
+    assert_eq!(format.decode(&[0x1E, 0, 0, 0, 0, 0, 0]), Err(TimeError::InvalidPField(0x1E)));
+}
+
+#[test]
+fn test_cds_pfield_byte() {
+    assert_eq!(CdsFormat::default().pfield_byte(), 0x40);
+
+    let mut format = CdsFormat::new(CdsDaySegment::Days24, CdsSubMillis::Picoseconds);
+    assert_eq!(format.pfield_byte(), 0x46);
+
+    format.epoch = Epoch::from_date(2000, 1, 1);
+    assert_eq!(format.pfield_byte(), 0x4E);
+}
+
+#[test]
+fn test_cds_with_pfield_byte() {
+    let agency_epoch = Epoch::from_date(2000, 1, 1);
+    let mut format = CdsFormat::new(CdsDaySegment::Days24, CdsSubMillis::Picoseconds);
+    format.epoch = agency_epoch;
+
+    // the epoch ID bit chooses between the CCSDS epoch and the agency epoch
+    let decoded = format.with_pfield_byte(0x4E).unwrap();
+    assert_eq!(decoded.epoch, agency_epoch);
+    assert_eq!(decoded.pfield_byte(), 0x4E);
+
+    let decoded = format.with_pfield_byte(0x41).unwrap();
+    assert_eq!(decoded.epoch, CCSDS_EPOCH);
+    assert_eq!(decoded.pfield_byte(), 0x41);
+
+    // a format without an agency epoch can not decode a time using one
+    let format = CdsFormat::default();
+    assert_eq!(format.with_pfield_byte(0x4E), Err(TimeError::InvalidPField(0x4E)));
+}
+
+#[test]
+fn test_cds_encode() {
+    let mut format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::Picoseconds);
+    format.pfield = PField::Explicit;
+    let time = CdsTime { days: 0x1234, ms_of_day: 0x05265C00 - 1, submillis: 999_999_999 };
+
+    assert_eq!(format.encode_to_vec(&time),
+               Ok(vec!(0x42, 0x12, 0x34, 0x05, 0x26, 0x5B, 0xFF, 0x3B, 0x9A, 0xC9, 0xFF)));
+
+    let (decoded, _) = format.decode(&format.encode_to_vec(&time).unwrap()).unwrap();
+    assert_eq!(decoded, time);
+}
+
+#[test]
+fn test_cds_out_of_range() {
+    let format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::Microseconds);
+
+    let time = CdsTime { days: 0x10000, ms_of_day: 0, submillis: 0 };
+    assert_eq!(format.encode_to_vec(&time), Err(TimeError::OutOfRange));
+
+    let time = CdsTime { days: 0, ms_of_day: MILLISECONDS_PER_DAY, submillis: 0 };
+    assert_eq!(format.encode_to_vec(&time), Err(TimeError::OutOfRange));
+
+    let time = CdsTime { days: 0, ms_of_day: 0, submillis: 1000 };
+    assert_eq!(format.encode_to_vec(&time), Err(TimeError::OutOfRange));
+
+    assert_eq!(format.time_from_duration(Duration::new(0x10000 * 86400, 0)), Err(TimeError::OutOfRange));
+}
+
+#[test]
+fn test_cds_duration_round_trip() {
+    let format = CdsFormat::new(CdsDaySegment::Days24, CdsSubMillis::Microseconds);
+    let duration = Duration::new(1_000_000_000, 123_456_000);
+
+    let time = format.time_from_duration(duration).unwrap();
+    assert_eq!(time, CdsTime { days: 11574, ms_of_day: 6_400_123, submillis: 456 });
+    assert_eq!(format.to_duration(&time), duration);
+}
+
+#[test]
+fn test_cds_tai_to_utc() {
+    let format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None);
+
+    // 2017-01-01T00:00:00 UTC is 37 seconds into the day in TAI
+    let time = CdsTime { days: 21550, ms_of_day: 37_000, submillis: 0 };
+    assert_eq!(format.to_utc(&time), UtcDateTime::new(2017, 1, 1, 0, 0, 0));
+    assert_eq!(format.time_from_utc(&UtcDateTime::new(2017, 1, 1, 0, 0, 0)), Ok(time));
+
+    // the leap second before it
+    let time = CdsTime { days: 21550, ms_of_day: 36_000, submillis: 0 };
+    assert_eq!(format.to_utc(&time), UtcDateTime::new(2016, 12, 31, 23, 59, 60));
+    assert_eq!(format.time_from_utc(&UtcDateTime::new(2016, 12, 31, 23, 59, 60)), Ok(time));
+
+    let time = CdsTime { days: 21550, ms_of_day: 35_000, submillis: 0 };
+    assert_eq!(format.to_utc(&time), UtcDateTime::new(2016, 12, 31, 23, 59, 59));
+}
+
+#[test]
+fn test_cds_utc_leap_second() {
+    let mut format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::Microseconds);
+    format.time_scale = TimeScale::Utc;
+
+    let mut utc = UtcDateTime::new(2016, 12, 31, 23, 59, 60);
+    utc.nanosecond = 500_250_000;
+
+    let time = format.time_from_utc(&utc).unwrap();
+    assert_eq!(time, CdsTime { days: 21549, ms_of_day: 86_400_500, submillis: 250 });
+    assert_eq!(format.to_utc(&time), utc);
+
+    // a leap second can only occur on a day that has one
+    let utc = UtcDateTime::new(2016, 12, 30, 23, 59, 60);
+    assert_eq!(format.time_from_utc(&utc), Err(TimeError::InvalidDate));
+}
+
+#[test]
+fn test_cds_agency_epoch() {
+    let mut format = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None);
+    format.epoch = Epoch::from_date(2000, 1, 1);
+    format.time_scale = TimeScale::Utc;
+
+    let time = CdsTime { days: 366, ms_of_day: 3_723_000, submillis: 0 };
+    assert_eq!(format.to_utc(&time), UtcDateTime::new(2001, 1, 1, 1, 2, 3));
+    assert_eq!(format.time_from_utc(&UtcDateTime::new(1999, 12, 31, 0, 0, 0)), Err(TimeError::OutOfRange));
+}
+
+fn utc_from_packet<F: TimeCodeFormat>(format: &F, packet: &[u8]) -> Result<UtcDateTime, TimeError> {
+    format.decode_from_packet(packet).map(|time| format.to_utc(&time))
+}
+
+#[test]
+fn test_time_code_generic() {
+    let header = [0x08, 0x10, 0xC0, 0x00, 0x00, 0x07];
+
+    let cds = CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None);
+    let mut packet = header.to_vec();
+    packet.extend_from_slice(&cds.encode_to_vec(&CdsTime { days: 21550, ms_of_day: 37_000, submillis: 0 }).unwrap());
+    assert_eq!(utc_from_packet(&cds, &packet), Ok(UtcDateTime::new(2017, 1, 1, 0, 0, 0)));
+
+    let cuc = CucFormat::new(4, 2);
+    let mut packet = header.to_vec();
+    packet.extend_from_slice(&cuc.encode_to_vec(&CucTime { coarse: 21550 * 86400 + 37, fine: 0 }).unwrap());
+    assert_eq!(utc_from_packet(&cuc, &packet), Ok(UtcDateTime::new(2017, 1, 1, 0, 0, 0)));
+}
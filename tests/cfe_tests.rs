@@ -0,0 +1,104 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::parser::*;
+use ccsds_primary_header::secondary_header::*;
+use ccsds_primary_header::space_packet::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::cfe;
+use ccsds_primary_header::cfe::*;
+use common::build_packet;
+
+
+fn make_packet(packet_type: PacketType, data: &[u8]) -> Vec<u8> {
+    build_packet(packet_type, 0x05, true, data)
+}
+
+#[test]
+fn test_cfe_checksum() {
+    let mut packet = make_packet(PacketType::Command, &[0x03, 0x00, 0x11, 0x22]);
+    assert_eq!(cfe::function_code(&packet), Some(3));
+
+    // 0xFF ^ 0x18 ^ 0x05 ^ 0xC0 ^ 0x00 ^ 0x00 ^ 0x03 (primary header) ^ 0x03 ^ 0x11 ^ 0x22
+    assert_eq!(cfe::compute_checksum(&packet), Some(0x11));
+    assert!(!cfe::is_checksum_valid(&packet));
+
+    assert!(cfe::set_checksum(&mut packet));
+    assert_eq!(cfe::checksum(&packet), Some(0x11));
+    assert!(cfe::is_checksum_valid(&packet));
+
+    // bytes following the packet are not part of the checksum
+    let mut extended = packet.clone();
+    extended.push(0x55);
+    assert!(cfe::is_checksum_valid(&extended));
+    assert_eq!(cfe::compute_checksum(&extended), Some(0x11));
+
+    assert!(cfe::set_function_code(&mut packet, 4));
+    assert_eq!(cfe::function_code(&packet), Some(4));
+    assert!(!cfe::is_checksum_valid(&packet));
+    assert!(!cfe::set_function_code(&mut packet, 0x80));
+}
+
+#[test]
+fn test_cfe_not_command() {
+    let mut packet = make_packet(PacketType::Data, &[0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(cfe::function_code(&packet), None);
+    assert_eq!(cfe::compute_checksum(&packet), None);
+    assert!(!cfe::set_checksum(&mut packet));
+    assert!(!cfe::is_checksum_valid(&packet));
+
+    // the packet is shorter than its length field
+    let packet = make_packet(PacketType::Command, &[0x03, 0x00, 0x11, 0x22]);
+    assert_eq!(cfe::compute_checksum(&packet[0..9]), None);
+}
+
+#[test]
+fn test_cfe_headers() {
+    let packet = make_packet(PacketType::Data, &[0x00, 0x00, 0x01, 0x00, 0x80, 0x00, 0xAA]);
+    let space_packet = SpacePacket::new(&packet).unwrap();
+
+    let tlm_header = space_packet.secondary_header::<CfeTlmHeader>(&()).unwrap();
+    assert_eq!(tlm_header, CfeTlmHeader { seconds: 0x100, subseconds: 0x8000 });
+    assert_eq!(tlm_header.time(), CucTime { coarse: 0x100, fine: 0x80000000 });
+    assert_eq!(CfeTlmHeader::from_time(&tlm_header.time()), tlm_header);
+    assert_eq!(space_packet.user_data::<CfeTlmHeader>(&()), Some(&[0xAA][..]));
+
+    let format = cfe::time_format(Epoch::from_date(1980, 1, 6));
+    assert_eq!(format.decode_from_packet(&packet), Ok(tlm_header.time()));
+
+    let mut bytes = [0; 6];
+    assert_eq!(tlm_header.write(&(), &mut bytes), Some(6));
+    assert_eq!(bytes, [0x00, 0x00, 0x01, 0x00, 0x80, 0x00]);
+
+    let cmd_header = CfeCmdHeader { function_code: 0x12, checksum: 0x34 };
+    assert_eq!(cmd_header.write(&(), &mut bytes), Some(2));
+    assert_eq!(CfeCmdHeader::parse(&(), &bytes), Some(cmd_header));
+    assert_eq!(CfeCmdHeader { function_code: 0x80, checksum: 0 }.write(&(), &mut bytes), None);
+}
+
+#[test]
+fn test_cfe_parser() {
+    let mut parser = cfe::parser();
+
+    let mut good = make_packet(PacketType::Command, &[0x03, 0x00, 0x11, 0x22]);
+    cfe::set_checksum(&mut good);
+    parser.recv_slice(&good);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+    assert_eq!(parser.pull_packet().unwrap().to_vec(), good);
+
+    let mut bad = good.clone();
+    bad[9] ^= 0x01;
+    parser.recv_slice(&bad);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::RejectedByValidator(CFE_CHECKSUM_REJECTED));
+
+    // telemetry has no checksum, but must hold the time secondary header
+    parser.bytes.clear();
+    parser.recv_slice(&make_packet(PacketType::Data, &[0, 0, 0, 0, 0, 0, 0xAA]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    parser.bytes.clear();
+    parser.recv_slice(&make_packet(PacketType::Data, &[0, 0, 0, 0, 0]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::SecondaryHeaderTooShort);
+}
@@ -0,0 +1,62 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::clcw::*;
+
+
+#[test]
+fn test_clcw_fields() {
+    let mut clcw = Clcw::new(0x2A);
+    clcw.set_status_field(0x05);
+    clcw.set_no_bit_lock(true);
+    clcw.set_lockout(true);
+    clcw.set_retransmit(true);
+    clcw.set_farm_b_counter(0x02);
+    clcw.set_report_value(0xC3);
+
+    assert_eq!(clcw.0, [0x15, 0xA8, 0x6C, 0xC3]);
+    assert_eq!(clcw.control_word_type(), 0);
+    assert_eq!(clcw.version(), CLCW_VERSION);
+    assert_eq!(clcw.status_field(), 0x05);
+    assert_eq!(clcw.cop_in_effect(), CLCW_COP_1);
+    assert_eq!(clcw.vcid(), 0x2A);
+    assert!(!clcw.no_rf_available());
+    assert!(clcw.no_bit_lock());
+    assert!(clcw.lockout());
+    assert!(!clcw.wait());
+    assert!(clcw.retransmit());
+    assert_eq!(clcw.farm_b_counter(), 0x02);
+    assert_eq!(clcw.report_value(), 0xC3);
+
+    clcw.set_lockout(false);
+    clcw.set_farm_b_counter(0x07);
+    assert!(!clcw.lockout());
+    assert_eq!(clcw.farm_b_counter(), 0x03);
+    assert!(clcw.retransmit());
+}
+
+#[test]
+fn test_clcw_conversions() {
+    let clcw = Clcw::from(0x0104_1A07);
+    assert_eq!(clcw.vcid(), 1);
+    assert!(clcw.wait());
+    assert!(clcw.retransmit());
+    assert_eq!(clcw.farm_b_counter(), 1);
+    assert_eq!(clcw.report_value(), 0x07);
+    assert_eq!(u32::from(clcw), 0x0104_1A07);
+
+    assert_eq!(Clcw::from_slice(&[0x01, 0x04, 0x1A, 0x07, 0xFF]), Some(clcw));
+    assert_eq!(Clcw::from_slice(&[0x01, 0x04, 0x1A]), None);
+}
+
+#[test]
+fn test_clcw_display() {
+    let mut clcw = Clcw::new(1);
+    assert_eq!(clcw.to_string(), "VC 1 V(R) 0 FARM-B 0");
+
+    clcw.set_no_rf_available(true);
+    clcw.set_lockout(true);
+    clcw.set_wait(true);
+    clcw.set_farm_b_counter(2);
+    clcw.set_report_value(7);
+    assert_eq!(clcw.to_string(), "VC 1 V(R) 7 FARM-B 2 no RF lockout wait");
+}
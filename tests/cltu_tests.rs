@@ -0,0 +1,116 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::cltu::*;
+use ccsds_primary_header::tc_frame::*;
+use common::make_command_packet;
+
+
+fn flip_bit(bytes: &mut [u8], bit: usize) {
+    bytes[bit / 8] ^= 0x80 >> (bit % 8);
+}
+
+#[test]
+fn test_cltu_bch_parity() {
+    // all zeros has a remainder of zero, which is complemented
+    assert_eq!(bch_parity(&[0; 7]), 0xFE);
+
+    let info = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD];
+    let mut codeblock = info.to_vec();
+    codeblock.push(bch_parity(&info));
+    assert_eq!(codeblock[7] & 0x01, 0);
+    assert_eq!(bch_correct(&mut codeblock), Some(0));
+
+    // the tail sequence can never be corrected
+    let mut tail = CLTU_TAIL_SEQUENCE;
+    assert_eq!(bch_correct(&mut tail), None);
+}
+
+#[test]
+fn test_cltu_bch_correct() {
+    let info = [0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x55, 0xFF];
+    let mut codeblock = info.to_vec();
+    codeblock.push(bch_parity(&info));
+
+    // every single bit error is corrected, and the filler bit is ignored
+    for bit in 0..64 {
+        let mut received = codeblock.clone();
+        flip_bit(&mut received, bit);
+
+        let expected = if bit == 63 { 0 } else { 1 };
+        assert_eq!(bch_correct(&mut received), Some(expected));
+        if bit != 63 {
+            assert_eq!(received, codeblock);
+        }
+    }
+
+    // every double bit error is detected
+    for first in 0..63 {
+        for second in first + 1..63 {
+            let mut received = codeblock.clone();
+            flip_bit(&mut received, first);
+            flip_bit(&mut received, second);
+            assert_eq!(bch_correct(&mut received), None);
+        }
+    }
+}
+
+#[test]
+fn test_cltu_encode() {
+    let frame: Vec<u8> = (0..10).collect();
+    let cltu = encode_cltu(&frame);
+
+    assert_eq!(cltu.len(), 2 + 2 * 8 + 8);
+    assert_eq!(&cltu[0..2], &CLTU_START_SEQUENCE);
+    assert_eq!(&cltu[2..9], &frame[0..7]);
+    assert_eq!(cltu[9], bch_parity(&frame[0..7]));
+    assert_eq!(&cltu[10..17], &[7, 8, 9, 0x55, 0x55, 0x55, 0x55]);
+    assert_eq!(cltu[17], bch_parity(&cltu[10..17]));
+    assert_eq!(&cltu[18..26], &CLTU_TAIL_SEQUENCE);
+}
+
+#[test]
+fn test_cltu_decode_frames() {
+    let config = TcFrameConfig::new(0x123);
+    let mut encoder = TcFrameEncoder::new(config);
+    let packets = [make_command_packet(0x10, 4), make_command_packet(0x11, 20)];
+
+    // idle bits between CLTUs, and a bit error in a codeblock of each CLTU
+    let mut stream = vec![0x55, 0x55, 0xEB];
+    for (index, packet) in packets.iter().enumerate() {
+        let frame = encoder.encode(TcFrameType::AD, 1, 0, packet).unwrap();
+        let mut cltu = encode_cltu(&frame);
+        flip_bit(&mut cltu, 16 + index * 70);
+
+        stream.extend_from_slice(&cltu);
+        stream.extend_from_slice(&[0x55; 5]);
+    }
+
+    let mut cltu_decoder = CltuDecoder::new();
+    for chunk in stream.chunks(3) {
+        cltu_decoder.recv_slice(chunk);
+    }
+
+    assert_eq!(cltu_decoder.num_cltus(), 2);
+    assert_eq!(cltu_decoder.corrected_bits, 2);
+    assert_eq!(cltu_decoder.rejected_codeblocks, 0);
+
+    let mut decoder = TcFrameDecoder::new(config);
+    while let Some(data) = cltu_decoder.pull_cltu() {
+        decoder.recv_frame(&data).unwrap();
+    }
+
+    assert_eq!(decoder.pull_packet(), Some(packets[0].as_slice().into()));
+    assert_eq!(decoder.pull_packet(), Some(packets[1].as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+
+    // without correction, the codeblock with an error ends the CLTU early, so the
+    // first gives no data and the second only its first codeblock
+    let mut cltu_decoder = CltuDecoder::new();
+    cltu_decoder.correct_errors = false;
+    cltu_decoder.recv_slice(&stream);
+    assert_eq!(cltu_decoder.rejected_codeblocks, 2);
+    assert_eq!(cltu_decoder.pull_cltu().unwrap().len(), 7);
+    assert_eq!(cltu_decoder.pull_cltu(), None);
+}
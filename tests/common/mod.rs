@@ -0,0 +1,40 @@
+/*!
+Packet fixtures shared by the integration tests. Each test file includes this
+module with `mod common;`, and most use only some of the fixtures.
+*/
+#![allow(dead_code)]
+
+use ccsds_primary_header::primary_header::*;
+
+
+/// Create the primary header of an unsegmented packet with the given data field length.
+pub fn packet_header(packet_type: PacketType, apid: u16, sec_header: bool, data_length: usize) -> PrimaryHeader {
+    let mut pri_header: PrimaryHeader = Default::default();
+    pri_header.control.set_packet_type(packet_type);
+    pri_header.control.set_apid(apid);
+    if sec_header {
+        pri_header.control.set_secondary_header_flag(SecondaryHeaderFlag::Present);
+    }
+    pri_header.sequence.set_sequence_type(SeqFlag::Unsegmented);
+    pri_header.length.set_length_field(data_length as u16 - 1);
+    pri_header
+}
+
+/// Build an unsegmented packet with the given data field.
+pub fn build_packet(packet_type: PacketType, apid: u16, sec_header: bool, data: &[u8]) -> Vec<u8> {
+    let mut packet = packet_header(packet_type, apid, sec_header, data.len()).to_bytes().to_vec();
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// Build a telemetry packet whose data field counts up from 0.
+pub fn make_packet(apid: u16, data_length: usize) -> Vec<u8> {
+    let data: Vec<u8> = (0..data_length).map(|index| index as u8).collect();
+    build_packet(PacketType::Data, apid, false, &data)
+}
+
+/// Build a command packet whose data field counts up from 0.
+pub fn make_command_packet(apid: u16, data_length: usize) -> Vec<u8> {
+    let data: Vec<u8> = (0..data_length).map(|index| index as u8).collect();
+    build_packet(PacketType::Command, apid, false, &data)
+}
@@ -0,0 +1,171 @@
+extern crate ccsds_primary_header;
+
+#[cfg(feature = "toml")]
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::parser::*;
+use ccsds_primary_header::config::*;
+
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_empty_toml() {
+    let config = CcsdsParserConfig::from_toml_str("").unwrap();
+    assert_eq!(config, CcsdsParserConfig::new());
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_config_empty_json() {
+    let config = CcsdsParserConfig::from_json_str("{}").unwrap();
+    assert_eq!(config, CcsdsParserConfig::new());
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_toml() {
+    let text = r#"
+        sync_bytes = "1ACFFC1D"
+        keep_sync = true
+        num_header_bytes = 2
+        keep_header = true
+        num_footer_bytes = 2
+        max_packet_length = 1024
+        min_packet_length = 8
+        secondary_header_required = true
+
+        allowed_apids = [
+            { first = 0x100, last = 0x1FF },
+            { apid = 0x20, packet_type = "command" },
+            { mask = 0x700, value = 0x300 },
+        ]
+        denied_apids = [ { apid = 0x105 } ]
+
+        apid_lengths = [
+            { apid = 0x100, length = 256 },
+            { apid = 0x101, min = 10, max = 20 },
+        ]
+
+        [secondary_header_sizes]
+        data = 10
+        command = 6
+        apids = [ { apid = 0x100, size = 12 } ]
+    "#;
+
+    let config = CcsdsParserConfig::from_toml_str(text).unwrap();
+
+    assert_eq!(config.sync_bytes, vec!(0x1A, 0xCF, 0xFC, 0x1D));
+    assert!(config.keep_sync);
+    assert_eq!(config.num_header_bytes, 2);
+    assert!(config.keep_header);
+    assert_eq!(config.num_footer_bytes, 2);
+    assert!(!config.keep_footer);
+    assert_eq!(config.max_packet_length, Some(1024));
+    assert_eq!(config.min_packet_length, Some(8));
+    assert!(config.secondary_header_required);
+
+    let filter = config.allowed_apids.unwrap();
+    assert!(filter.is_allowed(PacketType::Data, 0x100));
+    assert!(!filter.is_allowed(PacketType::Data, 0x105));
+    assert!(filter.is_allowed(PacketType::Command, 0x20));
+    assert!(!filter.is_allowed(PacketType::Data, 0x20));
+    assert!(filter.is_allowed(PacketType::Data, 0x3AB));
+
+    assert_eq!(config.apid_lengths.get(&0x100), Some(&ExpectedLength::Exact(256)));
+    assert_eq!(config.apid_lengths.get(&0x101), Some(&ExpectedLength::Range { min: 10, max: 20 }));
+
+    assert_eq!(config.secondary_header_sizes.data, Some(10));
+    assert_eq!(config.secondary_header_sizes.command, Some(6));
+    assert_eq!(config.secondary_header_sizes.apids.get(&0x100), Some(&12));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_config_json() {
+    let text = r#"{
+        "sync_bytes": "0xEB90",
+        "max_packet_length": 100,
+        "allowed_apids": [ { "first": "0x10", "last": "0x1F" } ],
+        "apid_lengths": [ { "apid": "0x10", "length": 8 } ]
+    }"#;
+
+    let config = CcsdsParserConfig::from_json_str(text).unwrap();
+
+    assert_eq!(config.sync_bytes, vec!(0xEB, 0x90));
+    assert_eq!(config.max_packet_length, Some(100));
+
+    let mut parser = CcsdsParser::with_config(config);
+    parser.recv_slice(&[0xEB, 0x90, 0x00,0x10,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    assert_eq!(CcsdsParserConfig::from_json_str(r#"{ "allowed_apids": [ { "apid": "0xZZ" } ] }"#),
+               Err(ConfigError::InvalidNumber("0xZZ".to_string())));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_deny_only() {
+    let config = CcsdsParserConfig::from_toml_str("denied_apids = [ { apid = 0x7FF } ]").unwrap();
+
+    let filter = config.allowed_apids.unwrap();
+    assert!(filter.is_allowed(PacketType::Data, 0x7FE));
+    assert!(!filter.is_allowed(PacketType::Data, 0x7FF));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_min_exceeds_max() {
+    let result = CcsdsParserConfig::from_toml_str("min_packet_length = 100\nmax_packet_length = 50");
+    assert_eq!(result, Err(ConfigError::MinLengthExceedsMaxLength { min: 100, max: 50 }));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_min_below_ccsds_min() {
+    let result = CcsdsParserConfig::from_toml_str("min_packet_length = 6");
+    assert_eq!(result, Err(ConfigError::LengthBelowCcsdsMinimum(6)));
+
+    let result = CcsdsParserConfig::from_toml_str("apid_lengths = [ { apid = 1, length = 3 } ]");
+    assert_eq!(result, Err(ConfigError::LengthBelowCcsdsMinimum(3)));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_keep_header_without_bytes() {
+    let result = CcsdsParserConfig::from_toml_str("keep_header = true");
+    assert_eq!(result, Err(ConfigError::KeepHeaderWithoutHeaderBytes));
+}
+
+#[test]
+#[cfg(feature = "toml")]
+fn test_config_invalid_values() {
+    assert_eq!(CcsdsParserConfig::from_toml_str("sync_bytes = \"EB9\""),
+               Err(ConfigError::InvalidSyncBytes("EB9".to_string())));
+
+    assert_eq!(CcsdsParserConfig::from_toml_str("allowed_apids = [ { apid = 0x800 } ]"),
+               Err(ConfigError::ApidOutOfRange(0x800)));
+
+    assert_eq!(CcsdsParserConfig::from_toml_str("allowed_apids = [ { apid = 1, packet_type = \"other\" } ]"),
+               Err(ConfigError::InvalidPacketType("other".to_string())));
+
+    match CcsdsParserConfig::from_toml_str("allowed_apids = [ { apid = 1, first = 2, last = 3 } ]") {
+        Err(ConfigError::InvalidApidRule(_)) => {},
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    match CcsdsParserConfig::from_toml_str("unknown_field = 1") {
+        Err(ConfigError::Parse(_)) => {},
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_config_validate() {
+    let mut config = CcsdsParserConfig::new();
+    assert_eq!(config.validate(), Ok(()));
+
+    config.keep_footer = true;
+    assert_eq!(config.validate(), Err(ConfigError::KeepFooterWithoutFooterBytes));
+
+    config.num_footer_bytes = 4;
+    assert_eq!(config.validate(), Ok(()));
+}
@@ -0,0 +1,267 @@
+extern crate ccsds_primary_header;
+
+use std::time::Duration;
+
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::clcw::*;
+use ccsds_primary_header::cop1::*;
+use ccsds_primary_header::tc_frame::*;
+
+
+const SPACECRAFT_ID: u16 = 0x123;
+
+const VCID: u8 = 2;
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+fn make_frame(frame_type: TcFrameType, sequence_number: u8, data: &[u8]) -> Vec<u8> {
+    let mut encoder = TcFrameEncoder::new(TcFrameConfig::new(SPACECRAFT_ID));
+    encoder.set_next_sequence_number(VCID, sequence_number);
+    encoder.encode(frame_type, VCID, 0, data).unwrap()
+}
+
+fn farm_recv(farm: &mut Farm, frame: &[u8]) -> FarmAction {
+    let frame = TcFrame::parse(&TcFrameConfig::new(SPACECRAFT_ID), frame).unwrap();
+    farm.recv_frame(&frame)
+}
+
+/// Send the frames waiting in the FOP to the FARM, except those for which drop returns
+/// true, giving the data of the Type-AD frames accepted.
+fn deliver<F: Fn(&[u8]) -> bool>(fop: &mut Fop, farm: &mut Farm, drop: F) -> Vec<Vec<u8>> {
+    let config = TcFrameConfig::new(SPACECRAFT_ID);
+    let mut accepted = Vec::new();
+
+    while let Some(bytes) = fop.pull_frame() {
+        if drop(&bytes) {
+            continue;
+        }
+
+        let frame = TcFrame::parse(&config, &bytes).unwrap();
+        if farm.recv_frame(&frame) == FarmAction::Accept && frame.header().frame_type() == TcFrameType::AD {
+            accepted.push(frame.data().to_vec());
+        }
+    }
+
+    accepted
+}
+
+#[test]
+fn test_cop1_control_commands() {
+    assert_eq!(ControlCommand::Unlock.encode(), vec![0x00]);
+    assert_eq!(ControlCommand::SetVr(0x42).encode(), vec![0x82, 0x00, 0x42]);
+    assert_eq!(ControlCommand::decode(&[0x00]), Some(ControlCommand::Unlock));
+    assert_eq!(ControlCommand::decode(&[0x82, 0x00, 0x42]), Some(ControlCommand::SetVr(0x42)));
+    assert_eq!(ControlCommand::decode(&[0x82, 0x01, 0x42]), None);
+    assert_eq!(ControlCommand::decode(&[]), None);
+}
+
+#[test]
+fn test_cop1_farm_window() {
+    let mut farm = Farm::new(VCID, 10);
+
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 0, &[1])), FarmAction::Accept);
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 1, &[2])), FarmAction::Accept);
+    assert_eq!(farm.next_sequence_number(), 2);
+    assert!(!farm.clcw().retransmit());
+
+    // a frame ahead of V(R) means one was lost, so a retransmission is asked for
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 4, &[3])), FarmAction::Discard);
+    assert!(farm.clcw().retransmit());
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 2, &[3])), FarmAction::Accept);
+    assert!(!farm.clcw().retransmit());
+
+    // a repeated frame behind V(R) is discarded
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 0, &[1])), FarmAction::Discard);
+    assert_eq!(farm.state(), FarmState::Open);
+
+    // Type-BD frames are always accepted, and counted
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::BD, 0, &[9])), FarmAction::Accept);
+    assert_eq!(farm.clcw().farm_b_counter(), 1);
+
+    // frames for other virtual channels are discarded
+    let mut other = make_frame(TcFrameType::AD, 3, &[4]);
+    let mut header = TcFrameHeader::from_slice(&other).unwrap();
+    header.set_vcid(VCID + 1);
+    other[0..5].copy_from_slice(&header.0);
+    let fecf = crc16_ccitt(&other[0..other.len() - 2]);
+    let length = other.len();
+    other[length - 2] = (fecf >> 8) as u8;
+    other[length - 1] = fecf as u8;
+    assert_eq!(farm_recv(&mut farm, &other), FarmAction::Discard);
+    assert_eq!(farm.next_sequence_number(), 3);
+
+    let clcw = farm.clcw();
+    assert_eq!(clcw.vcid(), VCID);
+    assert_eq!(clcw.cop_in_effect(), CLCW_COP_1);
+    assert_eq!(clcw.report_value(), 3);
+}
+
+#[test]
+fn test_cop1_farm_lockout_and_wait() {
+    let mut farm = Farm::new(VCID, 10);
+
+    // a frame outside the window locks the FARM out, until unlocked
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 100, &[1])), FarmAction::Discard);
+    assert_eq!(farm.state(), FarmState::Lockout);
+    assert!(farm.clcw().lockout());
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 0, &[1])), FarmAction::Discard);
+
+    // Set V(R) is not carried out in lockout, but Unlock is
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::BC, 0, &ControlCommand::SetVr(50).encode())), FarmAction::Accept);
+    assert_eq!(farm.next_sequence_number(), 0);
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::BC, 0, &ControlCommand::Unlock.encode())), FarmAction::Accept);
+    assert_eq!(farm.state(), FarmState::Open);
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::BC, 0, &ControlCommand::SetVr(50).encode())), FarmAction::Accept);
+    assert_eq!(farm.next_sequence_number(), 50);
+    assert_eq!(farm.clcw().farm_b_counter(), 3);
+
+    // without buffer space the expected frame is discarded, until space is released
+    farm.set_buffer_available(false);
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 50, &[1])), FarmAction::Discard);
+    assert_eq!(farm.state(), FarmState::Wait);
+    assert!(farm.clcw().wait());
+    assert!(farm.clcw().retransmit());
+
+    farm.set_buffer_available(true);
+    assert_eq!(farm.state(), FarmState::Open);
+    assert_eq!(farm_recv(&mut farm, &make_frame(TcFrameType::AD, 50, &[1])), FarmAction::Accept);
+    assert_eq!(farm.clcw().0[2], 0x06);
+}
+
+#[test]
+fn test_cop1_fop_delivers_in_order() {
+    let mut config = FopConfig::new(VCID);
+    config.window_width = 2;
+    let mut fop = Fop::new(config, TcFrameConfig::new(SPACECRAFT_ID));
+    let mut farm = Farm::new(VCID, 10);
+
+    assert_eq!(fop.transfer(&[0], seconds(0)), Err(CopError::InvalidState(FopState::Initial)));
+    fop.initiate().unwrap();
+    assert_eq!(fop.state(), FopState::Active);
+
+    // two frames fill the window, one more waits, and a fourth is rejected
+    for index in 0..3 {
+        fop.transfer(&[index], seconds(0)).unwrap();
+    }
+    assert_eq!(fop.transfer(&[3], seconds(0)), Err(CopError::Busy));
+    assert_eq!(fop.num_frames(), 2);
+    assert_eq!(fop.num_unacknowledged(), 2);
+    assert_eq!(fop.timer(), Some(seconds(5)));
+
+    assert_eq!(deliver(&mut fop, &mut farm, |_| false), vec![vec![0], vec![1]]);
+
+    // the acknowledgement makes room for the waiting frame
+    fop.recv_clcw(&farm.clcw(), seconds(1)).unwrap();
+    assert_eq!(fop.expected_ack(), 2);
+    assert_eq!(fop.num_unacknowledged(), 1);
+    assert_eq!(deliver(&mut fop, &mut farm, |_| false), vec![vec![2]]);
+
+    fop.recv_clcw(&farm.clcw(), seconds(2)).unwrap();
+    assert_eq!(fop.num_unacknowledged(), 0);
+    assert_eq!(fop.timer(), None);
+    assert_eq!(fop.next_sequence_number(), 3);
+    assert_eq!(fop.pull_alert(), None);
+}
+
+#[test]
+fn test_cop1_fop_retransmits_lost_frames() {
+    let mut fop = Fop::new(FopConfig::new(VCID), TcFrameConfig::new(SPACECRAFT_ID));
+    let mut farm = Farm::new(VCID, 10);
+    fop.initiate().unwrap();
+
+    for index in 0..4 {
+        fop.transfer(&[index], seconds(0)).unwrap();
+    }
+
+    // frame 1 is lost, so the FARM accepts only frame 0 and asks for a retransmission
+    let lost = |frame: &[u8]| frame[4] == 1;
+    assert_eq!(deliver(&mut fop, &mut farm, lost), vec![vec![0]]);
+    assert!(farm.clcw().retransmit());
+
+    fop.recv_clcw(&farm.clcw(), seconds(1)).unwrap();
+    assert_eq!(fop.state(), FopState::RetransmitWithoutWait);
+    assert_eq!(fop.num_frames(), 3);
+
+    // a repeated report does not send the frames yet again
+    fop.recv_clcw(&farm.clcw(), seconds(1)).unwrap();
+    assert_eq!(fop.num_frames(), 3);
+
+    assert_eq!(deliver(&mut fop, &mut farm, |_| false), vec![vec![1], vec![2], vec![3]]);
+    fop.recv_clcw(&farm.clcw(), seconds(2)).unwrap();
+    assert_eq!(fop.state(), FopState::Active);
+    assert_eq!(fop.num_unacknowledged(), 0);
+}
+
+#[test]
+fn test_cop1_fop_timeout() {
+    let mut config = FopConfig::new(VCID);
+    config.transmission_limit = 2;
+    let mut fop = Fop::new(config, TcFrameConfig::new(SPACECRAFT_ID));
+    fop.initiate().unwrap();
+
+    fop.transfer(&[0], seconds(10)).unwrap();
+    let frame = fop.pull_frame().unwrap();
+
+    // nothing happens until the timer runs out, then the frame is sent again
+    fop.tick(seconds(14)).unwrap();
+    assert_eq!(fop.num_frames(), 0);
+    fop.tick(seconds(15)).unwrap();
+    assert_eq!(fop.pull_frame(), Some(frame));
+    assert_eq!(fop.state(), FopState::RetransmitWithoutWait);
+    assert_eq!(fop.timer(), Some(seconds(20)));
+
+    // once the frame has been sent as many times as allowed, the service stops
+    fop.tick(seconds(20)).unwrap();
+    assert_eq!(fop.pull_alert(), Some(FopAlert::Timer));
+    assert_eq!(fop.state(), FopState::Initial);
+    assert_eq!(fop.num_unacknowledged(), 0);
+    assert_eq!(fop.timer(), None);
+}
+
+#[test]
+fn test_cop1_fop_lockout_and_unlock() {
+    let mut fop = Fop::new(FopConfig::new(VCID), TcFrameConfig::new(SPACECRAFT_ID));
+    let mut farm = Farm::new(VCID, 10);
+    fop.encoder.set_next_sequence_number(VCID, 100);
+    fop.initiate().unwrap();
+
+    // frames far outside the FARM's window lock it out, which stops the FOP
+    fop.transfer(&[0], seconds(0)).unwrap();
+    assert!(deliver(&mut fop, &mut farm, |_| false).is_empty());
+    assert_eq!(farm.state(), FarmState::Lockout);
+
+    fop.recv_clcw(&farm.clcw(), seconds(1)).unwrap();
+    assert_eq!(fop.pull_alert(), Some(FopAlert::Lockout));
+    assert_eq!(fop.state(), FopState::Initial);
+
+    // an Unlock does not change V(R), so a Set V(R) is needed to resynchronize
+    fop.initiate_with_unlock(seconds(2)).unwrap();
+    assert_eq!(fop.state(), FopState::InitializingWithBc);
+    deliver(&mut fop, &mut farm, |_| false);
+    assert_eq!(farm.state(), FarmState::Open);
+    fop.recv_clcw(&farm.clcw(), seconds(3)).unwrap();
+    assert_eq!(fop.state(), FopState::InitializingWithBc);
+    fop.terminate();
+    assert_eq!(fop.pull_alert(), Some(FopAlert::Terminated));
+
+    fop.initiate_with_set_vr(7, seconds(4)).unwrap();
+    fop.recv_clcw(&farm.clcw(), seconds(4)).unwrap();
+    assert_eq!(fop.state(), FopState::InitializingWithBc);
+
+    // a report from before the command is ignored, and the lost command is sent
+    // again when the timer runs out
+    fop.pull_frame().unwrap();
+    fop.tick(seconds(9)).unwrap();
+    deliver(&mut fop, &mut farm, |_| false);
+    assert_eq!(farm.next_sequence_number(), 7);
+    fop.recv_clcw(&farm.clcw(), seconds(10)).unwrap();
+    assert_eq!(fop.state(), FopState::Active);
+
+    fop.transfer(&[1], seconds(11)).unwrap();
+    assert_eq!(deliver(&mut fop, &mut farm, |_| false), vec![vec![1]]);
+
+    fop.terminate();
+    assert_eq!(fop.pull_alert(), Some(FopAlert::Terminated));
+}
@@ -0,0 +1,140 @@
+extern crate ccsds_primary_header;
+
+use std::time::Duration;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::time::*;
+
+
+#[test]
+fn test_cuc_decode_implicit() {
+    let format = CucFormat::new(4, 2);
+    let bytes = [0x12, 0x34, 0x56, 0x78, 0x80, 0x00, 0xFF];
+
+    let (time, len) = format.decode(&bytes).unwrap();
+    assert_eq!(len, 6);
+    assert_eq!(time, CucTime { coarse: 0x12345678, fine: 0x80000000 });
+    assert_eq!(format.to_duration(&time), Duration::new(0x12345678, 500_000_000));
+}
+
+#[test]
+fn test_cuc_decode_explicit() {
+    let mut format = CucFormat::new(4, 2);
+    format.pfield = PField::Explicit;
+
+    // level 1, 2 coarse octets, 1 fine octet
+    let bytes = [0x15, 0x01, 0x02, 0x40];
+    let (time, len) = format.decode(&bytes).unwrap();
+    assert_eq!(len, 4);
+    assert_eq!(time, CucTime { coarse: 0x0102, fine: 0x40000000 });
+}
+
+#[test]
+fn test_cuc_invalid_pfield() {
+    let mut format = CucFormat::new(4, 2);
+    format.pfield = PField::Explicit;
+
+    assert_eq!(format.decode(&[0x95, 0x01, 0x02, 0x40]), Err(TimeError::InvalidPField(0x95)));
+    assert_eq!(format.decode(&[0x45, 0x01, 0x02, 0x40]), Err(TimeError::InvalidPField(0x45)));
+    assert_eq!(format.decode(&[]), Err(TimeError::NotEnoughBytes));
+}
+
+#[test]
+fn test_cuc_pfield_byte() {
+    assert_eq!(CucFormat::new(4, 2).pfield_byte(), 0x1E);
+    assert_eq!(CucFormat::new(1, 0).pfield_byte(), 0x10);
+    assert_eq!(CucFormat::with_epoch(4, 3, UNIX_EPOCH).pfield_byte(), 0x2F);
+}
+
+#[test]
+fn test_cuc_from_pfield_byte() {
+    // time code ID 001 is the CCSDS epoch, whatever agency epoch is given
+    let format = CucFormat::new(4, 2);
+    let decoded = CucFormat::from_pfield_byte(format.pfield_byte(), UNIX_EPOCH).unwrap();
+    assert_eq!(decoded.epoch, CCSDS_EPOCH);
+    assert_eq!(decoded.pfield_byte(), format.pfield_byte());
+
+    // time code ID 010 uses the agency epoch
+    let format = CucFormat::with_epoch(4, 3, UNIX_EPOCH);
+    let decoded = CucFormat::from_pfield_byte(format.pfield_byte(), UNIX_EPOCH).unwrap();
+    assert_eq!(decoded.epoch, UNIX_EPOCH);
+    assert_eq!(decoded.pfield_byte(), format.pfield_byte());
+    assert_eq!((decoded.coarse_octets, decoded.fine_octets), (4, 3));
+
+    assert_eq!(CucFormat::from_pfield_byte(0x3E, UNIX_EPOCH), Err(TimeError::InvalidPField(0x3E)));
+}
+
+#[test]
+fn test_cuc_encode() {
+    let mut format = CucFormat::new(3, 1);
+    let time = CucTime { coarse: 0x010203, fine: 0xABCDEF01 };
+
+    assert_eq!(format.encode_to_vec(&time), Ok(vec!(0x01, 0x02, 0x03, 0xAB)));
+
+    format.pfield = PField::Explicit;
+    assert_eq!(format.encode_to_vec(&time), Ok(vec!(0x19, 0x01, 0x02, 0x03, 0xAB)));
+
+    let time = CucTime { coarse: 0x01000000, fine: 0 };
+    assert_eq!(format.encode_to_vec(&time), Err(TimeError::OutOfRange));
+
+    let mut bytes = [0; 2];
+    assert_eq!(format.encode(&time, &mut bytes), Err(TimeError::NotEnoughBytes));
+}
+
+#[test]
+fn test_cuc_invalid_format() {
+    assert_eq!(CucFormat::new(0, 2).decode(&[0; 8]), Err(TimeError::InvalidFormat));
+    assert_eq!(CucFormat::new(5, 0).encode_to_vec(&Default::default()), Err(TimeError::InvalidFormat));
+    assert_eq!(CucFormat::new(4, 5).time_from_duration(Duration::new(1, 0)), Err(TimeError::InvalidFormat));
+    assert_eq!(CucFormat::new(0, 2).time_from_duration(Duration::new(1, 0)), Err(TimeError::InvalidFormat));
+
+    // the field sizes of an invalid format are masked rather than overflowing
+    assert_eq!(CucFormat::new(0, 2).pfield_byte(), 0x1E);
+    assert_eq!(CucFormat::new(4, 5).pfield_byte(), 0x1D);
+}
+
+#[test]
+fn test_cuc_duration_round_trip() {
+    let format = CucFormat::new(4, 3);
+    let duration = Duration::new(1_000_000, 123_456_000);
+
+    let time = format.time_from_duration(duration).unwrap();
+    let back = format.to_duration(&time);
+
+    // 3 fine octets have a resolution of about 60 nanoseconds
+    let difference = if back > duration { back - duration } else { duration - back };
+    assert!(difference < Duration::new(0, 60));
+}
+
+#[test]
+fn test_cuc_from_duration_out_of_range() {
+    let format = CucFormat::new(1, 0);
+    assert_eq!(format.time_from_duration(Duration::new(255, 0)), Ok(CucTime { coarse: 255, fine: 0 }));
+    assert_eq!(format.time_from_duration(Duration::new(256, 0)), Err(TimeError::OutOfRange));
+}
+
+#[test]
+fn test_cuc_epoch() {
+    assert_eq!(Epoch::from_date(1958, 1, 1), CCSDS_EPOCH);
+    assert_eq!(Epoch::from_date(1970, 1, 1), UNIX_EPOCH);
+    // the GPS epoch is on the TAI based scale, 19 seconds ahead of UTC
+    assert_eq!(Epoch(Epoch::from_date(1980, 1, 6).0 + 19), GPS_EPOCH);
+
+    let format = CucFormat::with_epoch(4, 0, GPS_EPOCH);
+    assert_eq!(format.epoch(), GPS_EPOCH);
+    assert_eq!(format.time_code_id(), CUC_TIME_CODE_ID_AGENCY_EPOCH);
+}
+
+#[test]
+fn test_cuc_decode_from_packet() {
+    let format = CucFormat::new(4, 2);
+    let mut packet = vec!(0x08, 0x10, 0xC0, 0x00, 0x00, 0x07);
+    packet.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x40, 0x00, 0xAA, 0xBB]);
+
+    assert_eq!(format.decode_from_packet(&packet), Ok(CucTime { coarse: 0x100, fine: 0x40000000 }));
+
+    let mut pri_header = PrimaryHeader::from_slice(&packet).unwrap();
+    pri_header.control.set_secondary_header_flag(SecondaryHeaderFlag::NotPresent);
+    packet[0..2].copy_from_slice(&pri_header.control.0);
+    assert_eq!(format.decode_from_packet(&packet), Err(TimeError::NoSecondaryHeader));
+}
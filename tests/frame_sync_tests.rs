@@ -0,0 +1,193 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::frame_sync::*;
+
+
+const FRAME_LENGTH: usize = 16;
+
+fn make_frame(index: u8) -> Vec<u8> {
+    (0..FRAME_LENGTH as u8).map(|byte| byte.wrapping_mul(31) ^ index).collect()
+}
+
+/// A stream of some junk followed by ASMs and frames.
+fn make_stream(num_frames: u8) -> Vec<u8> {
+    let mut stream = vec![0x12, 0x34, 0x56];
+    for index in 0..num_frames {
+        stream.extend_from_slice(&CCSDS_ASM);
+        stream.extend_from_slice(&make_frame(index));
+    }
+    stream
+}
+
+/// Delay a stream by a number of bits, from 0 to 7.
+fn shift_bits(stream: &[u8], shift: usize) -> Vec<u8> {
+    let mut shifted = Vec::with_capacity(stream.len() + 1);
+    let mut carry = 0u8;
+    for byte in stream.iter() {
+        shifted.push(carry | (byte >> shift));
+        carry = if shift == 0 { 0 } else { byte << (8 - shift) };
+    }
+    shifted.push(carry);
+    shifted
+}
+
+/// Remove a single bit from a stream, as a receiver might when it slips.
+fn remove_bit(stream: &[u8], bit: usize) -> Vec<u8> {
+    let bits: Vec<u8> = (0..stream.len() * 8)
+        .filter(|index| *index != bit)
+        .map(|index| (stream[index / 8] >> (7 - index % 8)) & 1)
+        .collect();
+
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0, |byte, (index, bit)| byte | (bit << (7 - index))))
+        .collect()
+}
+
+fn asm_offset(index: usize) -> usize {
+    3 + index * (CCSDS_ASM.len() + FRAME_LENGTH)
+}
+
+#[test]
+fn test_frame_sync_aligned() {
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    assert_eq!(sync.state(), SyncState::Search);
+
+    sync.recv_slice(&make_stream(3));
+    assert_eq!(sync.num_frames(), 3);
+    assert_eq!(sync.state(), SyncState::Lock);
+    assert!(!sync.is_inverted());
+
+    for index in 0..3 {
+        assert_eq!(sync.pull_frame(), Some(make_frame(index).as_slice().into()));
+    }
+    assert_eq!(sync.pull_frame(), None);
+
+    let mut config = FrameSyncConfig::new(FRAME_LENGTH);
+    config.keep_asm = true;
+    let mut sync = FrameSync::new(config);
+    sync.recv_slice(&make_stream(2));
+    assert_eq!(&sync.pull_frame().unwrap()[0..4], &CCSDS_ASM[..]);
+}
+
+#[test]
+fn test_frame_sync_shifted_inverted_with_errors() {
+    let mut stream = make_stream(4);
+
+    // two bit errors in each marker, within the search tolerance
+    for index in 0..4 {
+        stream[asm_offset(index)] ^= 0x81;
+    }
+
+    let stream: Vec<u8> = shift_bits(&stream, 3).iter().map(|byte| !byte).collect();
+
+    // bytes arriving one at a time give the same frames
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    for byte in stream.iter() {
+        sync.recv_slice(&[*byte]);
+    }
+
+    assert!(sync.is_inverted());
+    assert_eq!(sync.asm_bit_errors, 8);
+    assert_eq!(sync.num_frames(), 4);
+    for index in 0..4 {
+        assert_eq!(sync.pull_frame(), Some(make_frame(index).as_slice().into()));
+    }
+
+    let mut config = FrameSyncConfig::new(FRAME_LENGTH);
+    config.detect_inversion = false;
+    let mut sync = FrameSync::new(config);
+    sync.recv_slice(&stream);
+    assert_eq!(sync.num_frames(), 0);
+}
+
+#[test]
+fn test_frame_sync_flywheel() {
+    let mut stream = make_stream(10);
+
+    // a single missed marker is flywheeled over
+    stream[asm_offset(3)] ^= 0xFF;
+
+    // three missed markers in a row lose the lock
+    for index in 5..8 {
+        stream[asm_offset(index) + 1] ^= 0xFF;
+    }
+
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    // a missed marker waits for the bits of a marker which might have slipped forward
+    sync.recv_slice(&stream[0..asm_offset(4)]);
+    assert_eq!(sync.state(), SyncState::Lock);
+    sync.recv_slice(&stream[asm_offset(4)..asm_offset(4) + 1]);
+    assert_eq!(sync.state(), SyncState::Flywheel);
+
+    sync.recv_slice(&stream[asm_offset(4) + 1..]);
+    assert_eq!(sync.sync_losses, 1);
+
+    // frames 0 to 6 are output, through two flywheeled markers, and the lock is lost
+    // at frame 7. The search finds frame 8 again.
+    let frames: Vec<Vec<u8>> = (0..sync.num_frames()).map(|_| sync.pull_frame().unwrap().to_vec()).collect();
+    let expected: Vec<Vec<u8>> = [0, 1, 2, 3, 4, 5, 6, 8, 9].iter().map(|index| make_frame(*index)).collect();
+    assert_eq!(frames, expected);
+    assert_eq!(sync.state(), SyncState::Lock);
+}
+
+#[test]
+fn test_frame_sync_bit_slip() {
+    // a bit is lost in the middle of frame 2, so later markers come one bit early
+    let stream = make_stream(5);
+    let slipped = remove_bit(&stream, (asm_offset(2) + 10) * 8);
+
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    sync.recv_slice(&slipped);
+
+    assert_eq!(sync.bit_slips, 1);
+    assert_eq!(sync.sync_losses, 0);
+    assert_eq!(sync.num_frames(), 5);
+
+    for index in 0..5 {
+        let frame = sync.pull_frame().unwrap();
+        if index != 2 {
+            assert_eq!(frame.to_vec(), make_frame(index));
+        }
+    }
+}
+
+#[test]
+fn test_frame_sync_check_state() {
+    let mut config = FrameSyncConfig::new(FRAME_LENGTH);
+    config.check_frames = 2;
+
+    let mut sync = FrameSync::new(config);
+    let stream = make_stream(3);
+    sync.recv_slice(&stream[0..asm_offset(2)]);
+    assert_eq!(sync.state(), SyncState::Check);
+
+    sync.recv_slice(&stream[asm_offset(2)..]);
+    assert_eq!(sync.state(), SyncState::Lock);
+
+    // a marker found by chance which is not repeated returns to the search
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    let mut false_start = CCSDS_ASM.to_vec();
+    false_start.extend_from_slice(&[0x00; 2 * FRAME_LENGTH + 4]);
+    sync.recv_slice(&false_start);
+    assert_eq!(sync.state(), SyncState::Search);
+    assert_eq!(sync.sync_losses, 0);
+    assert_eq!(sync.num_frames(), 0);
+}
+
+#[test]
+fn test_frame_sync_false_marker() {
+    // a marker found by chance just before the real stream, so the real marker is
+    // inside the frame which would follow it
+    let mut stream = CCSDS_ASM.to_vec();
+    stream.extend_from_slice(&[0x00; 2]);
+    stream.extend_from_slice(&make_stream(3));
+
+    let mut sync = FrameSync::new(FrameSyncConfig::new(FRAME_LENGTH));
+    sync.recv_slice(&stream);
+
+    assert_eq!(sync.state(), SyncState::Lock);
+    assert_eq!(sync.num_frames(), 3);
+    for index in 0..3 {
+        assert_eq!(sync.pull_frame(), Some(make_frame(index).as_slice().into()));
+    }
+}
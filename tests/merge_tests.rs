@@ -0,0 +1,189 @@
+extern crate bytes;
+extern crate ccsds_primary_header;
+
+mod common;
+
+use bytes::BytesMut;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::parser::*;
+use ccsds_primary_header::merge::*;
+use common::packet_header;
+
+
+fn make_packet(apid: u16, sequence_count: u16, data: u8) -> BytesMut {
+    let mut pri_header = packet_header(PacketType::Data, apid, false, 2);
+    pri_header.sequence.set_sequence_count(sequence_count);
+
+    let mut packet = BytesMut::new();
+    packet.extend_from_slice(&pri_header.to_bytes());
+    packet.extend_from_slice(&[data, data]);
+    packet
+}
+
+fn sequence_counts(merger: &mut PacketMerger) -> Vec<u16> {
+    let mut counts = Vec::new();
+    while let Some(merged) = merger.pull_packet() {
+        counts.push(PrimaryHeader::from_slice(&merged.packet).unwrap().sequence.sequence_count());
+    }
+    counts
+}
+
+#[test]
+fn test_merge_drops_duplicates() {
+    let mut merger = PacketMerger::new(4);
+
+    merger.recv_packet(0, make_packet(1, 0, 0));
+    merger.recv_packet(1, make_packet(1, 0, 0));
+    merger.recv_packet(1, make_packet(1, 1, 1));
+    merger.recv_packet(0, make_packet(1, 1, 1));
+    merger.flush();
+
+    let first = merger.pull_packet().unwrap();
+    assert_eq!(first.station, 0);
+    assert_eq!(first.packet, make_packet(1, 0, 0));
+
+    let second = merger.pull_packet().unwrap();
+    assert_eq!(second.station, 1);
+    assert_eq!(second.packet, make_packet(1, 1, 1));
+
+    assert_eq!(merger.pull_packet(), None);
+    assert_eq!(merger.duplicate_packets, 2);
+}
+
+#[test]
+fn test_merge_different_contents_kept() {
+    let mut merger = PacketMerger::new(4);
+
+    merger.recv_packet(0, make_packet(1, 0, 0));
+    merger.recv_packet(0, make_packet(1, 2, 0));
+    merger.recv_packet(1, make_packet(1, 2, 0xFF));
+    merger.recv_packet(1, make_packet(1, 1, 0));
+    merger.flush();
+
+    assert_eq!(sequence_counts(&mut merger), vec!(0, 1, 2, 2));
+    assert_eq!(merger.duplicate_packets, 0);
+
+    // a different copy of an already emitted packet can no longer be placed in order
+    merger.recv_packet(1, make_packet(1, 0, 0xFF));
+    assert_eq!(sequence_counts(&mut merger), Vec::<u16>::new());
+    assert_eq!(merger.late_packets, 1);
+}
+
+#[test]
+fn test_merge_reorders() {
+    let mut merger = PacketMerger::new(2);
+
+    merger.recv_packet(0, make_packet(1, 0, 0));
+    merger.recv_packet(0, make_packet(1, 2, 0));
+    merger.recv_packet(0, make_packet(1, 3, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(0));
+
+    // the second station fills in the gap
+    merger.recv_packet(1, make_packet(1, 1, 0));
+    merger.recv_packet(1, make_packet(1, 2, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(1, 2, 3));
+    assert_eq!(merger.duplicate_packets, 1);
+}
+
+#[test]
+fn test_merge_starts_at_earliest() {
+    let mut merger = PacketMerger::new(2);
+
+    // the first station to deliver a packet is ahead of the other
+    merger.recv_packet(0, make_packet(1, 10, 0));
+    merger.recv_packet(1, make_packet(1, 9, 0));
+    assert_eq!(sequence_counts(&mut merger), Vec::<u16>::new());
+
+    merger.recv_packet(0, make_packet(1, 11, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(9, 10, 11));
+    assert_eq!(merger.late_packets, 0);
+}
+
+#[test]
+fn test_merge_window_full() {
+    let mut merger = PacketMerger::new(2);
+
+    merger.recv_packet(0, make_packet(1, 0, 0));
+    merger.recv_packet(0, make_packet(1, 2, 0));
+    merger.recv_packet(0, make_packet(1, 3, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(0));
+
+    // the window overflows, so sequence count 1 is considered lost
+    merger.recv_packet(0, make_packet(1, 4, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(2, 3, 4));
+
+    merger.recv_packet(1, make_packet(1, 1, 0));
+    assert_eq!(sequence_counts(&mut merger), Vec::<u16>::new());
+    assert_eq!(merger.late_packets, 1);
+}
+
+#[test]
+fn test_merge_sequence_reset() {
+    let mut merger = PacketMerger::new(2);
+
+    for count in 4998..5002 {
+        merger.recv_packet(0, make_packet(1, count, 0));
+    }
+    assert_eq!(sequence_counts(&mut merger), vec!(4998, 4999, 5000, 5001));
+
+    // the spacecraft reboots, and its sequence counts start again from 0
+    merger.recv_packet(0, make_packet(1, 0, 0));
+    merger.recv_packet(1, make_packet(1, 0, 0));
+    merger.recv_packet(0, make_packet(1, 1, 0));
+    assert_eq!(sequence_counts(&mut merger), Vec::<u16>::new());
+    assert_eq!(merger.late_packets, 2);
+
+    merger.recv_packet(0, make_packet(1, 2, 0));
+    merger.recv_packet(0, make_packet(1, 3, 0));
+    assert_eq!(sequence_counts(&mut merger), vec!(0, 1, 2, 3));
+    assert_eq!(merger.late_packets, 0);
+    assert_eq!(merger.duplicate_packets, 1);
+    assert_eq!(merger.stream_resets, 1);
+}
+
+#[test]
+fn test_merge_per_apid() {
+    let mut merger = PacketMerger::new(1);
+
+    merger.recv_packet(0, make_packet(1, 10, 0));
+    merger.recv_packet(0, make_packet(2, 100, 0));
+    merger.recv_packet(1, make_packet(2, 102, 0));
+    merger.recv_packet(1, make_packet(1, 11, 0));
+
+    assert_eq!(sequence_counts(&mut merger), vec!(100, 10, 11));
+
+    merger.flush();
+    assert_eq!(sequence_counts(&mut merger), vec!(102));
+}
+
+#[test]
+fn test_merge_sequence_wrap() {
+    let mut merger = PacketMerger::new(4);
+
+    merger.recv_packet(0, make_packet(1, 0x3FFE, 0));
+    merger.recv_packet(0, make_packet(1, 0x0000, 0));
+    merger.recv_packet(1, make_packet(1, 0x3FFF, 0));
+    merger.recv_packet(1, make_packet(1, 0x0001, 0));
+    merger.flush();
+
+    assert_eq!(sequence_counts(&mut merger), vec!(0x3FFE, 0x3FFF, 0x0000, 0x0001));
+}
+
+#[test]
+fn test_merge_from_parsers() {
+    let mut parsers = vec!(CcsdsParser::new(), CcsdsParser::new());
+    parsers[0].recv_slice(&make_packet(5, 0, 1));
+    parsers[0].recv_slice(&make_packet(5, 1, 2));
+    parsers[1].recv_slice(&make_packet(5, 0, 1));
+    parsers[1].recv_slice(&make_packet(5, 2, 3));
+
+    let mut merger = PacketMerger::new(4);
+    merger.recv_from_parsers(&mut parsers);
+    merger.flush();
+
+    let stations: Vec<StationId> =
+        (0..3).map(|_| merger.pull_packet().unwrap().station).collect();
+    assert_eq!(stations, vec!(0, 0, 1));
+    assert_eq!(merger.duplicate_packets, 1);
+}
@@ -3,6 +3,7 @@ extern crate ccsds_primary_header;
 
 use bytes::BytesMut;
 
+use ccsds_primary_header::primary_header::*;
 use ccsds_primary_header::parser::*;
 
 
@@ -209,3 +210,121 @@ fn test_ccsds_parser_iterator() {
         assert_eq!(packet.unwrap(), bytes);
     }
 }
+
+#[test]
+fn test_ccsds_parser_validator_reject() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+
+    parser.add_validator(|pri_header, packet| {
+        if pri_header.control.apid() == 0x03 && packet.len() != 256 {
+            Err(42)
+        } else {
+            Ok(())
+        }
+    });
+
+    assert_eq!(parser.current_status(), CcsdsParserStatus::RejectedByValidator(42));
+    assert_eq!(parser.pull_packet(), None);
+}
+
+#[test]
+fn test_ccsds_parser_validator_sees_packet() {
+    let mut parser = CcsdsParser::new();
+    parser.config.sync_bytes.push(0xEB);
+    parser.config.sync_bytes.push(0x90);
+    parser.config.num_footer_bytes = 2;
+    parser.recv_slice(&[0xEB, 0x90, 0x00,0x3,0xFF,0xFF,0x00,0x01,0x12,0x34, 0xAB, 0xCD]);
+
+    parser.add_validator(|_, packet| {
+        if packet == [0x00,0x3,0xFF,0xFF,0x00,0x01,0x12,0x34] {
+            Ok(())
+        } else {
+            Err(1)
+        }
+    });
+
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+    assert_ne!(parser.pull_packet(), None);
+}
+
+#[test]
+fn test_ccsds_parser_validator_order() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x18,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+
+    parser.add_validator(|_, _| Ok(()));
+    parser.add_validator(|pri_header, _| {
+        if pri_header.control.packet_type() == PacketType::Command &&
+           pri_header.control.secondary_header_flag() != SecondaryHeaderFlag::Present {
+            Err(1)
+        } else {
+            Ok(())
+        }
+    });
+    parser.add_validator(|_, _| Err(2));
+
+    assert_eq!(parser.current_status(), CcsdsParserStatus::RejectedByValidator(2));
+
+    parser.validators.pop();
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+}
+
+#[test]
+fn test_ccsds_parser_apid_exact_length() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+
+    parser.expect_length(0x03, ExpectedLength::Exact(9));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::PacketLengthMismatch);
+    assert_eq!(parser.pull_packet(), None);
+
+    parser.bytes.clear();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0xFF,0xFF]);
+    parser.expect_length(0x03, ExpectedLength::Exact(8));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+}
+
+#[test]
+fn test_ccsds_parser_apid_length_range() {
+    let mut parser = CcsdsParser::new();
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x03,0xFF,0xFF,0xFF,0xFF]);
+
+    parser.expect_length(0x03, ExpectedLength::Range { min: 7, max: 9 });
+    assert_eq!(parser.current_status(), CcsdsParserStatus::PacketLengthMismatch);
+
+    parser.expect_length(0x03, ExpectedLength::Range { min: 10, max: 20 });
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+}
+
+#[test]
+fn test_ccsds_parser_apid_length_overrides_max() {
+    let mut parser = CcsdsParser::new();
+    parser.config.max_packet_length = Some(8);
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x03,0xFF,0xFF,0xFF,0xFF]);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ExceedsMaxPacketLength);
+
+    parser.expect_length(0x03, ExpectedLength::Exact(10));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    // other APIDs still use the max packet length
+    parser.bytes.clear();
+    parser.recv_slice(&[0x00,0x4,0xFF,0xFF,0x00,0x03,0xFF,0xFF,0xFF,0xFF]);
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ExceedsMaxPacketLength);
+}
+
+#[test]
+fn test_ccsds_parser_apid_length_resync() {
+    let mut parser = CcsdsParser::new();
+    parser.config.max_packet_length = Some(64);
+    parser.allow_apid(0x03);
+    parser.expect_length(0x03, ExpectedLength::Exact(8));
+
+    // garbage that claims a long packet with an allowed APID is skipped rather than waited on
+    parser.recv_slice(&[0x00, 0x03, 0xC0, 0x00, 0x10, 0xFF]);
+    parser.recv_slice(&[0x00,0x3,0xFF,0xFF,0x00,0x01,0x12,0x34]);
+
+    let packet = parser.pull_packet();
+    assert_eq!(packet.unwrap().len(), 8);
+    assert_eq!(parser.skipped_bytes, 6);
+}
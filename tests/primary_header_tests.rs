@@ -3,8 +3,6 @@ extern crate rand;
 extern crate quickcheck;
 extern crate byteorder;
 
-use std::marker::PhantomData;
-
 use quickcheck::*;
 
 use rand::{Rand};
@@ -75,6 +73,26 @@ mod tests {
         assert!(std::mem::size_of::<PrimaryHeader>() == CCSDS_PRI_HEADER_SIZE_BYTES as usize);
     }
 
+    #[test]
+    fn test_ccsds_set_packet_length_clamps() {
+        let mut pri_header: PrimaryHeader = Default::default();
+        pri_header.length.set_length_field(0x1234);
+
+        for length in 0..CCSDS_MIN_LENGTH as u16 {
+            pri_header.set_packet_length(length);
+            assert!(pri_header.length.length_field() == 0);
+            assert!(pri_header.packet_length() == CCSDS_MIN_LENGTH);
+        }
+    }
+
+    #[test]
+    fn test_ccsds_header_to_bytes() {
+        let bytes = [0x17, 0xFF, 0x3F, 0xFE, 0x01, 0x02];
+        let pri_header = PrimaryHeader::new(bytes);
+        assert!(pri_header.to_bytes() == bytes);
+        assert!(PrimaryHeader::from_slice(&pri_header.to_bytes()) == Some(pri_header));
+    }
+
     #[test]
     fn test_ccsds_header_from_slice() {
         assert!(PrimaryHeader::from_slice(&[0]) == None);
@@ -167,5 +185,16 @@ mod tests {
 
             return pri_header.length.length_field() == length;
         }
+
+        fn test_ccsds_packet_length_get_set(length: u16) -> bool {
+            let length = length.max(CCSDS_MIN_LENGTH as u16);
+
+            let mut pri_header: PrimaryHeader = Default::default();
+
+            pri_header.set_packet_length(length);
+
+            return pri_header.packet_length() == length as u32 &&
+                   pri_header.length.length_field() == length - CCSDS_MIN_LENGTH as u16;
+        }
     }
 }
@@ -0,0 +1,132 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::pus::*;
+use common::packet_header;
+
+
+fn make_packet(packet_type: PacketType, data: &[u8], with_pec: bool) -> Vec<u8> {
+    let pec_len = if with_pec { PUS_PEC_BYTES } else { 0 };
+
+    let mut pri_header = packet_header(packet_type, 0x33, true, data.len() + pec_len);
+    pri_header.sequence.set_sequence_count(9);
+
+    let mut packet = pri_header.to_bytes().to_vec();
+    packet.extend_from_slice(data);
+    if with_pec {
+        append_pec(&mut packet);
+    }
+    packet
+}
+
+#[test]
+fn test_pus_a_tm_header() {
+    let config = PusATmConfig::new(CucFormat::new(4, 0));
+    assert_eq!(config.header_len(), 8);
+
+    let bytes = [0x10, 0x03, 0x19, 0x42, 0x00, 0x00, 0x01, 0x00];
+    let header = PusATmHeader::decode(&config, &bytes).unwrap();
+    assert_eq!(header.version, PUS_A_VERSION);
+    assert_eq!(header.service_type, 3);
+    assert_eq!(header.message_subtype, 25);
+    assert_eq!(header.packet_subcounter, 0x42);
+    assert_eq!(header.time, CucTime { coarse: 0x100, fine: 0 });
+
+    let mut encoded = [0xFF; 8];
+    assert_eq!(header.encode(&config, &mut encoded), Ok(8));
+    assert_eq!(encoded, bytes);
+
+    assert_eq!(PusATmHeader::decode(&config, &[0x20, 0x03, 0x19, 0x42, 0, 0, 1, 0]), Err(PusError::InvalidVersion(2)));
+}
+
+#[test]
+fn test_pus_a_tc_header() {
+    let config = PusATcConfig::new();
+    let bytes = [0x19, 0x11, 0x01, 0x07];
+    let header = PusATcHeader::decode(&config, &bytes).unwrap();
+    assert_eq!(header.ack_flags, AckFlags::from_bits(0x09));
+    assert_eq!(header.source_id, 7);
+
+    let mut encoded = [0xFF; 4];
+    assert_eq!(header.encode(&config, &mut encoded), Ok(4));
+    assert_eq!(encoded, bytes);
+}
+
+#[test]
+fn test_pus_a_tm_to_c() {
+    let a_config = PusATmConfig::new(CucFormat::new(4, 0));
+    let c_config = PusTmConfig::new(CucFormat::new(4, 0));
+
+    let packet = make_packet(PacketType::Data, &[0x10, 0x05, 0x01, 0x42, 0x00, 0x00, 0x01, 0x00, 0xAA, 0xBB], true);
+    let converted = pus_a_tm_to_c(&packet, &a_config, &c_config, true).unwrap();
+
+    let pri_header = PrimaryHeader::from_slice(&converted).unwrap();
+    assert_eq!(pri_header.packet_length() as usize, converted.len());
+    assert_eq!(converted.len(), 6 + 11 + 2 + 2);
+    assert_eq!(pri_header.control.apid(), 0x33);
+    assert_eq!(pri_header.sequence.sequence_count(), 9);
+    assert!(has_valid_pec(&converted));
+
+    let header = PusTmHeader::from_packet(&c_config, &converted).unwrap();
+    assert_eq!(header.version, PUS_C_VERSION);
+    assert_eq!(header.service_type, 5);
+    assert_eq!(header.message_subtype, 1);
+    assert_eq!(header.message_type_counter, 0x42);
+    assert_eq!(header.destination_id, 0);
+    assert_eq!(header.time, CucTime { coarse: 0x100, fine: 0 });
+    assert_eq!(pus_tm_user_data(&c_config, &converted, true), Ok(&[0xAA, 0xBB][..]));
+
+    // and back again
+    assert_eq!(pus_c_tm_to_a(&converted, &c_config, &a_config, true), Ok(packet.clone()));
+
+    let mut corrupted = packet.clone();
+    corrupted[14] ^= 0x01;
+    assert_eq!(pus_a_tm_to_c(&corrupted, &a_config, &c_config, true), Err(PusError::InvalidPec));
+}
+
+#[test]
+fn test_pus_c_tm_to_a_not_representable() {
+    let a_config = PusATmConfig::new(CucFormat::new(4, 0));
+    let c_config = PusTmConfig::new(CucFormat::new(4, 0));
+
+    // time reference status of 3
+    let packet = make_packet(PacketType::Data, &[0x23, 0x05, 0x01, 0x00, 0x01, 0x00, 0x00, 0, 0, 0, 0], false);
+    assert_eq!(pus_c_tm_to_a(&packet, &c_config, &a_config, false),
+               Err(PusError::NotRepresentable(PusField::TimeReferenceStatus)));
+
+    // message type counter too large for the subcounter
+    let packet = make_packet(PacketType::Data, &[0x20, 0x05, 0x01, 0x01, 0x00, 0x00, 0x00, 0, 0, 0, 0], false);
+    assert_eq!(pus_c_tm_to_a(&packet, &c_config, &a_config, false),
+               Err(PusError::NotRepresentable(PusField::MessageTypeCounter)));
+
+    // a destination ID with no destination ID field
+    let packet = make_packet(PacketType::Data, &[0x20, 0x05, 0x01, 0x00, 0x01, 0x00, 0x02, 0, 0, 0, 0], false);
+    assert_eq!(pus_c_tm_to_a(&packet, &c_config, &a_config, false),
+               Err(PusError::NotRepresentable(PusField::DestinationId)));
+
+    let packet = make_packet(PacketType::Command, &[0x20, 0x05, 0x01, 0x00, 0x01, 0x00, 0x00, 0, 0, 0, 0], false);
+    assert_eq!(pus_c_tm_to_a(&packet, &c_config, &a_config, false), Err(PusError::WrongPacketType));
+}
+
+#[test]
+fn test_pus_tc_conversion() {
+    let a_config = PusATcConfig::new();
+    let c_config = PusTcConfig::new();
+
+    let packet = build_pus_tc(&c_config, 0x33, 9, &PusTcHeader { source_id: 0x12, .. PusTcHeader::new(17, 1) }, &[0x01]).unwrap();
+    let converted = pus_c_tc_to_a(&packet, &c_config, &a_config, true).unwrap();
+
+    assert_eq!(converted.len(), packet.len() - 1);
+    assert_eq!(PrimaryHeader::from_slice(&converted).unwrap().packet_length() as usize, converted.len());
+    assert!(has_valid_pec(&converted));
+    assert_eq!(&converted[6..11], &[0x1F, 0x11, 0x01, 0x12, 0x01]);
+
+    assert_eq!(pus_a_tc_to_c(&converted, &a_config, &c_config, true), Ok(packet.clone()));
+
+    let packet = build_pus_tc(&c_config, 0x33, 9, &PusTcHeader { source_id: 0x123, .. PusTcHeader::new(17, 1) }, &[]).unwrap();
+    assert_eq!(pus_c_tc_to_a(&packet, &c_config, &a_config, true),
+               Err(PusError::NotRepresentable(PusField::SourceId)));
+}
@@ -0,0 +1,133 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::pus::*;
+use common::packet_header;
+
+
+fn make_tm_packet(service_type: u8, message_subtype: u8, user_data: &[u8], with_pec: bool) -> Vec<u8> {
+    let config = PusTmConfig::new(CucFormat::new(4, 0));
+    let tm_header: PusTmHeader<CucFormat> =
+        PusTmHeader::new(service_type, message_subtype, CucTime { coarse: 100, fine: 0 });
+
+    let pec_len = if with_pec { PUS_PEC_BYTES } else { 0 };
+
+    let pri_header = packet_header(PacketType::Data, 0x01, true, config.header_len() + user_data.len() + pec_len);
+
+    let mut packet = pri_header.to_bytes().to_vec();
+    packet.resize(6 + config.header_len(), 0);
+    tm_header.encode(&config, &mut packet[6..]).unwrap();
+    packet.extend_from_slice(user_data);
+    if with_pec {
+        append_pec(&mut packet);
+    }
+    packet
+}
+
+#[test]
+fn test_housekeeping_report() {
+    let registry: ServiceRegistry<()> = ServiceRegistry::new();
+
+    let report = registry.decode(3, 25, &[0x00, 0x07, 0x01, 0x02, 0x03, 0x04, 0x05]).unwrap();
+    let expected = HousekeepingReport { diagnostic: false, structure_id: 7, parameters: vec![1, 2, 3, 4, 5] };
+    assert_eq!(report, PusReport::Housekeeping(expected.clone()));
+
+    assert_eq!(expected.parameter_values(&[1, 2, 2]),
+               Some(vec![&[1u8][..], &[2, 3][..], &[4, 5][..]]));
+    assert_eq!(expected.parameter_values(&[1, 2]), None);
+
+    match registry.decode(3, 26, &[0x00, 0x08]).unwrap() {
+        PusReport::Housekeeping(report) => {
+            assert!(report.diagnostic);
+            assert!(report.parameters.is_empty());
+        },
+        report => panic!("unexpected report {:?}", report),
+    }
+
+    assert_eq!(registry.decode(3, 1, &[0x00, 0x08]), Err(PusError::UnknownSubtype(3, 1)));
+    assert_eq!(registry.decode(3, 25, &[0x00]), Err(PusError::NotEnoughBytes));
+}
+
+#[test]
+fn test_event_report() {
+    let mut registry: ServiceRegistry<()> = ServiceRegistry::new();
+
+    assert_eq!(registry.decode(5, 4, &[0x12, 0x34, 0xAA]),
+               Ok(PusReport::Event(EventReport { severity: EventSeverity::High, event_id: 0x1234, data: vec![0xAA] })));
+    assert_eq!(registry.decode(5, 5, &[0x12, 0x34]), Err(PusError::UnknownSubtype(5, 5)));
+
+    registry.event_id_bytes = 1;
+    assert_eq!(registry.decode(5, 1, &[0x12, 0x34]),
+               Ok(PusReport::Event(EventReport { severity: EventSeverity::Informative, event_id: 0x12, data: vec![0x34] })));
+
+    for subtype in 1..5 {
+        assert_eq!(EventSeverity::from_subtype(subtype).unwrap().subtype(), subtype);
+    }
+    assert!(EventSeverity::High > EventSeverity::Low);
+}
+
+#[test]
+fn test_test_report() {
+    let registry: ServiceRegistry<()> = ServiceRegistry::new();
+
+    assert_eq!(registry.decode(17, 2, &[]), Ok(PusReport::Test(TestReport::AreYouAlive)));
+    assert_eq!(registry.decode(17, 4, &[0x01, 0x23]), Ok(PusReport::Test(TestReport::ConnectionTest { apid: 0x123 })));
+    assert_eq!(registry.decode(17, 4, &[0x01]), Err(PusError::NotEnoughBytes));
+    assert_eq!(registry.decode(17, 1, &[]), Err(PusError::UnknownSubtype(17, 1)));
+}
+
+#[derive(Debug, PartialEq)]
+enum MissionReport {
+    Temperature(i8),
+    Reset,
+}
+
+#[test]
+fn test_custom_services() {
+    let mut registry = ServiceRegistry::new();
+    registry.register(130, |subtype, data: &[u8]| {
+        match subtype {
+            1 if !data.is_empty() => Ok(MissionReport::Temperature(data[0] as i8)),
+            1 => Err(PusError::NotEnoughBytes),
+            _ => Err(PusError::UnknownSubtype(130, subtype)),
+        }
+    });
+    registry.register(17, |_, _: &[u8]| Ok(MissionReport::Reset));
+
+    assert_eq!(registry.decode(130, 1, &[0xFE]), Ok(PusReport::Custom(MissionReport::Temperature(-2))));
+    assert_eq!(registry.decode(130, 2, &[]), Err(PusError::UnknownSubtype(130, 2)));
+
+    // custom decoders replace the standard decoders
+    assert_eq!(registry.decode(17, 2, &[]), Ok(PusReport::Custom(MissionReport::Reset)));
+
+    assert_eq!(registry.decode(200, 3, &[1, 2]),
+               Ok(PusReport::Unknown { service_type: 200, message_subtype: 3, data: vec![1, 2] }));
+}
+
+#[test]
+fn test_decode_packet() {
+    let config = PusTmConfig::new(CucFormat::new(4, 0));
+    let registry: ServiceRegistry<()> = ServiceRegistry::new();
+
+    let packet = make_tm_packet(5, 2, &[0x00, 0x10, 0x77], true);
+    let (tm_header, report) = registry.decode_packet(&config, &packet, true).unwrap();
+    assert_eq!(tm_header.time, CucTime { coarse: 100, fine: 0 });
+    assert_eq!(report, PusReport::Event(EventReport { severity: EventSeverity::Low, event_id: 0x10, data: vec![0x77] }));
+
+    let mut corrupted = packet.clone();
+    corrupted[18] ^= 0xFF;
+    assert_eq!(registry.decode_packet(&config, &corrupted, true), Err(PusError::InvalidPec));
+
+    let packet = make_tm_packet(1, 1, &[0x18, 0x20, 0xC0, 0x05], false);
+    let (_, report) = registry.decode_packet(&config, &packet, false).unwrap();
+    match report {
+        PusReport::Verification(report) => {
+            assert_eq!(report.request_id, RequestId { apid: 0x20, sequence_count: 5 });
+            assert_eq!(report.stage, VerificationStage::Acceptance);
+        },
+        report => panic!("unexpected report {:?}", report),
+    }
+}
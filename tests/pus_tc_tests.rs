@@ -0,0 +1,110 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::pus::*;
+
+
+#[test]
+fn test_crc16_ccitt() {
+    assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+
+    // test vectors from ECSS-E-ST-70-41C annex B
+    assert_eq!(crc16_ccitt(&[0x00, 0x00]), 0x1D0F);
+    assert_eq!(crc16_ccitt(&[0x00, 0x00, 0x00]), 0xCC9C);
+    assert_eq!(crc16_ccitt(&[0xAB, 0xCD, 0xEF, 0x01]), 0x04A2);
+    assert_eq!(crc16_ccitt(&[0x14, 0x56, 0xF8, 0x9A, 0x00, 0x01]), 0x7FD5);
+
+    assert_eq!(crc16_ccitt_update(crc16_ccitt(b"1234"), b"56789"), 0x29B1);
+    assert!(crc16_ccitt_is_valid(&[0xAB, 0xCD, 0xEF, 0x01, 0x04, 0xA2]));
+    assert!(!crc16_ccitt_is_valid(&[0xAB, 0xCD, 0xEF, 0x01, 0x04, 0xA3]));
+    assert!(!crc16_ccitt_is_valid(&[0x00]));
+}
+
+#[test]
+fn test_ack_flags() {
+    assert_eq!(AckFlags::all().bits(), 0x0F);
+    assert_eq!(AckFlags::none().bits(), 0x00);
+
+    let flags = AckFlags::from_bits(0x09);
+    assert!(flags.acceptance);
+    assert!(!flags.start);
+    assert!(!flags.progress);
+    assert!(flags.completion);
+    assert_eq!(flags.bits(), 0x09);
+}
+
+#[test]
+fn test_pus_tc_decode_encode() {
+    let config = PusTcConfig::new();
+    assert_eq!(config.header_len(), 5);
+
+    let bytes = [0x21, 0x11, 0x01, 0x12, 0x34];
+    let header = PusTcHeader::decode(&config, &bytes).unwrap();
+    assert_eq!(header.version, PUS_C_VERSION);
+    assert_eq!(header.ack_flags, AckFlags { acceptance: true, .. AckFlags::none() });
+    assert_eq!(header.service_type, 17);
+    assert_eq!(header.message_subtype, 1);
+    assert_eq!(header.source_id, 0x1234);
+
+    let mut encoded = [0xFF; 5];
+    assert_eq!(header.encode(&config, &mut encoded), Ok(5));
+    assert_eq!(encoded, bytes);
+
+    assert_eq!(PusTcHeader::decode(&config, &bytes[0..4]), Err(PusError::NotEnoughBytes));
+    assert_eq!(PusTcHeader::decode(&config, &[0x11, 0x11, 0x01, 0x12, 0x34]), Err(PusError::InvalidVersion(1)));
+}
+
+#[test]
+fn test_pus_tc_mission_field_widths() {
+    let config = PusTcConfig { source_id_bytes: 1, spare_bytes: 1 };
+    let mut header = PusTcHeader::new(8, 1);
+    header.source_id = 0x55;
+
+    let mut bytes = [0xFF; 5];
+    assert_eq!(header.encode(&config, &mut bytes), Ok(5));
+    assert_eq!(bytes, [0x2F, 0x08, 0x01, 0x55, 0x00]);
+    assert_eq!(PusTcHeader::decode(&config, &bytes), Ok(header));
+
+    header.source_id = 0x155;
+    assert_eq!(header.encode(&config, &mut bytes), Err(PusError::FieldOutOfRange));
+
+    let invalid = PusTcConfig { source_id_bytes: 4, spare_bytes: 0 };
+    assert_eq!(header.encode(&invalid, &mut bytes), Err(PusError::InvalidConfig));
+}
+
+#[test]
+fn test_build_pus_tc() {
+    let config = PusTcConfig::new();
+    let mut header = PusTcHeader::new(17, 1);
+    header.source_id = 0x10;
+
+    let packet = build_pus_tc(&config, 0x123, 0x42, &header, &[0xAA, 0xBB]).unwrap();
+    assert_eq!(packet.len(), 6 + 5 + 2 + 2);
+    assert_eq!(&packet[0..13],
+               &[0x19, 0x23, 0xC0, 0x42, 0x00, 0x08, 0x2F, 0x11, 0x01, 0x00, 0x10, 0xAA, 0xBB]);
+    assert_eq!(((packet[13] as u16) << 8) | packet[14] as u16, crc16_ccitt(&packet[0..13]));
+    assert!(has_valid_pec(&packet));
+
+    let pri_header = PrimaryHeader::from_slice(&packet).unwrap();
+    assert_eq!(pri_header.packet_length() as usize, packet.len());
+    assert_eq!(pri_header.control.apid(), 0x123);
+    assert_eq!(pri_header.sequence.sequence_count(), 0x42);
+    assert_eq!(PusTcHeader::from_packet(&config, &packet), Ok(header));
+
+    let mut corrupted = packet.clone();
+    corrupted[11] ^= 0x01;
+    assert!(!has_valid_pec(&corrupted));
+    assert!(!has_valid_pec(&packet[0..14]));
+
+    assert_eq!(build_pus_tc(&config, 0x800, 0, &header, &[]), Err(PusError::FieldOutOfRange));
+}
+
+#[test]
+fn test_pus_tc_from_packet_wrong_type() {
+    let config = PusTcConfig::new();
+    let mut packet = build_pus_tc(&config, 0x10, 0, &PusTcHeader::new(17, 1), &[]).unwrap();
+    packet[0] &= !0x10;
+
+    assert_eq!(PusTcHeader::from_packet(&config, &packet), Err(PusError::WrongPacketType));
+}
@@ -0,0 +1,108 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::secondary_header::*;
+use ccsds_primary_header::space_packet::*;
+use ccsds_primary_header::pus::*;
+use common::build_packet;
+
+
+fn make_packet(packet_type: PacketType, sec_header: bool, data: &[u8]) -> Vec<u8> {
+    build_packet(packet_type, 0x42, sec_header, data)
+}
+
+#[test]
+fn test_pus_tm_decode() {
+    let config = PusTmConfig::new(CucFormat::new(4, 2));
+    assert_eq!(config.header_len(), 13);
+
+    let bytes = [0x23, 0x03, 0x19, 0x00, 0x07, 0x01, 0x02, 0x00, 0x00, 0x10, 0x00, 0x40, 0x00];
+    let header = PusTmHeader::decode(&config, &bytes).unwrap();
+
+    assert_eq!(header.version, PUS_C_VERSION);
+    assert_eq!(header.time_reference_status, 3);
+    assert_eq!(header.service_type, 3);
+    assert_eq!(header.message_subtype, 25);
+    assert_eq!(header.message_type_counter, 7);
+    assert_eq!(header.destination_id, 0x102);
+    assert_eq!(header.time, CucTime { coarse: 0x1000, fine: 0x40000000 });
+}
+
+#[test]
+fn test_pus_tm_encode() {
+    let config = PusTmConfig::new(CucFormat::new(4, 2));
+    let mut header: PusTmHeader<CucFormat> = PusTmHeader::new(1, 7, CucTime { coarse: 0x01020304, fine: 0x05060000 });
+    header.message_type_counter = 0xABCD;
+    header.destination_id = 0x10;
+
+    let mut bytes = [0xFF; 13];
+    assert_eq!(header.encode(&config, &mut bytes), Ok(13));
+    assert_eq!(bytes, [0x20, 0x01, 0x07, 0xAB, 0xCD, 0x00, 0x10, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+    assert_eq!(PusTmHeader::decode(&config, &bytes), Ok(header));
+
+    assert_eq!(header.encode(&config, &mut bytes[0..12]), Err(PusError::NotEnoughBytes));
+}
+
+#[test]
+fn test_pus_tm_mission_field_widths() {
+    let config = PusTmConfig {
+        message_counter_bytes: 1,
+        destination_id_bytes: 0,
+        time_format: CdsFormat::new(CdsDaySegment::Days16, CdsSubMillis::None),
+        spare_bytes: 2,
+    };
+    assert_eq!(config.header_len(), 3 + 1 + 6 + 2);
+
+    let mut header = PusTmHeader::new(5, 1, CdsTime { days: 20000, ms_of_day: 1000, submillis: 0 });
+    header.message_type_counter = 0x12;
+
+    let mut bytes = [0xFF; 12];
+    assert_eq!(header.encode(&config, &mut bytes), Ok(12));
+    assert_eq!(bytes, [0x20, 0x05, 0x01, 0x12, 0x4E, 0x20, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00]);
+    assert_eq!(PusTmHeader::decode(&config, &bytes), Ok(header));
+
+    // values must fit in the configured widths
+    header.message_type_counter = 0x100;
+    assert_eq!(header.encode(&config, &mut bytes), Err(PusError::FieldOutOfRange));
+    header.message_type_counter = 0;
+    header.destination_id = 1;
+    assert_eq!(header.encode(&config, &mut bytes), Err(PusError::FieldOutOfRange));
+
+    let invalid = PusTmConfig { message_counter_bytes: 3, .. config };
+    assert_eq!(PusTmHeader::decode(&invalid, &bytes), Err(PusError::InvalidConfig));
+}
+
+#[test]
+fn test_pus_tm_invalid_version() {
+    let config = PusTmConfig::new(CucFormat::new(4, 0));
+    let bytes = [0x10, 0x03, 0x19, 0x00, 0x07, 0x01, 0x02, 0x00, 0x00, 0x10, 0x00];
+
+    assert_eq!(PusTmHeader::decode(&config, &bytes), Err(PusError::InvalidVersion(1)));
+    assert_eq!(PusTmHeader::decode(&config, &bytes[0..10]), Err(PusError::NotEnoughBytes));
+}
+
+#[test]
+fn test_pus_tm_from_packet() {
+    let config = PusTmConfig::new(CucFormat::new(4, 0));
+    let data = [0x20, 0x11, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xAA];
+
+    let packet = make_packet(PacketType::Data, true, &data);
+    let header = PusTmHeader::from_packet(&config, &packet).unwrap();
+    assert_eq!(header.service_type, 17);
+    assert_eq!(header.message_subtype, 2);
+    assert_eq!(header.time, CucTime { coarse: 5, fine: 0 });
+
+    let space_packet = SpacePacket::new(&packet).unwrap();
+    assert_eq!(space_packet.secondary_header::<PusTmHeader<CucFormat>>(&config), Some(header));
+    assert_eq!(space_packet.user_data::<PusTmHeader<CucFormat>>(&config), Some(&[0xAA][..]));
+    assert_eq!(PusTmHeader::<CucFormat>::size(&config), 11);
+
+    let packet = make_packet(PacketType::Command, true, &data);
+    assert_eq!(PusTmHeader::from_packet(&config, &packet), Err(PusError::WrongPacketType));
+
+    let packet = make_packet(PacketType::Data, false, &data);
+    assert_eq!(PusTmHeader::from_packet(&config, &packet), Err(PusError::NoSecondaryHeader));
+}
@@ -0,0 +1,182 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use std::time::Duration;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::pus::*;
+use common::packet_header;
+
+
+fn tm_config() -> PusTmConfig<CucFormat> {
+    PusTmConfig::new(CucFormat::new(4, 0))
+}
+
+/// Build a service 1 report for a command, with the given subtype and report data
+/// following the request ID.
+fn make_report(command: &[u8], message_subtype: u8, data: &[u8]) -> Vec<u8> {
+    let config = tm_config();
+    let tm_header: PusTmHeader<CucFormat> =
+        PusTmHeader::new(PUS_SERVICE_VERIFICATION, message_subtype, CucTime { coarse: 100, fine: 0 });
+
+    let mut user_data = command[0..4].to_vec();
+    user_data.extend_from_slice(data);
+
+    let pri_header = packet_header(PacketType::Data, 0x01, true, config.header_len() + user_data.len());
+
+    let mut packet = pri_header.to_bytes().to_vec();
+    packet.resize(6 + config.header_len(), 0);
+    tm_header.encode(&config, &mut packet[6..]).unwrap();
+    packet.extend_from_slice(&user_data);
+    packet
+}
+
+fn make_command(sequence_count: u16, ack_flags: AckFlags) -> Vec<u8> {
+    let mut header = PusTcHeader::new(8, 1);
+    header.ack_flags = ack_flags;
+    build_pus_tc(&PusTcConfig::new(), 0x20, sequence_count, &header, &[1, 2, 3]).unwrap()
+}
+
+#[test]
+fn test_verification_report_decode() {
+    let data = [0x18, 0x20, 0xC0, 0x05, 0x03];
+    let report = VerificationReport::decode(5, &data, 1, 2).unwrap();
+    assert_eq!(report.request_id, RequestId { apid: 0x20, sequence_count: 5 });
+    assert_eq!(report.stage, VerificationStage::Progress);
+    assert_eq!(report.step, Some(3));
+    assert_eq!(report.failure, None);
+
+    let data = [0x18, 0x20, 0xC0, 0x05, 0x00, 0x2A, 0xEE];
+    let report = VerificationReport::decode(2, &data, 1, 2).unwrap();
+    assert_eq!(report.stage, VerificationStage::Acceptance);
+    assert_eq!(report.failure,
+               Some(VerificationFailure { stage: VerificationStage::Acceptance, code: 0x2A, data: vec![0xEE] }));
+
+    assert_eq!(VerificationReport::decode(9, &data, 1, 2), Err(PusError::UnknownSubtype(1, 9)));
+    assert_eq!(VerificationReport::decode(2, &data[0..5], 1, 2), Err(PusError::NotEnoughBytes));
+}
+
+#[test]
+fn test_command_verifier_success() {
+    let mut verifier = CommandVerifier::new(tm_config());
+    let command = make_command(5, AckFlags::all());
+
+    let request_id = verifier.send_command(&PusTcConfig::new(), &command, Duration::from_secs(0)).unwrap();
+    assert_eq!(request_id, RequestId { apid: 0x20, sequence_count: 5 });
+    assert_eq!(verifier.state(&request_id), Some(CommandState::Pending));
+
+    assert_eq!(verifier.recv_telemetry(&make_report(&command, 1, &[])), Some(request_id));
+    assert_eq!(verifier.command(&request_id).unwrap().stage, Some(VerificationStage::Acceptance));
+
+    verifier.recv_telemetry(&make_report(&command, 3, &[]));
+    verifier.recv_telemetry(&make_report(&command, 5, &[1]));
+    verifier.recv_telemetry(&make_report(&command, 5, &[2]));
+    assert_eq!(verifier.command(&request_id).unwrap().stage, Some(VerificationStage::Progress));
+    assert_eq!(verifier.command(&request_id).unwrap().progress_step, Some(2));
+    assert_eq!(verifier.state(&request_id), Some(CommandState::Pending));
+
+    verifier.recv_telemetry(&make_report(&command, 7, &[]));
+    assert_eq!(verifier.state(&request_id), Some(CommandState::Completed));
+
+    // completed commands do not time out
+    assert!(verifier.check_timeouts(Duration::from_secs(1000)).is_empty());
+
+    let finished = verifier.take_finished();
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].request_id, request_id);
+    assert_eq!(verifier.commands().count(), 0);
+}
+
+#[test]
+fn test_command_verifier_failure() {
+    let mut verifier = CommandVerifier::new(tm_config());
+    let command = make_command(6, AckFlags::all());
+    let request_id = verifier.send_command(&PusTcConfig::new(), &command, Duration::from_secs(0)).unwrap();
+
+    verifier.recv_telemetry(&make_report(&command, 1, &[]));
+    verifier.recv_telemetry(&make_report(&command, 4, &[0x01, 0x02, 0xAA, 0xBB]));
+
+    let record = verifier.command(&request_id).unwrap();
+    assert_eq!(record.state(), CommandState::Failed);
+    assert_eq!(record.failure_code(), Some(0x0102));
+    assert_eq!(record.failure.as_ref().unwrap().stage, VerificationStage::Start);
+    assert_eq!(record.failure.as_ref().unwrap().data, vec![0xAA, 0xBB]);
+}
+
+#[test]
+fn test_command_verifier_timeouts() {
+    let mut verifier = CommandVerifier::new(tm_config());
+    verifier.acceptance_timeout = Some(Duration::from_secs(5));
+    verifier.completion_timeout = Some(Duration::from_secs(30));
+
+    let accepted = make_command(1, AckFlags::all());
+    let silent = make_command(2, AckFlags::all());
+    let no_acks = make_command(3, AckFlags::none());
+
+    let accepted_id = verifier.send_command(&PusTcConfig::new(), &accepted, Duration::from_secs(0)).unwrap();
+    let silent_id = verifier.send_command(&PusTcConfig::new(), &silent, Duration::from_secs(0)).unwrap();
+    let no_acks_id = verifier.send_command(&PusTcConfig::new(), &no_acks, Duration::from_secs(0)).unwrap();
+
+    verifier.recv_telemetry(&make_report(&accepted, 1, &[]));
+
+    assert!(verifier.check_timeouts(Duration::from_secs(5)).is_empty());
+    assert_eq!(verifier.check_timeouts(Duration::from_secs(6)), vec![silent_id]);
+    assert_eq!(verifier.state(&silent_id), Some(CommandState::TimedOut(VerificationStage::Acceptance)));
+
+    assert_eq!(verifier.check_timeouts(Duration::from_secs(31)), vec![accepted_id]);
+    assert_eq!(verifier.state(&accepted_id), Some(CommandState::TimedOut(VerificationStage::Completion)));
+
+    // a command that requests no acknowledgements has nothing to wait for, so it
+    // is kept until it is removed
+    assert_eq!(verifier.state(&no_acks_id), Some(CommandState::Pending));
+    assert_eq!(verifier.remove_command(&no_acks_id).unwrap().request_id, no_acks_id);
+    assert_eq!(verifier.command(&no_acks_id), None);
+    assert_eq!(verifier.remove_command(&no_acks_id), None);
+}
+
+#[test]
+fn test_command_verifier_last_requested_stage() {
+    let mut verifier = CommandVerifier::new(tm_config());
+    verifier.completion_timeout = Some(Duration::from_secs(30));
+
+    let acceptance_only = make_command(1, AckFlags::from_bits(0x01));
+    let start_only = make_command(2, AckFlags::from_bits(0x02));
+
+    let acceptance_id = verifier.send_command(&PusTcConfig::new(), &acceptance_only, Duration::from_secs(0)).unwrap();
+    let start_id = verifier.send_command(&PusTcConfig::new(), &start_only, Duration::from_secs(0)).unwrap();
+    assert_eq!(verifier.command(&acceptance_id).unwrap().last_requested_stage(), Some(VerificationStage::Acceptance));
+
+    // no completion report is sent for a command which only requested acceptance
+    verifier.recv_telemetry(&make_report(&acceptance_only, 1, &[]));
+    assert_eq!(verifier.state(&acceptance_id), Some(CommandState::Completed));
+
+    // the completion timeout applies to the last requested stage
+    assert_eq!(verifier.check_timeouts(Duration::from_secs(31)), vec![start_id]);
+    assert_eq!(verifier.state(&start_id), Some(CommandState::TimedOut(VerificationStage::Start)));
+
+    assert_eq!(verifier.take_finished().len(), 2);
+    assert_eq!(verifier.commands().count(), 0);
+}
+
+#[test]
+fn test_command_verifier_ignores_other_packets() {
+    let mut verifier = CommandVerifier::new(tm_config());
+    let command = make_command(7, AckFlags::all());
+    verifier.send_command(&PusTcConfig::new(), &command, Duration::from_secs(0)).unwrap();
+
+    // a report for a command that was never sent
+    let other = make_command(8, AckFlags::all());
+    assert_eq!(verifier.recv_telemetry(&make_report(&other, 1, &[])), None);
+    assert_eq!(verifier.unmatched_reports, 1);
+
+    // a report with an unknown subtype
+    assert_eq!(verifier.recv_telemetry(&make_report(&command, 12, &[])), None);
+    assert_eq!(verifier.invalid_reports, 1);
+
+    // a command packet is not a report
+    assert_eq!(verifier.recv_telemetry(&command), None);
+    assert_eq!(verifier.unmatched_reports, 1);
+    assert_eq!(verifier.invalid_reports, 1);
+}
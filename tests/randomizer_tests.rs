@@ -0,0 +1,102 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::frame_sync::*;
+use ccsds_primary_header::randomizer::*;
+use ccsds_primary_header::tm_frame::*;
+use common::build_packet;
+
+
+fn bit(bytes: &[u8], index: usize) -> u8 {
+    (bytes[index / 8] >> (7 - index % 8)) & 1
+}
+
+#[test]
+fn test_randomizer_pn255_sequence() {
+    let sequence = Randomizer::Pn255.sequence(510);
+
+    assert_eq!(&sequence[0..16], &[0xFF, 0x48, 0x0E, 0xC0, 0x9A, 0x0D, 0x70, 0xBC,
+                                   0x8E, 0x2C, 0x93, 0xAD, 0xA7, 0xB7, 0x46, 0xCE]);
+
+    // the sequence repeats every 255 bits, and so every 255 bytes
+    assert_eq!(&sequence[0..255], &sequence[255..510]);
+    assert!((1..255).all(|shift| sequence[shift..shift + 16] != sequence[0..16]));
+}
+
+#[test]
+fn test_randomizer_pn131071_sequence() {
+    let period = 131071;
+    let sequence = Randomizer::Pn131071.sequence(2 * period / 8 + 1);
+
+    assert_eq!(&sequence[0..2], &[0xFF, 0xFF]);
+    assert!((0..period).all(|index| bit(&sequence, index) == bit(&sequence, index + period)));
+
+    // the period is prime, so the only shorter period would be a constant sequence
+    assert!(sequence.iter().any(|byte| *byte != 0xFF));
+}
+
+#[test]
+fn test_randomizer_round_trip() {
+    for randomizer in [Randomizer::Pn255, Randomizer::Pn131071].iter() {
+        let frame: Vec<u8> = (0..300).map(|index| index as u8).collect();
+
+        let mut randomized = frame.clone();
+        randomizer.randomize(&mut randomized);
+        assert_ne!(randomized, frame);
+
+        randomizer.derandomize(&mut randomized);
+        assert_eq!(randomized, frame);
+    }
+
+    let mut zeros = vec![0; 4];
+    Randomizer::Pn255.randomize(&mut zeros);
+    assert_eq!(zeros, vec![0xFF, 0x48, 0x0E, 0xC0]);
+}
+
+#[test]
+fn test_randomizer_cadus_to_packets() {
+    const FRAME_LENGTH: usize = 32;
+
+    let packet = build_packet(PacketType::Data, 0x55, false, &[0; 11]);
+
+    // a TM frame with a 24 byte data field holding the packet, padded with an idle packet
+    let mut header = TmFrameHeader::default();
+    header.set_spacecraft_id(0x12);
+    let mut frame = header.0.to_vec();
+    frame.extend_from_slice(&packet);
+    frame.extend_from_slice(&[0x07, 0xFF, 0xC0, 0x00, 0x00, 0x00, 0x00]);
+    frame.extend_from_slice(&[0x00, 0x00]);
+    assert_eq!(frame.len(), FRAME_LENGTH);
+
+    let mut cadus = vec![0xAA, 0x55];
+    for count in 0..2 {
+        let mut frame = frame.clone();
+        frame[2] = count;
+        frame[3] = count;
+        let fecf = crc16_ccitt(&frame[0..FRAME_LENGTH - 2]);
+        frame[FRAME_LENGTH - 2] = (fecf >> 8) as u8;
+        frame[FRAME_LENGTH - 1] = fecf as u8;
+
+        Randomizer::Pn255.randomize(&mut frame);
+        cadus.extend_from_slice(&CCSDS_ASM);
+        cadus.extend_from_slice(&frame);
+    }
+
+    let mut sync_config = FrameSyncConfig::new(FRAME_LENGTH);
+    sync_config.derandomizer = Some(Randomizer::Pn255);
+    let mut sync = FrameSync::new(sync_config);
+    let mut decoder = TmFrameDecoder::new(TmFrameConfig::new(FRAME_LENGTH));
+
+    sync.recv_slice(&cadus);
+    while let Some(frame) = sync.pull_frame() {
+        decoder.recv_frame(&frame).unwrap();
+    }
+
+    assert_eq!(decoder.pull_packet(), Some(packet.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), Some(packet.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+    assert_eq!(decoder.invalid_frames, 0);
+}
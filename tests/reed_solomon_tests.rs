@@ -0,0 +1,162 @@
+extern crate ccsds_primary_header;
+
+use ccsds_primary_header::frame_sync::*;
+use ccsds_primary_header::randomizer::*;
+use ccsds_primary_header::reed_solomon::*;
+
+
+/// Pseudo-random bytes from a linear congruential generator, so tests are repeatable.
+fn make_bytes(seed: u32, length: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..length).map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 16) as u8
+    }).collect()
+}
+
+/// Corrupt a number of distinct symbols of one codeword in a codeblock.
+fn add_errors(codeblock: &mut [u8], config: &RsConfig, codeword: usize, num_errors: usize) {
+    let codeword_length = codeblock.len() / config.interleave_depth;
+    let values = make_bytes(codeword as u32 + 7, num_errors);
+
+    for (index, value) in values.iter().enumerate() {
+        let symbol = (index * 37 + codeword * 11) % codeword_length;
+        codeblock[symbol * config.interleave_depth + codeword] ^= value | 0x01;
+    }
+}
+
+#[test]
+fn test_reed_solomon_round_trip() {
+    let config = RsConfig::new(RsCode::Rs255_223, 5);
+    assert_eq!(config.frame_length(), 1115);
+    assert_eq!(config.codeblock_length(), 1275);
+
+    let rs = ReedSolomon::new(config).unwrap();
+    let frame = make_bytes(1, config.frame_length());
+    let codeblock = rs.encode(&frame).unwrap();
+    assert_eq!(codeblock.len(), 1275);
+    assert_eq!(&codeblock[0..frame.len()], frame.as_slice());
+
+    let mut received = codeblock.clone();
+    let report = rs.decode(&mut received).unwrap();
+    assert_eq!(report.corrected_symbols, vec![Some(0); 5]);
+    assert!(report.is_correctable());
+    assert_eq!(received, codeblock);
+
+    // a frame of zeros has check symbols of zero in either basis
+    let zeros = rs.encode(&vec![0; config.frame_length()]).unwrap();
+    assert!(zeros.iter().all(|symbol| *symbol == 0));
+
+    assert_eq!(rs.encode(&frame[1..]), Err(RsError::WrongLength { expected: 1115, actual: 1114 }));
+    assert_eq!(ReedSolomon::new(RsConfig::new(RsCode::Rs255_223, 0)).err(), Some(RsError::InvalidConfig));
+}
+
+#[test]
+fn test_reed_solomon_corrects_errors() {
+    for code in [RsCode::Rs255_223, RsCode::Rs255_239].iter() {
+        for depth in 1..6 {
+            let config = RsConfig::new(*code, depth);
+            let rs = ReedSolomon::new(config).unwrap();
+
+            let codeblock = rs.encode(&make_bytes(depth as u32, config.frame_length())).unwrap();
+            let mut received = codeblock.clone();
+            for codeword in 0..depth {
+                add_errors(&mut received, &config, codeword, code.max_corrections() - codeword % 2);
+            }
+
+            let report = rs.decode(&mut received).unwrap();
+            let expected: Vec<Option<usize>> = (0..depth).map(|codeword| Some(code.max_corrections() - codeword % 2)).collect();
+            assert_eq!(report.corrected_symbols, expected);
+            assert_eq!(received, codeblock);
+        }
+    }
+}
+
+#[test]
+fn test_reed_solomon_uncorrectable() {
+    let config = RsConfig::new(RsCode::Rs255_223, 3);
+    let rs = ReedSolomon::new(config).unwrap();
+
+    let codeblock = rs.encode(&make_bytes(3, config.frame_length())).unwrap();
+    let mut received = codeblock.clone();
+    add_errors(&mut received, &config, 0, 2);
+    add_errors(&mut received, &config, 1, 17);
+    let corrupted = received.clone();
+
+    let report = rs.decode(&mut received).unwrap();
+    assert_eq!(report.corrected_symbols, vec![Some(2), None, Some(0)]);
+    assert!(!report.is_correctable());
+    assert_eq!(report.total_corrected(), 2);
+
+    // the codeword which could not be corrected is left as it was received
+    for (index, symbol) in received.iter().enumerate() {
+        if index % 3 == 1 {
+            assert_eq!(*symbol, corrupted[index]);
+        } else {
+            assert_eq!(*symbol, codeblock[index]);
+        }
+    }
+}
+
+#[test]
+fn test_reed_solomon_virtual_fill_and_basis() {
+    let mut config = RsConfig::new(RsCode::Rs255_223, 2);
+    config.virtual_fill = 100;
+    assert_eq!(config.frame_length(), 246);
+    assert_eq!(config.codeblock_length(), 310);
+
+    let rs = ReedSolomon::new(config).unwrap();
+    let frame = make_bytes(4, config.frame_length());
+    let codeblock = rs.encode(&frame).unwrap();
+
+    let mut received = codeblock.clone();
+    add_errors(&mut received, &config, 0, 16);
+    add_errors(&mut received, &config, 1, 5);
+    assert_eq!(rs.decode(&mut received).unwrap().corrected_symbols, vec![Some(16), Some(5)]);
+    assert_eq!(received, codeblock);
+
+    // the conventional basis gives different check symbols, which still correct errors
+    config.dual_basis = false;
+    let conventional = ReedSolomon::new(config).unwrap();
+    let conventional_codeblock = conventional.encode(&frame).unwrap();
+    assert_ne!(conventional_codeblock, codeblock);
+
+    let mut received = conventional_codeblock.clone();
+    add_errors(&mut received, &config, 1, 9);
+    assert_eq!(conventional.decode(&mut received).unwrap().corrected_symbols, vec![Some(0), Some(9)]);
+    assert_eq!(received, conventional_codeblock);
+}
+
+#[test]
+fn test_reed_solomon_frame_sync() {
+    let rs_config = RsConfig::new(RsCode::Rs255_239, 2);
+    let rs = ReedSolomon::new(rs_config).unwrap();
+
+    let frames: Vec<Vec<u8>> = (0..3).map(|index| make_bytes(10 + index, rs_config.frame_length())).collect();
+
+    let mut stream = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let mut codeblock = rs.encode(frame).unwrap();
+        add_errors(&mut codeblock, &rs_config, 0, 3);
+        if index == 1 {
+            add_errors(&mut codeblock, &rs_config, 1, 9);
+        }
+
+        Randomizer::Pn255.randomize(&mut codeblock);
+        stream.extend_from_slice(&CCSDS_ASM);
+        stream.extend_from_slice(&codeblock);
+    }
+
+    let mut sync_config = FrameSyncConfig::new(rs_config.codeblock_length());
+    sync_config.derandomizer = Some(Randomizer::Pn255);
+    sync_config.reed_solomon = Some(rs_config);
+    let mut sync = FrameSync::new(sync_config);
+    sync.recv_slice(&stream);
+
+    // the frame which could not be corrected is dropped
+    assert_eq!(sync.uncorrectable_frames, 1);
+    assert_eq!(sync.corrected_symbols, 6);
+    assert_eq!(sync.pull_frame(), Some(frames[0].as_slice().into()));
+    assert_eq!(sync.pull_frame(), Some(frames[2].as_slice().into()));
+    assert_eq!(sync.pull_frame(), None);
+}
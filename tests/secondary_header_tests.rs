@@ -0,0 +1,159 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::parser::*;
+use ccsds_primary_header::time::*;
+use ccsds_primary_header::secondary_header::*;
+use ccsds_primary_header::space_packet::*;
+use common::build_packet;
+
+
+/// A mission header with a 4 byte seconds field and an optional 2 byte subseconds field.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct MissionHeader {
+    seconds: u32,
+    subseconds: u16,
+}
+
+impl SecondaryHeader for MissionHeader {
+    type Layout = bool;
+
+    fn size(has_subseconds: &bool) -> usize {
+        if *has_subseconds { 6 } else { 4 }
+    }
+
+    fn parse(has_subseconds: &bool, bytes: &[u8]) -> Option<MissionHeader> {
+        if bytes.len() < MissionHeader::size(has_subseconds) {
+            return None;
+        }
+
+        let seconds = ((bytes[0] as u32) << 24) | ((bytes[1] as u32) << 16) | ((bytes[2] as u32) << 8) | bytes[3] as u32;
+        let subseconds = if *has_subseconds { ((bytes[4] as u16) << 8) | bytes[5] as u16 } else { 0 };
+        Some(MissionHeader { seconds, subseconds })
+    }
+
+    fn write(&self, has_subseconds: &bool, bytes: &mut [u8]) -> Option<usize> {
+        let size = MissionHeader::size(has_subseconds);
+        if bytes.len() < size {
+            return None;
+        }
+
+        bytes[0..4].copy_from_slice(&[(self.seconds >> 24) as u8, (self.seconds >> 16) as u8,
+                                      (self.seconds >> 8) as u8, self.seconds as u8]);
+        if *has_subseconds {
+            bytes[4] = (self.subseconds >> 8) as u8;
+            bytes[5] = self.subseconds as u8;
+        }
+        Some(size)
+    }
+}
+
+#[test]
+fn test_secondary_header_registry() {
+    let mut registry: SecondaryHeaderRegistry<MissionHeader> = SecondaryHeaderRegistry::new();
+    registry.register_packet_type(PacketType::Data, true);
+    registry.register_apid(0x10, false);
+
+    let data = [0, 0, 1, 0, 0xAB, 0xCD, 0xFF];
+
+    let packet = build_packet(PacketType::Data, 0x20, true, &data);
+    assert_eq!(registry.parse(&packet), Some(MissionHeader { seconds: 0x100, subseconds: 0xABCD }));
+
+    let packet = build_packet(PacketType::Data, 0x10, true, &data);
+    assert_eq!(registry.parse(&packet), Some(MissionHeader { seconds: 0x100, subseconds: 0 }));
+
+    // no layout for commands, and no secondary header for packets without the flag
+    let packet = build_packet(PacketType::Command, 0x20, true, &data);
+    assert_eq!(registry.parse(&packet), None);
+    let packet = build_packet(PacketType::Data, 0x20, false, &data);
+    assert_eq!(registry.parse(&packet), None);
+
+    // bytes after the end of the packet, such as the start of the next packet, are not
+    // part of its secondary header
+    let mut packet = build_packet(PacketType::Data, 0x20, true, &data[0..4]);
+    packet.extend_from_slice(&data[4..]);
+    assert_eq!(registry.parse(&packet), None);
+
+    let sizes = registry.sizes();
+    assert_eq!(sizes.data, Some(6));
+    assert_eq!(sizes.command, None);
+    assert_eq!(sizes.apids.get(&0x10), Some(&4));
+}
+
+#[test]
+fn test_secondary_header_write() {
+    let header = MissionHeader { seconds: 0x01020304, subseconds: 0x0506 };
+    let mut bytes = [0; 6];
+
+    assert_eq!(header.write(&true, &mut bytes), Some(6));
+    assert_eq!(bytes, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(MissionHeader::parse(&true, &bytes), Some(header));
+    assert_eq!(header.write(&true, &mut bytes[0..5]), None);
+}
+
+#[test]
+fn test_space_packet() {
+    let mut packet = build_packet(PacketType::Data, 0x20, true, &[0, 0, 1, 0, 0xAB, 0xCD, 0xEE, 0xFF]);
+    packet.push(0x55);
+
+    let space_packet = SpacePacket::new(&packet).unwrap();
+    assert_eq!(space_packet.bytes().len(), 14);
+    assert_eq!(space_packet.primary_header().control.apid(), 0x20);
+    assert_eq!(space_packet.data_field(), &[0, 0, 1, 0, 0xAB, 0xCD, 0xEE, 0xFF]);
+
+    assert_eq!(space_packet.secondary_header::<MissionHeader>(&false),
+               Some(MissionHeader { seconds: 0x100, subseconds: 0 }));
+    assert_eq!(space_packet.user_data::<MissionHeader>(&false), Some(&[0xAB, 0xCD, 0xEE, 0xFF][..]));
+    assert_eq!(space_packet.user_data::<MissionHeader>(&true), Some(&[0xEE, 0xFF][..]));
+
+    let mut registry = SecondaryHeaderRegistry::new();
+    registry.register_apid(0x20, true);
+    assert_eq!(space_packet.secondary_header_from(&registry),
+               Some(MissionHeader { seconds: 0x100, subseconds: 0xABCD }));
+
+    assert_eq!(SpacePacket::new(&packet[0..10]), None);
+}
+
+#[test]
+fn test_space_packet_time_header() {
+    let format = CucFormat::new(4, 2);
+    let packet = build_packet(PacketType::Data, 0x20, true, &[0, 0, 1, 0, 0x80, 0x00, 0x12]);
+    let space_packet = SpacePacket::new(&packet).unwrap();
+
+    assert_eq!(space_packet.secondary_header::<CucTime>(&format),
+               Some(CucTime { coarse: 0x100, fine: 0x80000000 }));
+    assert_eq!(space_packet.user_data::<CucTime>(&format), Some(&[0x12][..]));
+
+    let packet = build_packet(PacketType::Data, 0x20, false, &[0, 0, 1, 0, 0x80, 0x00, 0x12]);
+    let space_packet = SpacePacket::new(&packet).unwrap();
+    assert_eq!(space_packet.secondary_header::<CucTime>(&format), None);
+    assert_eq!(space_packet.user_data::<CucTime>(&format), Some(&[0, 0, 1, 0, 0x80, 0x00, 0x12][..]));
+}
+
+#[test]
+fn test_ccsds_parser_secondary_header_too_short() {
+    let mut registry: SecondaryHeaderRegistry<MissionHeader> = SecondaryHeaderRegistry::new();
+    registry.register_packet_type(PacketType::Data, true);
+    registry.register_packet_type(PacketType::Command, false);
+
+    let mut parser = CcsdsParser::new();
+    parser.config.secondary_header_sizes = registry.sizes();
+
+    parser.recv_slice(&build_packet(PacketType::Data, 0x20, true, &[0, 0, 0, 0, 0]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::SecondaryHeaderTooShort);
+
+    parser.bytes.clear();
+    parser.recv_slice(&build_packet(PacketType::Data, 0x20, true, &[0, 0, 0, 0, 0, 0]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    parser.bytes.clear();
+    parser.recv_slice(&build_packet(PacketType::Command, 0x20, true, &[0, 0, 0, 0]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+
+    // packets without a secondary header are not checked
+    parser.bytes.clear();
+    parser.recv_slice(&build_packet(PacketType::Data, 0x20, false, &[0]));
+    assert_eq!(parser.current_status(), CcsdsParserStatus::ValidPacket);
+}
@@ -0,0 +1,168 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::frame::*;
+use ccsds_primary_header::tc_frame::*;
+use common::make_command_packet;
+
+
+#[test]
+fn test_tc_frame_header() {
+    let mut header = TcFrameHeader::default();
+    header.set_frame_type(TcFrameType::BC);
+    header.set_spacecraft_id(0x2AB);
+    header.set_vcid(0x15);
+    header.set_frame_length(10);
+    header.set_sequence_number(0x42);
+
+    assert_eq!(header.0, [0x32, 0xAB, 0x54, 0x09, 0x42]);
+    assert!(header.bypass_flag());
+    assert!(header.control_command_flag());
+    assert_eq!(header.frame_type(), TcFrameType::BC);
+    assert_eq!(header.spacecraft_id(), 0x2AB);
+    assert_eq!(header.vcid(), 0x15);
+    assert_eq!(header.frame_length(), 10);
+    assert_eq!(header.sequence_number(), 0x42);
+}
+
+#[test]
+fn test_tc_frame_encode() {
+    let config = TcFrameConfig::new(0x123);
+    let mut encoder = TcFrameEncoder::new(config);
+    let packet = make_command_packet(0x10, 4);
+
+    let frame = encoder.encode(TcFrameType::AD, 1, 0, &packet).unwrap();
+    assert_eq!(frame.len(), 5 + 10 + 2);
+    assert_eq!(&frame[0..5], &[0x01, 0x23, 0x04, 0x10, 0x00]);
+    assert_eq!(&frame[5..15], &packet[..]);
+    assert!(crc16_ccitt_is_valid(&frame));
+
+    // AD frames count on each virtual channel, and BD frames use 0
+    let frame = encoder.encode(TcFrameType::AD, 1, 0, &packet).unwrap();
+    assert_eq!(frame[4], 1);
+    let frame = encoder.encode(TcFrameType::AD, 2, 0, &packet).unwrap();
+    assert_eq!(frame[4], 0);
+    let frame = encoder.encode(TcFrameType::BD, 1, 0, &packet).unwrap();
+    assert_eq!(frame[0] & 0x30, 0x20);
+    assert_eq!(frame[4], 0);
+    assert_eq!(encoder.next_sequence_number(1), 2);
+
+    encoder.set_next_sequence_number(1, 0xFF);
+    assert_eq!(encoder.encode(TcFrameType::AD, 1, 0, &packet).unwrap()[4], 0xFF);
+    assert_eq!(encoder.next_sequence_number(1), 0);
+
+    assert_eq!(encoder.encode(TcFrameType::AD, 1, 0, &[0; 1018]), Err(FrameError::FrameTooLong(1025)));
+    assert_eq!(encoder.encode(TcFrameType::AD, 0x40, 0, &packet), Err(FrameError::InvalidHeader));
+}
+
+#[test]
+fn test_tc_frame_parse() {
+    let config = TcFrameConfig::new(0x123);
+    let mut encoder = TcFrameEncoder::new(config);
+    let packet = make_command_packet(0x10, 4);
+    let mut frame_bytes = encoder.encode(TcFrameType::AD, 3, 0, &packet).unwrap();
+
+    let frame = TcFrame::parse(&config, &frame_bytes).unwrap();
+    assert_eq!(frame.header().vcid(), 3);
+    assert_eq!(frame.header().frame_type(), TcFrameType::AD);
+    assert_eq!(frame.data(), &packet[..]);
+    assert_eq!(frame.segment_header(), None);
+    assert_eq!(frame.fecf(), Some(crc16_ccitt(&frame_bytes[0..15])));
+
+    // trailing bytes after the frame are ignored
+    frame_bytes.push(0x55);
+    assert_eq!(TcFrame::parse(&config, &frame_bytes).unwrap().bytes().len(), 17);
+
+    assert_eq!(TcFrame::parse(&config, &frame_bytes[0..16]),
+               Err(FrameError::WrongLength { expected: 17, actual: 16 }));
+
+    frame_bytes[6] ^= 0x01;
+    assert_eq!(TcFrame::parse(&config, &frame_bytes), Err(FrameError::InvalidFecf));
+
+    assert_eq!(TcFrame::parse(&TcFrameConfig::new(0x124), &frame_bytes), Err(FrameError::WrongSpacecraft(0x123)));
+}
+
+#[test]
+fn test_tc_frame_length_shorter_than_header() {
+    let config = TcFrameConfig { has_segment_header: true, .. TcFrameConfig::new(0x123) };
+    let mut decoder = TcFrameDecoder::new(config);
+
+    // a length field of 0 to 3 gives a frame shorter than its own header
+    for length_field in 0..4 {
+        let mut header = TcFrameHeader::default();
+        header.set_spacecraft_id(0x123);
+        header.set_frame_length_field(length_field);
+
+        let mut frame_bytes = header.0.to_vec();
+        frame_bytes.extend_from_slice(&[0; 8]);
+
+        assert_eq!(TcFrame::parse(&config, &frame_bytes), Err(FrameError::InvalidHeader));
+        assert_eq!(decoder.recv_frame(&frame_bytes), Err(FrameError::InvalidHeader));
+    }
+
+    assert_eq!(decoder.invalid_frames, 4);
+}
+
+#[test]
+fn test_tc_frame_roundtrip() {
+    let config = TcFrameConfig::new(0x123);
+    let mut encoder = TcFrameEncoder::new(config);
+    let mut decoder = TcFrameDecoder::new(config);
+
+    let first = make_command_packet(0x10, 4);
+    let second = make_command_packet(0x11, 20);
+
+    let mut data = first.clone();
+    data.extend_from_slice(&second);
+    let frame = encoder.encode(TcFrameType::AD, 0, 0, &data).unwrap();
+
+    let header = decoder.recv_frame(&frame).unwrap();
+    assert_eq!(header.sequence_number(), 0);
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), first);
+    assert_eq!(decoder.pull_packet_with_vcid().unwrap(), (0, second.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+
+    // control command frames carry no packets
+    let frame = encoder.encode(TcFrameType::BC, 0, 0, &[0x00]).unwrap();
+    assert_eq!(decoder.recv_frame(&frame).unwrap().frame_type(), TcFrameType::BC);
+    assert_eq!(decoder.pull_packet(), None);
+}
+
+#[test]
+fn test_tc_frame_segmentation() {
+    let config = TcFrameConfig { has_segment_header: true, max_frame_length: 40, .. TcFrameConfig::new(0x7) };
+    assert_eq!(config.max_data_length(), 32);
+
+    let mut encoder = TcFrameEncoder::new(config);
+    let mut decoder = TcFrameDecoder::new(config);
+
+    let long = make_command_packet(0x20, 74);
+    let short = make_command_packet(0x21, 10);
+
+    let frames = encoder.encode_packet(TcFrameType::AD, 1, 5, &long).unwrap();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames.iter().map(|frame| frame[5] >> 6).collect::<Vec<u8>>(), vec![1, 0, 2]);
+    assert!(frames.iter().all(|frame| (frame[5] & 0x3F) == 5));
+    assert_eq!(frames.iter().map(|frame| frame[4]).collect::<Vec<u8>>(), vec![0, 1, 2]);
+
+    let short_frames = encoder.encode_packet(TcFrameType::AD, 1, 6, &short).unwrap();
+    assert_eq!(short_frames.len(), 1);
+    assert_eq!(TcFrame::parse(&config, &short_frames[0]).unwrap().segment_header(),
+               Some((SegmentFlag::Unsegmented, 6)));
+
+    // a packet on another MAP is not mixed up with the segments
+    decoder.recv_frame(&frames[0]).unwrap();
+    decoder.recv_frame(&short_frames[0]).unwrap();
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), short);
+
+    decoder.recv_frame(&frames[1]).unwrap();
+    assert_eq!(decoder.pull_packet(), None);
+    decoder.recv_frame(&frames[2]).unwrap();
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), long);
+
+    // without segment headers, a long packet is an error
+    let mut encoder = TcFrameEncoder::new(TcFrameConfig { has_segment_header: false, .. config });
+    assert_eq!(encoder.encode_packet(TcFrameType::AD, 1, 0, &long), Err(FrameError::FrameTooLong(87)));
+}
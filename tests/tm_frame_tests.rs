@@ -0,0 +1,233 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::crc::*;
+use ccsds_primary_header::clcw::*;
+use ccsds_primary_header::frame::*;
+use ccsds_primary_header::tm_frame::*;
+use common::make_packet;
+
+
+const FRAME_LENGTH: usize = 32;
+
+/// Build a frame with a 20 byte data field, an OCF and a FECF.
+fn make_frame(vcid: u8, count: u8, first_header_pointer: u16, data: &[u8]) -> Vec<u8> {
+    let mut header = TmFrameHeader::default();
+    header.set_spacecraft_id(0x1AB);
+    header.set_vcid(vcid);
+    header.set_ocf_flag(true);
+    header.set_master_channel_frame_count(count);
+    header.set_virtual_channel_frame_count(count);
+    header.set_first_header_pointer(first_header_pointer);
+
+    let mut frame = header.0.to_vec();
+    frame.extend_from_slice(data);
+    frame.resize(FRAME_LENGTH - 6, 0x55);
+    frame.extend_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+    let fecf = crc16_ccitt(&frame);
+    frame.push((fecf >> 8) as u8);
+    frame.push(fecf as u8);
+    frame
+}
+
+#[test]
+fn test_tm_frame_header() {
+    let mut header = TmFrameHeader::default();
+    header.set_spacecraft_id(0x3FF);
+    header.set_vcid(5);
+    header.set_ocf_flag(true);
+    header.set_master_channel_frame_count(0x12);
+    header.set_virtual_channel_frame_count(0x34);
+    header.set_secondary_header_flag(true);
+    header.set_first_header_pointer(0x7FE);
+
+    assert_eq!(header.0, [0x3F, 0xFB, 0x12, 0x34, 0x87, 0xFE]);
+    assert_eq!(header.version(), TM_FRAME_VERSION);
+    assert_eq!(header.spacecraft_id(), 0x3FF);
+    assert_eq!(header.vcid(), 5);
+    assert!(header.ocf_flag());
+    assert!(header.secondary_header_flag());
+    assert!(!header.sync_flag());
+    assert_eq!(header.first_header_pointer(), TM_FHP_IDLE_DATA);
+}
+
+#[test]
+fn test_tm_frame_fecf_and_clcw() {
+    let config = TmFrameConfig::new(FRAME_LENGTH);
+
+    let mut clcw = Clcw::new(3);
+    clcw.set_retransmit(true);
+    clcw.set_report_value(0x2A);
+
+    let mut header = TmFrameHeader::default();
+    header.set_spacecraft_id(0x1AB);
+    header.set_ocf_flag(true);
+
+    let mut frame_bytes = header.0.to_vec();
+    frame_bytes.resize(FRAME_LENGTH - 6, 0x00);
+    frame_bytes.extend_from_slice(&clcw.0);
+    let mut unchecked = frame_bytes.clone();
+    unchecked.extend_from_slice(&[0, 0]);
+    assert!(!fecf_is_valid(&unchecked));
+
+    append_fecf(&mut frame_bytes);
+    assert_eq!(frame_bytes.len(), FRAME_LENGTH);
+    assert!(fecf_is_valid(&frame_bytes));
+
+    let frame = TmFrame::parse(&config, &frame_bytes).unwrap();
+    assert_eq!(frame.ocf(), Some(u32::from(clcw)));
+    assert_eq!(frame.clcw(), Some(clcw));
+    assert_eq!(frame.clcw().unwrap().to_string(), "VC 3 V(R) 42 FARM-B 0 retransmit");
+
+    // an OCF holding another kind of report is not a CLCW
+    frame_bytes[FRAME_LENGTH - 6] |= 0x80;
+    frame_bytes.truncate(FRAME_LENGTH - FECF_SIZE_BYTES);
+    append_fecf(&mut frame_bytes);
+    let frame = TmFrame::parse(&config, &frame_bytes).unwrap();
+    assert!(frame.ocf().is_some());
+    assert_eq!(frame.clcw(), None);
+}
+
+#[test]
+fn test_tm_frame_parse() {
+    let config = TmFrameConfig::new(FRAME_LENGTH);
+    let frame_bytes = make_frame(2, 7, 0, &[0xAA; 20]);
+
+    let frame = TmFrame::parse(&config, &frame_bytes).unwrap();
+    assert_eq!(frame.header().spacecraft_id(), 0x1AB);
+    assert_eq!(frame.header().vcid(), 2);
+    assert_eq!(frame.data_field(), &[0xAA; 20][..]);
+    assert_eq!(frame.ocf(), Some(0x01020304));
+    assert_eq!(frame.fecf(), Some(crc16_ccitt(&frame_bytes[0..30])));
+    assert_eq!(frame.first_packet_offset(), Some(0));
+    assert_eq!(frame.secondary_header(), None);
+
+    let mut corrupted = frame_bytes.clone();
+    corrupted[10] ^= 0x01;
+    assert_eq!(TmFrame::parse(&config, &corrupted), Err(FrameError::InvalidFecf));
+
+    assert_eq!(TmFrame::parse(&config, &frame_bytes[0..31]),
+               Err(FrameError::WrongLength { expected: 32, actual: 31 }));
+
+    let other_spacecraft = TmFrameConfig { spacecraft_id: Some(0x1AC), .. config };
+    assert_eq!(TmFrame::parse(&other_spacecraft, &frame_bytes), Err(FrameError::WrongSpacecraft(0x1AB)));
+
+    // a first header pointer past the end of the data field
+    let frame_bytes = make_frame(2, 7, 20, &[0xAA; 20]);
+    assert_eq!(TmFrame::parse(&config, &frame_bytes), Err(FrameError::InvalidHeader));
+}
+
+#[test]
+fn test_tm_frame_secondary_header() {
+    let config = TmFrameConfig { has_fecf: false, .. TmFrameConfig::new(16) };
+
+    let mut header = TmFrameHeader::default();
+    header.set_secondary_header_flag(true);
+    header.set_first_header_pointer(TM_FHP_NO_PACKET_START);
+
+    let mut frame_bytes = header.0.to_vec();
+    frame_bytes.extend_from_slice(&[0x03, 0xA1, 0xA2, 0xA3, 1, 2, 3, 4, 5, 6]);
+
+    let frame = TmFrame::parse(&config, &frame_bytes).unwrap();
+    assert_eq!(frame.secondary_header_version(), Some(0));
+    assert_eq!(frame.secondary_header(), Some(&[0xA1, 0xA2, 0xA3][..]));
+    assert_eq!(frame.data_field(), &[1, 2, 3, 4, 5, 6][..]);
+    assert_eq!(frame.first_packet_offset(), None);
+    assert_eq!(frame.ocf(), None);
+    assert_eq!(frame.fecf(), None);
+}
+
+#[test]
+fn test_tm_frame_decoder_spanning_packets() {
+    let mut decoder = TmFrameDecoder::new(TmFrameConfig::new(FRAME_LENGTH));
+
+    // packets of 15, 30 and 10 bytes, followed by an idle packet
+    let first = make_packet(0x10, 9);
+    let second = make_packet(0x11, 24);
+    let third = make_packet(0x12, 4);
+    let idle = make_packet(CCSDS_IDLE_APID, 5);
+
+    let mut stream = Vec::new();
+    stream.extend_from_slice(&first);
+    stream.extend_from_slice(&second);
+    stream.extend_from_slice(&third);
+    stream.extend_from_slice(&idle);
+    assert_eq!(stream.len(), 66);
+
+    // frame 0 starts with the first packet, frame 1 is entirely inside the second packet,
+    // and the third packet starts at byte 5 of frame 2
+    decoder.recv_frame(&make_frame(1, 0, 0, &stream[0..20])).unwrap();
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), first);
+    assert_eq!(decoder.pull_packet(), None);
+
+    decoder.recv_frame(&make_frame(1, 1, TM_FHP_NO_PACKET_START, &stream[20..40])).unwrap();
+    assert_eq!(decoder.pull_packet(), None);
+
+    decoder.recv_frame(&make_frame(1, 2, 5, &stream[40..60])).unwrap();
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), second);
+
+    decoder.recv_frame(&make_frame(1, 3, 6, &stream[60..66])).unwrap();
+    let (vcid, packet) = decoder.pull_packet_with_vcid().unwrap();
+    assert_eq!(vcid, 1);
+    assert_eq!(packet.to_vec(), third);
+
+    // idle packets are dropped
+    assert_eq!(decoder.pull_packet(), None);
+    assert_eq!(decoder.lost_frames, 0);
+    assert_eq!(decoder.virtual_channel_gaps, 0);
+}
+
+#[test]
+fn test_tm_frame_decoder_lost_frame() {
+    let mut decoder = TmFrameDecoder::new(TmFrameConfig::new(FRAME_LENGTH));
+
+    let first = make_packet(0x10, 24);
+    let second = make_packet(0x11, 10);
+
+    let mut stream = Vec::new();
+    stream.extend_from_slice(&first);
+    stream.extend_from_slice(&second);
+
+    decoder.recv_frame(&make_frame(0, 0, 0, &stream[0..20])).unwrap();
+
+    // the frame completing the first packet is lost, so the decoder resynchronizes on
+    // the second packet using the first header pointer
+    decoder.recv_frame(&make_frame(0, 2, 0, &stream[30..46])).unwrap();
+    assert_eq!(decoder.pull_packet().unwrap().to_vec(), second);
+    assert_eq!(decoder.pull_packet(), None);
+
+    assert_eq!(decoder.lost_frames, 1);
+    assert_eq!(decoder.virtual_channel_gaps, 1);
+    assert_eq!(decoder.extractor(0).unwrap().dropped_partial_packets, 1);
+
+    // frames after a gap with no packet start are dropped until a packet starts
+    let mut decoder = TmFrameDecoder::new(TmFrameConfig::new(FRAME_LENGTH));
+    decoder.recv_frame(&make_frame(0, 0, TM_FHP_NO_PACKET_START, &stream[10..30])).unwrap();
+    assert_eq!(decoder.pull_packet(), None);
+
+    let mut corrupted = make_frame(0, 1, 0, &stream[30..46]);
+    corrupted[8] ^= 0x01;
+    assert_eq!(decoder.recv_frame(&corrupted), Err(FrameError::InvalidFecf));
+    assert_eq!(decoder.invalid_frames, 1);
+}
+
+#[test]
+fn test_packet_extractor() {
+    let mut extractor = PacketExtractor::new();
+    extractor.keep_idle_packets = true;
+
+    let idle = make_packet(CCSDS_IDLE_APID, 2);
+    let packet = make_packet(0x20, 3);
+
+    let mut data = idle.clone();
+    data.extend_from_slice(&packet[0..4]);
+    extractor.recv_data(&data, Some(0));
+    assert_eq!(extractor.num_packets(), 1);
+
+    // the pointer disagrees with the length of the partial packet
+    extractor.recv_data(&packet[4..6], Some(1));
+    assert_eq!(extractor.dropped_partial_packets, 1);
+    assert_eq!(extractor.pull_packet().unwrap().to_vec(), idle);
+    assert_eq!(extractor.pull_packet(), None);
+}
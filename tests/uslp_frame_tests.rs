@@ -0,0 +1,201 @@
+extern crate ccsds_primary_header;
+
+mod common;
+
+use ccsds_primary_header::frame::*;
+use ccsds_primary_header::uslp_frame::*;
+use common::make_packet;
+
+
+#[test]
+fn test_uslp_frame_header() {
+    let mut header = UslpFrameHeader::new(0xABCD, 0x2A, 0x5);
+    header.destination = true;
+    header.frame_length = 0x0123 + 1;
+    header.bypass = true;
+    header.ocf_flag = true;
+    header.frame_count_length = 2;
+    header.frame_count = 0x4567;
+
+    let bytes = header.encode().unwrap();
+    assert_eq!(bytes, vec![0xCA, 0xBC, 0xDD, 0x4A, 0x01, 0x23, 0x8A, 0x45, 0x67]);
+    assert_eq!(header.size(), 9);
+
+    let mut frame = bytes.clone();
+    frame.resize(header.frame_length, 0);
+    assert_eq!(UslpFrameHeader::decode(&frame), Ok(header));
+
+    let mut truncated = UslpFrameHeader::new(0xABCD, 0x2A, 0x5);
+    truncated.truncated = true;
+    assert_eq!(truncated.encode().unwrap(), vec![0xCA, 0xBC, 0xD5, 0x4B]);
+    assert_eq!(truncated.size(), USLP_TRUNCATED_HEADER_SIZE_BYTES);
+
+    header.frame_count = 0x10000;
+    assert_eq!(header.encode(), Err(FrameError::InvalidHeader));
+
+    let tfdf_header = TfdfHeader::packet_spanning(Some(0x0102));
+    assert_eq!(tfdf_header.encode(), vec![0x00, 0x01, 0x02]);
+    assert_eq!(TfdfHeader::decode(&[0x00, 0x01, 0x02]), Ok(tfdf_header));
+    assert_eq!(TfdfHeader::new().encode(), vec![0xE0]);
+    assert_eq!(TfdfHeader::decode(&[0x9F]).unwrap().construction_rule, ConstructionRule::StartingSegment);
+    assert_eq!(TfdfHeader::decode(&[0x9F]).unwrap().upid, USLP_UPID_IDLE);
+}
+
+#[test]
+fn test_uslp_variable_length_frames() {
+    let config = UslpFrameConfig::new();
+    let mut encoder = UslpFrameEncoder::new(config);
+    let mut decoder = UslpFrameDecoder::new(config);
+
+    let mut header = UslpFrameHeader::new(0x0042, 3, 2);
+    header.frame_count_length = 1;
+
+    let packet = make_packet(0x10, 20);
+    let frames = encoder.encode_packet(&header, &packet).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].len(), 8 + 1 + packet.len() + 2);
+
+    let frame = UslpFrame::parse(&config, &frames[0]).unwrap();
+    assert_eq!(frame.header().frame_length, frames[0].len());
+    assert_eq!(frame.header().frame_count, 0);
+    assert_eq!(frame.tfdf_header(), TfdfHeader::new());
+    assert_eq!(frame.data_zone(), &packet[..]);
+    assert_eq!(frame.ocf(), None);
+    assert!(frame.fecf().is_some());
+
+    decoder.recv_frame(&frames[0]).unwrap();
+    assert_eq!(decoder.pull_packet_with_ids(), Some((3, 2, packet.as_slice().into())));
+
+    let with_ocf = encoder.encode(&header, &TfdfHeader::new(), &packet, Some(0x01020304)).unwrap();
+    let frame = UslpFrame::parse(&config, &with_ocf).unwrap();
+    assert_eq!(frame.header().frame_count, 1);
+    assert_eq!(frame.ocf(), Some(0x01020304));
+    assert_eq!(frame.clcw().map(|clcw| clcw.vcid()), Some(0));
+    assert_eq!(frame.data_zone(), &packet[..]);
+
+    let mut corrupted = with_ocf.clone();
+    corrupted[12] ^= 0x10;
+    assert_eq!(UslpFrame::parse(&config, &corrupted), Err(FrameError::InvalidFecf));
+    assert_eq!(UslpFrame::parse(&config, &with_ocf[0..20]),
+               Err(FrameError::WrongLength { expected: with_ocf.len(), actual: 20 }));
+}
+
+#[test]
+fn test_uslp_segmented_packets() {
+    let mut config = UslpFrameConfig::new();
+    config.max_frame_length = 32;
+
+    let mut encoder = UslpFrameEncoder::new(config);
+    let mut decoder = UslpFrameDecoder::new(config);
+
+    // 22 bytes of data fit in each frame, after the header, TFDF header and FECF
+    let header = UslpFrameHeader::new(0x0042, 1, 7);
+    let packet = make_packet(0x20, 50);
+    let frames = encoder.encode_packet(&header, &packet).unwrap();
+    assert_eq!(frames.len(), 3);
+    assert!(frames.iter().all(|frame| frame.len() <= 32));
+
+    let rules: Vec<ConstructionRule> = frames.iter()
+        .map(|frame| UslpFrame::parse(&config, frame).unwrap().tfdf_header().construction_rule)
+        .collect();
+    assert_eq!(rules, vec![ConstructionRule::StartingSegment,
+                           ConstructionRule::ContinuingSegment,
+                           ConstructionRule::LastSegment]);
+
+    // a segment of a packet on another MAP does not disturb reassembly
+    let other = make_packet(0x21, 4);
+    let other_header = UslpFrameHeader::new(0x0042, 1, 8);
+    let other_frame = encoder.encode_packet(&other_header, &other).unwrap();
+
+    decoder.recv_frame(&frames[0]).unwrap();
+    decoder.recv_frame(&other_frame[0]).unwrap();
+    decoder.recv_frame(&frames[1]).unwrap();
+    assert_eq!(decoder.pull_packet_with_ids(), Some((1, 8, other.as_slice().into())));
+
+    decoder.recv_frame(&frames[2]).unwrap();
+    assert_eq!(decoder.pull_packet_with_ids(), Some((1, 7, packet.as_slice().into())));
+    assert_eq!(decoder.pull_packet(), None);
+}
+
+#[test]
+fn test_uslp_fixed_length_packet_spanning() {
+    // 8 byte header, 3 byte TFDF header, 20 byte data zone and FECF
+    let config = UslpFrameConfig::fixed(33);
+    let mut encoder = UslpFrameEncoder::new(config);
+    let mut decoder = UslpFrameDecoder::new(config);
+
+    let first = make_packet(0x30, 24);
+    let second = make_packet(0x31, 4);
+    let mut stream = first.clone();
+    stream.extend_from_slice(&second);
+
+    let mut header = UslpFrameHeader::new(0x0042, 4, 0);
+    header.frame_count_length = 1;
+
+    let frame0 = encoder.encode(&header, &TfdfHeader::packet_spanning(Some(0)), &stream[0..20], None).unwrap();
+    let frame1 = encoder.encode(&header, &TfdfHeader::packet_spanning(Some(10)), &stream[20..40], None).unwrap();
+    assert_eq!(frame0.len(), 33);
+
+    assert_eq!(encoder.encode(&header, &TfdfHeader::packet_spanning(None), &stream[0..10], None),
+               Err(FrameError::WrongLength { expected: 33, actual: 23 }));
+
+    let frame = UslpFrame::parse(&config, &frame1).unwrap();
+    assert_eq!(frame.first_packet_offset(), Some(10));
+
+    decoder.recv_frame(&frame0).unwrap();
+    assert_eq!(decoder.pull_packet(), None);
+    decoder.recv_frame(&frame1).unwrap();
+    assert_eq!(decoder.pull_packet(), Some(first.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), Some(second.as_slice().into()));
+    assert_eq!(decoder.pull_packet(), None);
+
+    // a lost frame drops the partial packet
+    let frame2 = encoder.encode(&header, &TfdfHeader::packet_spanning(Some(0)), &first[0..20], None).unwrap();
+    let _lost = encoder.encode(&header, &TfdfHeader::packet_spanning(None), &[0; 20], None).unwrap();
+    let mut end = first[20..].to_vec();
+    end.resize(20, 0);
+    let frame4 = encoder.encode(&header, &TfdfHeader::packet_spanning(None), &end, None).unwrap();
+
+    decoder.recv_frame(&frame2).unwrap();
+    decoder.recv_frame(&frame4).unwrap();
+    assert_eq!(decoder.virtual_channel_gaps, 1);
+    assert_eq!(decoder.pull_packet(), None);
+    assert_eq!(decoder.extractor(4, 0).unwrap().dropped_partial_packets, 1);
+}
+
+#[test]
+fn test_uslp_truncated_frames() {
+    let mut config = UslpFrameConfig::fixed(32);
+    config.truncated_frame_length = Some(16);
+    config.insert_zone_length = 2;
+
+    let mut encoder = UslpFrameEncoder::new(config);
+    encoder.insert_zone = vec![0xA5, 0x5A];
+
+    let mut header = UslpFrameHeader::new(0x0042, 5, 1);
+    header.truncated = true;
+
+    let packet = make_packet(0x40, 1);
+    let frame_bytes = encoder.encode(&header, &TfdfHeader::packet_spanning(Some(0)), &packet, None).unwrap();
+    assert_eq!(frame_bytes.len(), 16);
+    assert_eq!(&frame_bytes[0..4], &[0xC0, 0x04, 0x20, 0xA3]);
+
+    let frame = UslpFrame::parse(&config, &frame_bytes).unwrap();
+    assert!(frame.header().truncated);
+    assert_eq!(frame.header().vcid, 5);
+    assert_eq!(frame.header().map_id, 1);
+    assert_eq!(frame.insert_zone(), None);
+    assert_eq!(frame.data_zone(), &packet[..]);
+
+    assert_eq!(encoder.encode(&header, &TfdfHeader::packet_spanning(Some(0)), &packet, Some(0)),
+               Err(FrameError::InvalidHeader));
+
+    let mut decoder = UslpFrameDecoder::new(config);
+    decoder.recv_frame(&frame_bytes).unwrap();
+    assert_eq!(decoder.pull_packet(), Some(packet.as_slice().into()));
+
+    // without a truncated frame length, truncated frames are not expected
+    let mut untruncated = config;
+    untruncated.truncated_frame_length = None;
+    assert_eq!(UslpFrame::parse(&untruncated, &frame_bytes), Err(FrameError::InvalidHeader));
+}